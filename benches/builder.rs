@@ -0,0 +1,45 @@
+use calculator::{
+	parser::{ast::Builder, Operator, Span},
+	Decimal,
+};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+const EXPRESSIONS: usize = 10_000;
+
+/// Add the elements of `1 + 2 * 3 - 4`.
+fn add_elements(builder: &mut Builder) {
+	let span = Span::default();
+	for (index, operator) in [Operator::Add, Operator::Mul, Operator::Sub]
+		.into_iter()
+		.enumerate()
+	{
+		let value = Decimal::from(index + 1);
+		builder.add_node(value.into(), span).unwrap();
+		builder.add_operator(operator, span).unwrap();
+	}
+	builder.add_node(Decimal::from(4).into(), span).unwrap();
+}
+
+fn new_and_reused(c: &mut Criterion) {
+	c.bench_function("new builder per expression", |b| {
+		b.iter(|| {
+			for _ in 0..EXPRESSIONS {
+				let mut builder = Builder::new();
+				add_elements(&mut builder);
+				black_box(builder.build().unwrap());
+			}
+		})
+	});
+	c.bench_function("reused builder", |b| {
+		b.iter(|| {
+			let mut builder = Builder::new();
+			for _ in 0..EXPRESSIONS {
+				add_elements(&mut builder);
+				black_box(builder.finish().unwrap());
+			}
+		})
+	});
+}
+
+criterion_group!(benches, new_and_reused);
+criterion_main!(benches);
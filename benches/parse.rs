@@ -0,0 +1,46 @@
+use calculator::{evaluate, parser};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+/// Expression using every kind of group and operator.
+const TERM: &str = "(1 + 2.5) * -3 / |4 - 5| ^ 2 + max(1, 2, 3) - sqrt(16)% * 2x + 3!";
+
+fn parse_and_validate(c: &mut Criterion) {
+	let input = vec![TERM; 100].join(" - ");
+	assert!(parser::validate(&input).is_ok());
+	c.bench_function("parse", |b| b.iter(|| parser::parse(black_box(&input))));
+	c.bench_function("validate", |b| {
+		b.iter(|| parser::validate(black_box(&input)))
+	});
+}
+
+fn tokenize_parse_evaluate(c: &mut Criterion) {
+	// 100 tokens: a negation followed by 50 values and 49 operators.
+	let values = (1..=50).map(|value| value.to_string()).collect::<Vec<_>>();
+	let input = format!("-{}", values.join(" + "));
+	assert_eq!(parser::Tokenizer::new(&input).count(), 100);
+	c.bench_function("tokenize 100 tokens", |b| {
+		b.iter(|| parser::Tokenizer::new(black_box(&input)).count())
+	});
+	c.bench_function("parse 100 tokens", |b| {
+		b.iter(|| parser::parse(black_box(&input)))
+	});
+	c.bench_function("evaluate 100 tokens", |b| {
+		b.iter(|| evaluate(black_box(&input)))
+	});
+}
+
+fn nested(c: &mut Criterion) {
+	// `((((1 + 2) * 3) / 4) - 5)` nested in place of the `1` until there are 201 nodes
+	let mut input = "1".to_string();
+	for _ in 0..25 {
+		input = format!("(((({input} + 2) * 3) / 4) - 5)");
+	}
+	let node = parser::parse(&input).unwrap();
+	assert_eq!(node.count_values() + node.count_ops(), 201);
+	c.bench_function("evaluate nested", |b| {
+		b.iter(|| evaluate(black_box(&input)))
+	});
+}
+
+criterion_group!(benches, parse_and_validate, tokenize_parse_evaluate, nested);
+criterion_main!(benches);
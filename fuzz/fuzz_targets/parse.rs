@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+	// Any input must be rejected gracefully, only panics are failures
+	let input = String::from_utf8_lossy(data);
+	let parsed = calculator::parse(&input);
+	// Validation is a shortcut for parsing, so they have to agree
+	assert_eq!(calculator::parser::validate(&input).is_ok(), parsed.is_ok());
+});
@@ -1,15 +1,60 @@
-use crate::parser;
+use crate::{
+	engine::{self, Context},
+	parser::{self, ParseResult},
+};
 use rust_decimal::Decimal;
-use std::{error, fmt, io};
+use std::{
+	env, error, fmt,
+	io::{self, BufRead, Write},
+	path::PathBuf,
+};
+
+mod command;
+mod output;
+#[cfg(feature = "readline")]
+mod readline;
+
+/// Maximum nesting depth of an expression, which keeps the recursive evaluation
+/// of pathological expressions from overflowing the stack.
+pub const MAX_DEPTH: usize = 100;
+
+/// Environment variable with the default number of decimal places of results.
+pub const PRECISION_VAR: &str = "CALCULATOR_PRECISION";
+
+pub use self::command::{parse_command, Command, HELP};
+pub use self::output::{
+	format_result, insert_thousands_separator, json_output, to_scientific, FormatOptions,
+	OutputBase, SCIENTIFIC_THRESHOLD,
+};
+#[cfg(feature = "readline")]
+pub use self::readline::history_path;
 
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
 	Input(io::Error),
 	Parse(parser::Error),
-	Math(rust_decimal::Error),
+	Math(engine::Error),
+	Argument(String),
+	MissingArgumentValue(String),
+	InvalidArgumentValue(String, String),
+	UnrepresentableOutput(Decimal),
+	/// The expression is nested deeper than `MAX_DEPTH`
+	ExpressionTooDeep(usize),
+	/// A REPL command, starting with `:`, is not recognized
+	UnknownCommand(String),
 }
 
-impl error::Error for Error {}
+impl error::Error for Error {
+	fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+		match self {
+			Error::Input(e) => Some(e),
+			Error::Parse(e) => Some(e),
+			Error::Math(e) => Some(e),
+			_ => None,
+		}
+	}
+}
 
 impl fmt::Display for Error {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -17,16 +62,875 @@ impl fmt::Display for Error {
 			Error::Input(e) => write!(f, "{e}"),
 			Error::Parse(e) => write!(f, "{e}"),
 			Error::Math(e) => write!(f, "{e}"),
+			Error::Argument(arg) => write!(f, "Error: Unknown argument {arg}"),
+			Error::MissingArgumentValue(arg) => {
+				write!(f, "Error: Missing value for argument {arg}")
+			}
+			Error::InvalidArgumentValue(arg, value) => {
+				write!(f, "Error: Invalid value {value} for argument {arg}")
+			}
+			Error::UnrepresentableOutput(value) => {
+				write!(f, "Error: {value} is not a non-negative integer")
+			}
+			Error::ExpressionTooDeep(depth) => {
+				write!(
+					f,
+					"Error: Expression nesting depth {depth} exceeds {MAX_DEPTH}"
+				)
+			}
+			Error::UnknownCommand(command) => write!(f, "Error: Unknown command {command}"),
+		}
+	}
+}
+
+impl PartialEq for Error {
+	/// Input errors cannot be compared, so they are equal if they are of the same kind.
+	fn eq(&self, other: &Self) -> bool {
+		match (self, other) {
+			(Error::Input(lhs), Error::Input(rhs)) => lhs.kind() == rhs.kind(),
+			(Error::Parse(lhs), Error::Parse(rhs)) => lhs == rhs,
+			(Error::Math(lhs), Error::Math(rhs)) => lhs == rhs,
+			(Error::Argument(lhs), Error::Argument(rhs))
+			| (Error::MissingArgumentValue(lhs), Error::MissingArgumentValue(rhs))
+			| (Error::UnknownCommand(lhs), Error::UnknownCommand(rhs)) => lhs == rhs,
+			(
+				Error::InvalidArgumentValue(lhs_arg, lhs_value),
+				Error::InvalidArgumentValue(rhs_arg, rhs_value),
+			) => lhs_arg == rhs_arg && lhs_value == rhs_value,
+			(Error::UnrepresentableOutput(lhs), Error::UnrepresentableOutput(rhs)) => lhs == rhs,
+			(Error::ExpressionTooDeep(lhs), Error::ExpressionTooDeep(rhs)) => lhs == rhs,
+			// Variants are listed instead of a wildcard, so new ones are not unequal by accident
+			(
+				Error::Input(_)
+				| Error::Parse(_)
+				| Error::Math(_)
+				| Error::Argument(_)
+				| Error::MissingArgumentValue(_)
+				| Error::InvalidArgumentValue(..)
+				| Error::UnrepresentableOutput(_)
+				| Error::ExpressionTooDeep(_)
+				| Error::UnknownCommand(_),
+				_,
+			) => false,
+		}
+	}
+}
+
+impl From<Error> for io::Error {
+	/// Input errors are unwrapped and the rest are caused by invalid input.
+	fn from(error: Error) -> Self {
+		match error {
+			Error::Input(e) => e,
+			error => io::Error::new(io::ErrorKind::InvalidInput, error),
+		}
+	}
+}
+
+impl Error {
+	/// Determine whether the REPL can continue after the error. Only closed input
+	/// and output streams are fatal, while invalid expressions and lines are not.
+	pub fn is_recoverable(&self) -> bool {
+		!matches!(
+			self,
+			Error::Input(e)
+				if matches!(e.kind(), io::ErrorKind::BrokenPipe | io::ErrorKind::UnexpectedEof)
+		)
+	}
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+/// Command line options.
+pub struct Options {
+	/// Record evaluation steps
+	pub trace: bool,
+	/// Evaluate expressions from a file instead of standard input
+	pub file: Option<PathBuf>,
+	/// Result formatting
+	pub format: FormatOptions,
+	/// Number system of the results
+	pub base: OutputBase,
+	/// Print the syntax tree as a GraphViz DOT graph instead of the result
+	pub dump_ast: bool,
+	/// Print the syntax tree as an S-expression instead of the result
+	pub dump_sexpr: bool,
+	/// Read expressions in reverse Polish notation
+	pub rpn: bool,
+	/// Print results and errors as JSON lines
+	pub json: bool,
+	/// Only check the syntax of expressions without evaluating them
+	pub check: bool,
+	/// Highlight results and errors with `colorize_result`
+	pub color: bool,
+	/// Write prompts and the startup banner, or only the results if `false`.
+	/// Unless set, it depends on whether the input is a terminal.
+	pub interactive: Option<bool>,
+}
+
+impl Options {
+	/// Parse command line arguments, excluding the executable name.
+	pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, Error> {
+		Self::parse_with_precision(args, None)
+	}
+
+	/// Parse command line arguments like `parse`, with the precision
+	/// defaulting to the value of the `PRECISION_VAR` environment variable.
+	pub fn from_env(args: impl IntoIterator<Item = String>) -> Result<Self, Error> {
+		Self::parse_with_precision(args, env::var(PRECISION_VAR).ok())
+	}
+
+	fn parse_with_precision(
+		args: impl IntoIterator<Item = String>,
+		precision: Option<String>,
+	) -> Result<Self, Error> {
+		let mut options = Self::default();
+		if let Some(value) = precision {
+			match value.parse() {
+				Ok(precision) => options.format.precision = Some(precision),
+				Err(_) => return Err(Error::InvalidArgumentValue(PRECISION_VAR.into(), value)),
+			}
+		}
+		let mut args = args.into_iter();
+		while let Some(arg) = args.next() {
+			match arg.as_str() {
+				"--trace" | "--step" => options.trace = true,
+				"--file" | "-f" => match args.next() {
+					Some(path) => options.file = Some(path.into()),
+					None => return Err(Error::MissingArgumentValue(arg)),
+				},
+				"--precision" => match args.next() {
+					Some(value) => match value.parse() {
+						Ok(precision) => options.format.precision = Some(precision),
+						Err(_) => return Err(Error::InvalidArgumentValue(arg, value)),
+					},
+					None => return Err(Error::MissingArgumentValue(arg)),
+				},
+				"--strip-zeros" => options.format.strip_trailing_zeros = true,
+				"--scientific" => options.format.use_scientific = true,
+				"--no-scientific" => options.format.plain = true,
+				"--thousands-sep" => match args.next().as_deref() {
+					Some("comma") => options.format.thousands_separator = Some(','),
+					Some("underscore") => options.format.thousands_separator = Some('_'),
+					Some("space") => options.format.thousands_separator = Some(' '),
+					Some(value) => return Err(Error::InvalidArgumentValue(arg, value.to_string())),
+					None => return Err(Error::MissingArgumentValue(arg)),
+				},
+				"--output-hex" => options.base = OutputBase::Hex,
+				"--output-bin" => options.base = OutputBase::Binary,
+				"--output-oct" => options.base = OutputBase::Octal,
+				"--dump-ast" => options.dump_ast = true,
+				"--dump-ast-sexpr" => options.dump_sexpr = true,
+				"--rpn" => options.rpn = true,
+				"--json" => options.json = true,
+				"--check" => options.check = true,
+				"--interactive" => options.interactive = Some(true),
+				"--non-interactive" => options.interactive = Some(false),
+				_ => return Err(Error::Argument(arg)),
+			}
+		}
+		Ok(options)
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A successful calculation.
+pub struct Calculation {
+	/// Name of the variable the value was assigned to
+	pub name: Option<String>,
+	/// Result of the expression
+	pub value: Decimal,
+	/// Result formatted for output
+	pub output: String,
+	/// Evaluation steps, only recorded in trace mode
+	pub steps: Vec<String>,
+	/// Syntax tree as a GraphViz DOT graph, only generated when dumping the AST
+	pub ast: Option<String>,
+}
+
+impl fmt::Display for Calculation {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if let Some(ast) = &self.ast {
+			// Nothing else, so that the output can be piped to GraphViz
+			return write!(f, "{ast}");
+		}
+		for step in &self.steps {
+			writeln!(f, "{step}")?;
+		}
+		match &self.name {
+			Some(name) => write!(f, "{name} = {}", self.output),
+			None => write!(f, "{}", self.output),
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+/// Read-eval-print loop, keeping the context between calculations.
+pub struct Repl {
+	pub context: Context,
+	pub options: Options,
+	/// Shown before reading each expression, `PROMPT` by default
+	pub prompt: String,
+}
+
+impl Default for Repl {
+	fn default() -> Self {
+		Self::new(Options::default())
+	}
+}
+
+impl Repl {
+	pub fn new(options: Options) -> Self {
+		Self {
+			context: Context::new(),
+			options,
+			prompt: PROMPT.to_string(),
+		}
+	}
+
+	/// Evaluate arithmetic expressions until the input is exhausted:
+	/// 1. Read a line of user input
+	/// 2. Parse the input and generate an abstract syntax tree (AST)
+	/// 3. Evaluate the AST and write the numeric result or the error
+	///
+	/// Lines ending with a backslash continue on the next line.
+	/// Lines starting with `:` are commands, such as `:help` and `:quit`.
+	pub fn run<R: BufRead, W: Write>(&mut self, mut reader: R, mut writer: W) -> io::Result<()> {
+		let mut buffer = String::new();
+		self.write_prompt(&mut writer)?;
+		loop {
+			match reader.read_line(&mut buffer) {
+				Ok(0) => break,
+				Ok(_) => {}
+				// Lines which are not valid UTF-8 are skipped. Other errors would
+				// most likely repeat on the next read, such as reading a directory.
+				Err(error) if error.kind() == io::ErrorKind::InvalidData => {
+					let error = Error::Input(error);
+					self.write_output(&mut writer, &format!("Error: {error}"))?;
+					self.write_prompt(&mut writer)?;
+					continue;
+				}
+				Err(error) => return Err(error),
+			}
+			match self.process(&mut buffer, &mut writer)? {
+				Status::Continued if self.is_interactive() => {
+					write!(writer, "{CONTINUATION_PROMPT}")?;
+					writer.flush()?;
+				}
+				Status::Continued => {}
+				Status::Done => self.write_prompt(&mut writer)?,
+				Status::Quit => return Ok(()),
+			}
+		}
+		self.finish(&buffer, &mut writer)
+	}
+
+	/// Handle the line at the end of the buffer. If it is continued on the next line,
+	/// the buffer is kept for it.
+	fn process<W: Write>(&mut self, buffer: &mut String, writer: &mut W) -> io::Result<Status> {
+		if let Some(line) = buffer.trim_end().strip_suffix('\\') {
+			buffer.truncate(line.len());
+			buffer.push(' ');
+			return Ok(Status::Continued);
+		}
+		let line = buffer.trim();
+		let status = if line.starts_with(':') {
+			match parse_command(line) {
+				Some(command) => self.command(command, writer)?,
+				None => {
+					let error = Error::UnknownCommand(line.to_string());
+					self.write_output(writer, &error.to_string())?;
+					Status::Done
+				}
+			}
+		} else {
+			self.calculate(buffer, writer)?;
+			Status::Done
+		};
+		buffer.clear();
+		Ok(status)
+	}
+
+	/// Handle the rest of the buffer once the input ends, which may end with a continued line.
+	fn finish<W: Write>(&mut self, buffer: &str, writer: &mut W) -> io::Result<()> {
+		if !buffer.trim().is_empty() {
+			self.calculate(buffer, writer)?;
+		}
+		Ok(())
+	}
+
+	/// Prompts are left out of JSON output, which is meant for other programs,
+	/// and out of non-interactive output.
+	fn write_prompt<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+		if self.options.json || !self.is_interactive() {
+			return Ok(());
+		}
+		write!(writer, "{}", self.prompt)?;
+		writer.flush()
+	}
+
+	/// Interactive mode is only turned off explicitly, since the REPL cannot tell
+	/// where its input comes from.
+	fn is_interactive(&self) -> bool {
+		self.options.interactive != Some(false)
+	}
+
+	/// Write a result or an error, followed by a blank line in interactive mode
+	/// to set it apart from the next prompt.
+	fn write_output<W: Write>(&self, writer: &mut W, output: &str) -> io::Result<()> {
+		if self.is_interactive() {
+			writeln!(writer, "{output}\n")
+		} else {
+			writeln!(writer, "{output}")
+		}
+	}
+
+	fn command<W: Write>(&mut self, command: Command, writer: &mut W) -> io::Result<Status> {
+		match command {
+			Command::Help => writeln!(writer, "{HELP}")?,
+			Command::Quit => return Ok(Status::Quit),
+			Command::Precision(precision) => self.options.format.precision = precision,
+			Command::Vars => {
+				for (name, value) in &self.context.variables {
+					writeln!(writer, "{name} = {value}")?;
+				}
+				if let Some(value) = self.context.last_result {
+					writeln!(writer, "ans = {value}")?;
+				}
+				writeln!(writer)?;
+			}
+			Command::SetPrompt(prompt) => self.prompt = prompt,
+		}
+		Ok(Status::Done)
+	}
+
+	fn calculate<W: Write>(&mut self, input: &str, writer: &mut W) -> io::Result<()> {
+		let result = try_calculate_str(input, &mut self.context, &self.options);
+		if self.options.json {
+			let result = result.map(|calculation| calculation.value);
+			return writeln!(writer, "{}", json_output(input.trim(), result));
+		}
+		let (output, is_error) = match result {
+			Ok(result) => (result.to_string(), false),
+			Err(error) => (error.to_string(), true),
+		};
+		if self.options.color {
+			self.write_output(writer, &colorize_result(&output, is_error))
+		} else {
+			self.write_output(writer, &output)
+		}
+	}
+}
+
+/// Show results in green and errors in red with ANSI escape codes
+/// if the `colored` feature is enabled.
+pub fn colorize_result(s: &str, is_error: bool) -> String {
+	if !cfg!(feature = "colored") {
+		return s.to_string();
+	}
+	let color = if is_error { 31 } else { 32 };
+	format!("\x1b[{color}m{s}\x1b[0m")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// What the REPL does after processing a line.
+enum Status {
+	/// Read the next expression
+	Done,
+	/// Read the rest of the expression on the next line
+	Continued,
+	/// Stop reading
+	Quit,
+}
+
+/// Default prompt shown before reading an expression.
+pub const PROMPT: &str = "> ";
+
+/// Prompt shown before continued lines of an expression.
+pub const CONTINUATION_PROMPT: &str = "... ";
+
+/// Evaluate an arithmetic expression or a variable assignment from a string
+/// and store the result in the context on success.
+pub fn try_calculate_str(
+	input: &str,
+	context: &mut Context,
+	options: &Options,
+) -> Result<Calculation, Error> {
+	let statement = if options.rpn {
+		// Assignments have no postfix form
+		parser::parse_rpn_with_context(input, context).map(ParseResult::Expression)
+	} else {
+		parser::parse_statement(input, context)
+	};
+	let (name, node) = match statement.map_err(Error::Parse)? {
+		ParseResult::Expression(node) => (None, node),
+		ParseResult::Assignment { name, value } => (Some(name), value),
+	};
+	let depth = node.depth();
+	if depth > MAX_DEPTH {
+		return Err(Error::ExpressionTooDeep(depth));
+	}
+	let ast = if options.dump_ast {
+		Some(engine::to_dot(&node))
+	} else {
+		options.dump_sexpr.then(|| engine::to_sexpr(&node))
+	};
+	let (value, steps) = if options.trace {
+		engine::evaluate_traced(node).map_err(Error::Math)?
+	} else {
+		// Folding is skipped in trace mode since it would hide the steps
+		#[cfg(feature = "optimize")]
+		let node = engine::fold_constants(node);
+		(node.try_into().map_err(Error::Math)?, Vec::new())
+	};
+	let output = match options.base {
+		OutputBase::Decimal => options.format.format(value),
+		base => format_result(value, base)?,
+	};
+	if let Some(name) = &name {
+		context.set(name.clone(), value);
+	}
+	context.last_result = Some(value);
+	Ok(Calculation {
+		name,
+		value,
+		output,
+		steps,
+		ast,
+	})
+}
+
+/// Evaluate each line of the input as a separate expression, in order and
+/// sharing the context, skipping empty lines and lines beginning with `#`.
+/// Results are paired with their line numbers, starting at one.
+pub fn calculate_lines(input: &str, options: &Options) -> Vec<(usize, Result<Calculation, Error>)> {
+	let mut context = Context::new();
+	input
+		.lines()
+		.enumerate()
+		.filter(|(_, line)| {
+			let line = line.trim();
+			!line.is_empty() && !line.starts_with('#')
+		})
+		.map(|(index, line)| (index + 1, try_calculate_str(line, &mut context, options)))
+		.collect()
+}
+
+/// Parse each line of the input without evaluating it and write `OK` or the error,
+/// paired with the line number like `calculate_lines` and skipping the same lines.
+/// Variables are only defined by preceding assignments, and the last result
+/// (`ans` or `_`) by any preceding valid line, with placeholder values.
+/// Returns whether every line is valid.
+pub fn check_all(reader: impl BufRead, mut writer: impl Write) -> io::Result<bool> {
+	let mut context = Context::new();
+	let mut valid = true;
+	for (index, line) in reader.lines().enumerate() {
+		let line = line?;
+		let line = line.trim();
+		if line.is_empty() || line.starts_with('#') {
+			continue;
+		}
+		match parser::parse_statement(line, &context) {
+			Ok(statement) => {
+				if let ParseResult::Assignment { name, .. } = statement {
+					context.set(name, Decimal::ZERO);
+				}
+				context.last_result = Some(Decimal::ZERO);
+				writeln!(writer, "line {}: OK", index + 1)?;
+			}
+			Err(error) => {
+				valid = false;
+				writeln!(writer, "line {}: {error}", index + 1)?;
+			}
 		}
 	}
+	Ok(valid)
 }
 
-/// Evaluate an arithmetic expression:
-/// 1. Read user input
-/// 2. Parse the input and generate an abstract syntax tree (AST)
-/// 3. Evaluate the AST and return a numeric result
-pub fn try_calculate(buffer: &mut String) -> Result<Decimal, Error> {
-	io::stdin().read_line(buffer).map_err(Error::Input)?;
-	let root_node = parser::parse(buffer).map_err(Error::Parse)?;
-	root_node.try_into().map_err(Error::Math)
+#[cfg(test)]
+mod tests {
+	use super::{
+		calculate_lines, check_all, colorize_result, try_calculate_str, Error, FormatOptions,
+		Options, OutputBase, Repl, MAX_DEPTH, PRECISION_VAR,
+	};
+	use crate::{
+		engine::{self, Context},
+		parser,
+	};
+	use rust_decimal::Decimal;
+	use std::{error, io};
+
+	#[test]
+	fn last_result() {
+		let mut context = Context::new();
+		assert_eq!(
+			try_calculate_str("1 + 1", &mut context, &Options::default())
+				.unwrap()
+				.value,
+			Decimal::TWO
+		);
+		assert_eq!(
+			try_calculate_str("ans * 5", &mut context, &Options::default())
+				.unwrap()
+				.value,
+			Decimal::TEN
+		);
+		assert_eq!(context.last_result, Some(Decimal::TEN));
+	}
+
+	#[test]
+	fn last_result_before_calculation() {
+		let mut context = Context::new();
+		assert_eq!(
+			try_calculate_str("ans * 2", &mut context, &Options::default()).map(|_| ()),
+			Err(Error::Parse(parser::Error::UndefinedVariable(
+				"ans".to_string(),
+				parser::Span::new(0, 3)
+			)))
+		);
+	}
+
+	#[test]
+	fn failed_calculation_keeps_last_result() {
+		let mut context = Context::new();
+		try_calculate_str("2", &mut context, &Options::default()).unwrap();
+		assert!(try_calculate_str("ans / 0", &mut context, &Options::default()).is_err());
+		assert_eq!(context.last_result, Some(Decimal::TWO));
+	}
+
+	#[test]
+	fn assignment() {
+		let mut context = Context::new();
+		let calculation =
+			try_calculate_str("let a = 5", &mut context, &Options::default()).unwrap();
+		assert_eq!(calculation.name.as_deref(), Some("a"));
+		assert_eq!(calculation.value, Decimal::new(5, 0));
+		assert_eq!(calculation.to_string(), "a = 5");
+		assert_eq!(
+			try_calculate_str("a + 3", &mut context, &Options::default())
+				.unwrap()
+				.value,
+			Decimal::new(8, 0)
+		);
+	}
+
+	#[test]
+	fn use_before_assignment() {
+		let mut context = Context::new();
+		assert!(matches!(
+			try_calculate_str("b + 3", &mut context, &Options::default()),
+			Err(Error::Parse(parser::Error::UndefinedVariable(..)))
+		));
+		try_calculate_str("let b = 1", &mut context, &Options::default()).unwrap();
+		assert!(try_calculate_str("b + 3", &mut context, &Options::default()).is_ok());
+	}
+
+	#[test]
+	fn trace() {
+		let options = Options {
+			trace: true,
+			..Options::default()
+		};
+		let calculation =
+			try_calculate_str("let x = -(1 + 2)", &mut Context::new(), &options).unwrap();
+		assert_eq!(calculation.steps, ["Add(1, 2) → 3", "Neg(3) → -3"]);
+		assert_eq!(
+			calculation.to_string(),
+			"Add(1, 2) → 3\nNeg(3) → -3\nx = -3"
+		);
+	}
+
+	#[test]
+	fn parse_options() {
+		let options = Options::parse(["--trace".to_string()]).unwrap();
+		assert!(options.trace);
+		let options = Options::parse(["--step".to_string()]).unwrap();
+		assert!(options.trace);
+		let options = Options::parse(["-f".to_string(), "input.txt".to_string()]).unwrap();
+		assert_eq!(options.file, Some("input.txt".into()));
+		assert_eq!(
+			Options::parse(["--file".to_string()]),
+			Err(Error::MissingArgumentValue("--file".to_string()))
+		);
+		let options =
+			Options::parse(["--precision", "2", "--strip-zeros", "--scientific"].map(String::from))
+				.unwrap();
+		assert_eq!(
+			options.format,
+			FormatOptions {
+				precision: Some(2),
+				strip_trailing_zeros: true,
+				use_scientific: true,
+				plain: false,
+				thousands_separator: None,
+			}
+		);
+		let options = Options::parse(["--thousands-sep", "underscore"].map(String::from));
+		assert_eq!(options.unwrap().format.thousands_separator, Some('_'));
+		assert_eq!(
+			Options::parse(["--thousands-sep", "dot"].map(String::from)),
+			Err(Error::InvalidArgumentValue(
+				"--thousands-sep".to_string(),
+				"dot".to_string()
+			))
+		);
+		let options = Options::parse_with_precision([], Some("3".to_string())).unwrap();
+		assert_eq!(options.format.precision, Some(3));
+		let options =
+			Options::parse_with_precision(["--precision", "1"].map(String::from), Some("3".into()));
+		assert_eq!(options.unwrap().format.precision, Some(1));
+		assert_eq!(
+			Options::parse_with_precision([], Some("-1".to_string())),
+			Err(Error::InvalidArgumentValue(
+				PRECISION_VAR.to_string(),
+				"-1".to_string()
+			))
+		);
+		assert!(
+			Options::parse(["--no-scientific".to_string()])
+				.unwrap()
+				.format
+				.plain
+		);
+		assert_eq!(
+			Options::parse(["--precision", "two"].map(String::from)),
+			Err(Error::InvalidArgumentValue(
+				"--precision".to_string(),
+				"two".to_string()
+			))
+		);
+		assert!(Options::parse(["--dump-ast".to_string()]).unwrap().dump_ast);
+		assert!(
+			Options::parse(["--dump-ast-sexpr".to_string()])
+				.unwrap()
+				.dump_sexpr
+		);
+		assert!(Options::parse(["--rpn".to_string()]).unwrap().rpn);
+		assert!(Options::parse(["--json".to_string()]).unwrap().json);
+		assert_eq!(
+			Options::parse(["--interactive".to_string()])
+				.unwrap()
+				.interactive,
+			Some(true)
+		);
+		assert_eq!(
+			Options::parse(["--non-interactive".to_string()])
+				.unwrap()
+				.interactive,
+			Some(false)
+		);
+		assert_eq!(Options::parse([]).unwrap(), Options::default());
+		assert_eq!(
+			Options::parse(["--foo".to_string()]),
+			Err(Error::Argument("--foo".to_string()))
+		);
+	}
+
+	#[test]
+	fn lines() {
+		let results = calculate_lines(
+			"# comment\n1 + 1\n\nlet x = 3\n1 / 0\n  # indented comment\nx * 2\n",
+			&Options::default(),
+		);
+		let lines: Vec<usize> = results.iter().map(|(line, _)| *line).collect();
+		assert_eq!(lines, [2, 4, 5, 7]);
+		assert_eq!(results[0].1.as_ref().unwrap().value, Decimal::TWO);
+		assert!(results[2].1.is_err());
+		assert_eq!(results[3].1.as_ref().unwrap().value, Decimal::new(6, 0));
+	}
+
+	#[test]
+	fn formatted_output() {
+		let mut context = Context::new();
+		let options = Options {
+			format: FormatOptions {
+				precision: Some(2),
+				..FormatOptions::default()
+			},
+			..Options::default()
+		};
+		let calculation = try_calculate_str("let x = 2 / 3", &mut context, &options).unwrap();
+		assert_eq!(calculation.to_string(), "x = 0.67");
+		assert_eq!(calculation.value, context.get("x").unwrap());
+		assert!(calculation.value != Decimal::new(67, 2));
+	}
+
+	#[test]
+	fn output_base() {
+		let options = Options::parse(["--output-hex".to_string()]).unwrap();
+		assert_eq!(options.base, OutputBase::Hex);
+		let calculation = try_calculate_str("0xFF + 1", &mut Context::new(), &options).unwrap();
+		assert_eq!(calculation.to_string(), "0x100");
+		let mut context = Context::new();
+		assert!(matches!(
+			try_calculate_str("1 / 2", &mut context, &options),
+			Err(Error::UnrepresentableOutput(_))
+		));
+		assert_eq!(context.last_result, None);
+	}
+
+	#[test]
+	fn dump_ast() {
+		let options = Options {
+			dump_ast: true,
+			..Options::default()
+		};
+		let calculation = try_calculate_str("1 + 2", &mut Context::new(), &options).unwrap();
+		assert_eq!(calculation.value, Decimal::new(3, 0));
+		assert!(calculation.to_string().starts_with("digraph ast {"));
+		assert!(calculation.to_string().ends_with('}'));
+
+		let options = Options {
+			dump_sexpr: true,
+			..Options::default()
+		};
+		let calculation = try_calculate_str("1 + 2", &mut Context::new(), &options).unwrap();
+		assert_eq!(calculation.to_string(), "(+ 1 2)");
+	}
+
+	#[test]
+	fn error_source() {
+		let error =
+			try_calculate_str("1 + 1.2.3", &mut Context::new(), &Options::default()).unwrap_err();
+		let source = error::Error::source(&error).unwrap();
+		assert!(source.is::<parser::Error>());
+		assert!(source.source().is_some());
+		let error =
+			try_calculate_str("1 / 0", &mut Context::new(), &Options::default()).unwrap_err();
+		assert!(error::Error::source(&error).unwrap().is::<engine::Error>());
+	}
+
+	#[test]
+	fn rpn() {
+		let options = Options {
+			rpn: true,
+			..Options::default()
+		};
+		let mut context = Context::new();
+		let calculation = try_calculate_str("3 4 + 2 *", &mut context, &options).unwrap();
+		assert_eq!(calculation.value, Decimal::new(14, 0));
+		let calculation = try_calculate_str("ans 2 /", &mut context, &options).unwrap();
+		assert_eq!(calculation.value, Decimal::new(7, 0));
+		assert!(matches!(
+			try_calculate_str("let x = 1", &mut context, &options),
+			Err(Error::Parse(_))
+		));
+	}
+
+	#[test]
+	fn expression_too_deep() {
+		let mut context = Context::new();
+		let nested = |depth| "-".repeat(depth) + "1";
+		assert!(try_calculate_str(&nested(MAX_DEPTH), &mut context, &Options::default()).is_ok());
+		assert_eq!(
+			try_calculate_str(&nested(MAX_DEPTH + 1), &mut context, &Options::default())
+				.map(|_| ()),
+			Err(Error::ExpressionTooDeep(MAX_DEPTH + 1))
+		);
+	}
+
+	#[test]
+	fn color() {
+		let mut repl = Repl::default();
+		let mut output = Vec::new();
+		repl.run(io::Cursor::new("1 + 1\n1 / 0\n"), &mut output)
+			.unwrap();
+		assert!(!String::from_utf8(output).unwrap().contains('\x1b'));
+
+		repl.options.color = true;
+		let mut output = Vec::new();
+		repl.run(io::Cursor::new("1 + 1\n1 / 0\n"), &mut output)
+			.unwrap();
+		let output = String::from_utf8(output).unwrap();
+		if cfg!(feature = "colored") {
+			assert_eq!(
+				output,
+				"> \x1b[32m2\x1b[0m\n\n> \x1b[31mError: Division by zero\x1b[0m\n\n> "
+			);
+		} else {
+			assert_eq!(output, "> 2\n\n> Error: Division by zero\n\n> ");
+			assert_eq!(colorize_result("2", false), "2");
+		}
+	}
+
+	#[test]
+	fn read_errors() {
+		struct Unreadable;
+
+		impl io::Read for Unreadable {
+			fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+				Err(io::Error::other("Is a directory"))
+			}
+		}
+
+		let mut output = Vec::new();
+		Repl::default()
+			.run(io::Cursor::new(b"\xff\n1 + 1\n"), &mut output)
+			.unwrap();
+		let output = String::from_utf8(output).unwrap();
+		assert!(output.ends_with("\n> 2\n\n> "), "{output:?}");
+		let result = Repl::default().run(io::BufReader::new(Unreadable), io::sink());
+		assert_eq!(result.unwrap_err().kind(), io::ErrorKind::Other);
+	}
+
+	#[test]
+	fn recoverable() {
+		let broken_pipe = Error::Input(io::Error::from(io::ErrorKind::BrokenPipe));
+		assert!(!broken_pipe.is_recoverable());
+		assert!(!Error::Input(io::ErrorKind::UnexpectedEof.into()).is_recoverable());
+		assert!(Error::Input(io::ErrorKind::InvalidData.into()).is_recoverable());
+		assert!(Error::Math(engine::Error::DivisionByZero).is_recoverable());
+		assert!(Error::UnknownCommand(":foo".to_string()).is_recoverable());
+		assert_eq!(
+			io::Error::from(broken_pipe).kind(),
+			io::ErrorKind::BrokenPipe
+		);
+	}
+
+	#[test]
+	fn error_eq() {
+		assert_eq!(
+			Error::Input(io::Error::new(io::ErrorKind::BrokenPipe, "closed")),
+			Error::Input(io::ErrorKind::BrokenPipe.into())
+		);
+		assert_ne!(
+			Error::Input(io::ErrorKind::BrokenPipe.into()),
+			Error::Input(io::ErrorKind::UnexpectedEof.into())
+		);
+		assert_eq!(
+			Error::Math(engine::Error::DivisionByZero),
+			Error::Math(engine::Error::DivisionByZero)
+		);
+		assert_ne!(
+			Error::Argument("--foo".to_string()),
+			Error::UnknownCommand("--foo".to_string())
+		);
+	}
+
+	#[test]
+	fn check() {
+		let mut output = Vec::new();
+		let input = "let x = 1 / 0\n\n# comment\nx + 1\n";
+		assert!(check_all(input.as_bytes(), &mut output).unwrap());
+		assert_eq!(
+			String::from_utf8(output).unwrap(),
+			"line 1: OK\nline 4: OK\n"
+		);
+		let mut output = Vec::new();
+		assert!(!check_all("1 +\ny\n2".as_bytes(), &mut output).unwrap());
+		assert_eq!(
+			String::from_utf8(output).unwrap(),
+			"line 1: Error: '+' is not valid at column 3\n\
+			 line 2: Error: Undefined variable y at column 1\n\
+			 line 3: OK\n"
+		);
+		let mut output = Vec::new();
+		assert!(!check_all("ans\n1 + 1\nans * 2\n_ / 2".as_bytes(), &mut output).unwrap());
+		assert_eq!(
+			String::from_utf8(output).unwrap(),
+			"line 1: Error: Undefined variable ans at column 1\n\
+			 line 2: OK\n\
+			 line 3: OK\n\
+			 line 4: OK\n"
+		);
+		assert!(Options::parse(["--check".to_string()]).unwrap().check);
+	}
 }
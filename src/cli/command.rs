@@ -0,0 +1,115 @@
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+/// REPL meta-command, given on a line starting with `:`.
+pub enum Command {
+	/// `:help` lists operators, functions and commands
+	Help,
+	/// `:quit` or `:exit` ends the REPL
+	Quit,
+	/// `:precision N` rounds results to `N` decimal places, `:precision` stops rounding
+	Precision(Option<u32>),
+	/// `:vars` lists defined variables
+	Vars,
+	/// `:set prompt "<prompt>"` changes the prompt
+	SetPrompt(String),
+}
+
+/// Recognize a meta-command. Returns `None` for expressions and unknown commands.
+pub fn parse_command(input: &str) -> Option<Command> {
+	let command = input.trim().strip_prefix(':')?;
+	let (name, argument) = command
+		.split_once(char::is_whitespace)
+		.map_or((command, ""), |(name, argument)| (name, argument.trim()));
+	match (name, argument) {
+		("help", "") => Some(Command::Help),
+		("quit" | "exit", "") => Some(Command::Quit),
+		("vars", "") => Some(Command::Vars),
+		("precision", "") => Some(Command::Precision(None)),
+		("precision", precision) => precision.parse().ok().map(Some).map(Command::Precision),
+		("set", setting) => match setting.split_once(char::is_whitespace) {
+			Some(("prompt", value)) => {
+				let value = value.trim();
+				// Quotes allow a prompt to end with whitespace
+				let value = value
+					.strip_prefix('"')
+					.and_then(|value| value.strip_suffix('"'))
+					.unwrap_or(value);
+				Some(Command::SetPrompt(value.to_string()))
+			}
+			_ => None,
+		},
+		_ => None,
+	}
+}
+
+/// Text printed by `:help`.
+pub const HELP: &str = "\
+Operators, from the loosest to the tightest binding:
+  == != < <= > >=  comparisons
+  &                bitwise and
+  + -              addition and subtraction
+  * /              multiplication and division
+  - ~              negation and bitwise not
+  ^                power
+  % !              percentage and factorial
+  |x|              absolute value
+
+Functions:
+  sqrt(x)  floor(x)  ceil(x)  round(x, places)  trunc(x[, places])
+  sign(x) or sgn(x)  ln(x)  log(base, x)  rem(x, y)  gcd(x, y)  lcm(x, y)
+  bitor(x, y)  bitxor(x, y)  min(x, y, ...)  max(x, y, ...)  clamp(x, min, max)
+
+Variables:
+  let x = 1 + 2    assign a variable
+  ans              result of the last calculation
+
+Commands:
+  :help                 show this help
+  :vars                 list variables
+  :precision N          round results to N decimal places
+  :set prompt \"> \"      change the prompt
+  :quit, :exit          leave
+";
+
+#[cfg(test)]
+mod tests {
+	use super::{parse_command, Command, HELP};
+	use crate::parser::FUNCTION_NAMES;
+
+	#[test]
+	fn commands() {
+		assert_eq!(parse_command(":help"), Some(Command::Help));
+		assert_eq!(parse_command(" :quit \n"), Some(Command::Quit));
+		assert_eq!(parse_command(":exit"), Some(Command::Quit));
+		assert_eq!(parse_command(":vars"), Some(Command::Vars));
+		assert_eq!(
+			parse_command(":precision 3"),
+			Some(Command::Precision(Some(3)))
+		);
+		assert_eq!(parse_command(":precision"), Some(Command::Precision(None)));
+		assert_eq!(
+			parse_command(":set prompt \">>> \""),
+			Some(Command::SetPrompt(">>> ".to_string()))
+		);
+		for input in [
+			"help",
+			"1 + 1",
+			":foo",
+			":help me",
+			":precision x",
+			":set foo 1",
+		] {
+			assert_eq!(parse_command(input), None, "{input}");
+		}
+	}
+
+	#[test]
+	fn help() {
+		for name in FUNCTION_NAMES {
+			assert!(HELP.contains(&format!("{name}(")), "{name}");
+		}
+		for keyword in ["^", "Commands", ":quit", ":vars", ":precision"] {
+			assert!(HELP.contains(keyword), "{keyword}");
+		}
+	}
+}
@@ -0,0 +1,351 @@
+use super::Error;
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+
+/// Largest exponent magnitude still printed in plain notation by `to_scientific`.
+pub const SCIENTIFIC_THRESHOLD: i64 = 6;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+/// Options controlling how results are printed.
+pub struct FormatOptions {
+	/// Number of decimal places to round to
+	pub precision: Option<u32>,
+	/// Remove insignificant zeros after the decimal point
+	pub strip_trailing_zeros: bool,
+	/// Print in scientific notation, such as `1.23e4`
+	pub use_scientific: bool,
+	/// Print very large and very small values in plain notation too,
+	/// instead of switching to scientific notation with `to_scientific`
+	pub plain: bool,
+	/// Group the integer digits of values in plain notation by thousands,
+	/// see `insert_thousands_separator`
+	pub thousands_separator: Option<char>,
+}
+
+impl FormatOptions {
+	/// Format a value according to the options.
+	/// Rounding is applied first, then zero stripping and finally the notation.
+	pub fn format(&self, value: Decimal) -> String {
+		let output = self.format_notation(value);
+		match self.thousands_separator {
+			Some(separator) if !output.contains('e') => {
+				insert_thousands_separator(&output, separator)
+			}
+			_ => output,
+		}
+	}
+
+	fn format_notation(&self, value: Decimal) -> String {
+		let mut value = value;
+		if let Some(precision) = self.precision {
+			value = value.round_dp(precision);
+		}
+		if self.strip_trailing_zeros {
+			value = value.normalize();
+		}
+		if !self.use_scientific && self.plain {
+			value.to_string()
+		} else if !self.use_scientific {
+			to_scientific(&value)
+		} else if value.is_zero() {
+			"0e0".to_string()
+		} else {
+			let scientific = format!("{value:e}");
+			match scientific.split_once('e') {
+				// Integer trailing zeros are part of the mantissa, not the scale
+				Some((mantissa, exponent))
+					if self.strip_trailing_zeros && mantissa.contains('.') =>
+				{
+					let mantissa = mantissa.trim_end_matches('0').trim_end_matches('.');
+					format!("{mantissa}e{exponent}")
+				}
+				_ => scientific,
+			}
+		}
+	}
+}
+
+/// Insert a separator between every three digits of the integer part of a number,
+/// counting from the decimal point. Example: `-1234567.0001` -> `-1,234,567.0001`
+pub fn insert_thousands_separator(s: &str, sep: char) -> String {
+	let (sign, unsigned) = s.split_at(s.find(|c: char| c.is_ascii_digit()).unwrap_or(0));
+	let (integer, fraction) = unsigned.split_at(unsigned.find('.').unwrap_or(unsigned.len()));
+	let mut output = String::with_capacity(s.len() + integer.len() / 3);
+	output.push_str(sign);
+	for (i, digit) in integer.chars().enumerate() {
+		if i > 0 && (integer.len() - i) % 3 == 0 {
+			output.push(sep);
+		}
+		output.push(digit);
+	}
+	output.push_str(fraction);
+	output
+}
+
+/// Format a value in scientific notation with the shortest mantissa, such as `1.23e-9`,
+/// if the magnitude of its exponent exceeds `SCIENTIFIC_THRESHOLD`.
+/// Other values are formatted as usual.
+pub fn to_scientific(value: &Decimal) -> String {
+	let digits = value.mantissa().unsigned_abs().to_string();
+	let exponent = digits.len() as i64 - 1 - i64::from(value.scale());
+	if value.is_zero() || exponent.abs() <= SCIENTIFIC_THRESHOLD {
+		return value.to_string();
+	}
+	let sign = if value.is_sign_negative() { "-" } else { "" };
+	let (first, rest) = digits.trim_end_matches('0').split_at(1);
+	if rest.is_empty() {
+		format!("{sign}{first}e{exponent}")
+	} else {
+		format!("{sign}{first}.{rest}e{exponent}")
+	}
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+/// Number system for printing results.
+pub enum OutputBase {
+	#[default]
+	Decimal,
+	Hex,
+	Binary,
+	Octal,
+}
+
+/// Format a result in the given number system.
+/// Bases other than decimal only support non-negative integers.
+pub fn format_result(value: Decimal, base: OutputBase) -> Result<String, Error> {
+	if base == OutputBase::Decimal {
+		return Ok(value.to_string());
+	}
+	let integer = value
+		.fract()
+		.is_zero()
+		.then(|| value.to_u128())
+		.flatten()
+		.ok_or(Error::UnrepresentableOutput(value))?;
+	Ok(match base {
+		OutputBase::Decimal => integer.to_string(),
+		OutputBase::Hex => format!("0x{integer:X}"),
+		OutputBase::Binary => format!("{integer:#b}"),
+		OutputBase::Octal => format!("{integer:#o}"),
+	})
+}
+
+/// Format an expression and its result or error as a single line JSON object,
+/// such as `{"expression":"1+1","result":"2"}`.
+/// Results are strings so that no precision is lost by JSON number parsers.
+pub fn json_output(expr: &str, result: Result<Decimal, Error>) -> String {
+	let expression = json_string(expr);
+	match result {
+		Ok(value) => format!("{{\"expression\":{expression},\"result\":\"{value}\"}}"),
+		Err(error) => {
+			let error = json_string(&error.to_string());
+			format!("{{\"expression\":{expression},\"error\":{error}}}")
+		}
+	}
+}
+
+/// Quote a string and escape it for JSON.
+fn json_string(value: &str) -> String {
+	let mut output = String::with_capacity(value.len() + 2);
+	output.push('"');
+	for c in value.chars() {
+		match c {
+			'"' => output.push_str("\\\""),
+			'\\' => output.push_str("\\\\"),
+			'\n' => output.push_str("\\n"),
+			'\r' => output.push_str("\\r"),
+			'\t' => output.push_str("\\t"),
+			c if c.is_control() => output.push_str(&format!("\\u{:04x}", c as u32)),
+			c => output.push(c),
+		}
+	}
+	output.push('"');
+	output
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{
+		format_result, insert_thousands_separator, json_output, json_string, to_scientific,
+		FormatOptions, OutputBase,
+	};
+	use crate::cli::Error;
+	use rust_decimal::Decimal;
+
+	#[test]
+	fn default() {
+		assert_eq!(
+			FormatOptions::default().format(Decimal::new(1500, 3)),
+			"1.500"
+		);
+	}
+
+	#[test]
+	fn precision() {
+		let options = FormatOptions {
+			precision: Some(2),
+			..FormatOptions::default()
+		};
+		assert_eq!(options.format(Decimal::new(12346, 3)), "12.35");
+		assert_eq!(options.format(Decimal::new(15, 1)), "1.5");
+	}
+
+	#[test]
+	fn strip_trailing_zeros() {
+		let options = FormatOptions {
+			strip_trailing_zeros: true,
+			..FormatOptions::default()
+		};
+		assert_eq!(options.format(Decimal::new(1500, 3)), "1.5");
+		assert_eq!(options.format(Decimal::new(100, 0)), "100");
+	}
+
+	#[test]
+	fn scientific() {
+		let options = FormatOptions {
+			use_scientific: true,
+			..FormatOptions::default()
+		};
+		assert_eq!(options.format(Decimal::new(12345, 0)), "1.2345e4");
+		assert_eq!(options.format(Decimal::new(-15, 4)), "-1.5e-3");
+		assert_eq!(options.format(Decimal::ZERO), "0e0");
+	}
+
+	#[test]
+	fn combined() {
+		let options = FormatOptions {
+			precision: Some(3),
+			strip_trailing_zeros: true,
+			use_scientific: false,
+			plain: false,
+			thousands_separator: None,
+		};
+		assert_eq!(options.format(Decimal::new(10002, 4)), "1");
+		let options = FormatOptions {
+			use_scientific: true,
+			..options
+		};
+		assert_eq!(options.format(Decimal::new(12300, 0)), "1.23e4");
+		assert_eq!(options.format(Decimal::new(123456, 1)), "1.23456e4");
+		assert_eq!(options.format(Decimal::new(1234567, 4)), "1.23457e2");
+	}
+
+	#[test]
+	fn bases() {
+		let value = Decimal::new(6719, 0);
+		assert_eq!(format_result(value, OutputBase::Decimal).unwrap(), "6719");
+		assert_eq!(format_result(value, OutputBase::Hex).unwrap(), "0x1A3F");
+		assert_eq!(
+			format_result(Decimal::new(22, 0), OutputBase::Binary).unwrap(),
+			"0b10110"
+		);
+		assert_eq!(
+			format_result(Decimal::new(8, 0), OutputBase::Octal).unwrap(),
+			"0o10"
+		);
+		assert_eq!(
+			format_result(Decimal::new(20, 1), OutputBase::Hex).unwrap(),
+			"0x2"
+		);
+	}
+
+	#[test]
+	fn unrepresentable_in_base() {
+		for value in [Decimal::new(15, 1), Decimal::NEGATIVE_ONE] {
+			assert!(matches!(
+				format_result(value, OutputBase::Hex),
+				Err(Error::UnrepresentableOutput(v)) if v == value
+			));
+		}
+	}
+
+	#[test]
+	fn json() {
+		assert_eq!(
+			json_output("1+1", Ok(Decimal::TWO)),
+			r#"{"expression":"1+1","result":"2"}"#
+		);
+		assert_eq!(
+			json_output(
+				"1 / 0",
+				Err(Error::Math(crate::engine::Error::DivisionByZero))
+			),
+			r#"{"expression":"1 / 0","error":"Error: Division by zero"}"#
+		);
+		let output = json_output("1+", Err(Error::Argument("\"x\"".to_string())));
+		assert_eq!(
+			output,
+			r#"{"expression":"1+","error":"Error: Unknown argument \"x\""}"#
+		);
+		let value: serde_json::Value = serde_json::from_str(&output).unwrap();
+		assert_eq!(value["error"], "Error: Unknown argument \"x\"");
+	}
+
+	#[test]
+	fn json_escapes() {
+		assert_eq!(json_string("a\\b\n\t\u{1}c"), r#""a\\b\n\t\u0001c""#);
+	}
+
+	#[test]
+	fn thousands_separator() {
+		assert_eq!(
+			insert_thousands_separator("1234567890", ','),
+			"1,234,567,890"
+		);
+		assert_eq!(
+			insert_thousands_separator("1234567890", '_'),
+			"1_234_567_890"
+		);
+		assert_eq!(
+			insert_thousands_separator("1234567890", ' '),
+			"1 234 567 890"
+		);
+		for (value, output) in [
+			("123", "123"),
+			("123456", "123,456"),
+			("1234", "1,234"),
+			("0", "0"),
+			("-1234567", "-1,234,567"),
+			("-123", "-123"),
+			("1234.56789", "1,234.56789"),
+			("0.000001", "0.000001"),
+			("-1000.000", "-1,000.000"),
+		] {
+			assert_eq!(insert_thousands_separator(value, ','), output);
+		}
+		let options = FormatOptions {
+			thousands_separator: Some(','),
+			..FormatOptions::default()
+		};
+		assert_eq!(options.format(Decimal::new(-12345678, 2)), "-123,456.78");
+		assert_eq!(options.format(Decimal::new(123456789, 0)), "1.23456789e8");
+		let options = FormatOptions {
+			plain: true,
+			..options
+		};
+		assert_eq!(options.format(Decimal::new(123456789, 0)), "123,456,789");
+	}
+
+	#[test]
+	fn automatic_scientific() {
+		assert_eq!(to_scientific(&Decimal::new(1, 9)), "1e-9");
+		assert_eq!(to_scientific(&Decimal::new(123456789, 0)), "1.23456789e8");
+		assert_eq!(to_scientific(&Decimal::new(-100_000_000_000, 0)), "-1e11");
+		assert_eq!(to_scientific(&Decimal::new(12340, 11)), "1.234e-7");
+		for (value, output) in [
+			(Decimal::new(1234567, 0), "1234567"),
+			(Decimal::new(1, 6), "0.000001"),
+			(Decimal::new(1500, 3), "1.500"),
+			(Decimal::new(-25, 1), "-2.5"),
+			(Decimal::new(0, 10), "0.0000000000"),
+		] {
+			assert_eq!(to_scientific(&value), output);
+		}
+		assert_eq!(FormatOptions::default().format(Decimal::new(1, 9)), "1e-9");
+		let options = FormatOptions {
+			plain: true,
+			..FormatOptions::default()
+		};
+		assert_eq!(options.format(Decimal::new(1, 9)), "0.000000001");
+	}
+}
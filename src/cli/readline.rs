@@ -0,0 +1,168 @@
+use super::{Repl, Status, CONTINUATION_PROMPT};
+use crate::parser::FUNCTION_NAMES;
+use rustyline::{
+	completion::{Completer, Pair},
+	error::ReadlineError,
+	highlight::Highlighter,
+	hint::Hinter,
+	history::DefaultHistory,
+	validate::Validator,
+	Editor, Helper,
+};
+use std::{
+	env,
+	ffi::OsString,
+	fs, io,
+	path::{Path, PathBuf},
+};
+
+/// Location of the REPL history, in `$XDG_DATA_HOME/calculator` if it is set
+/// and in the home directory otherwise.
+pub fn history_path() -> Option<PathBuf> {
+	history_path_from(env::var_os("XDG_DATA_HOME"), env::var_os("HOME"))
+}
+
+fn history_path_from(data_home: Option<OsString>, home: Option<OsString>) -> Option<PathBuf> {
+	let non_empty = |path: &OsString| !path.is_empty();
+	match data_home.filter(non_empty) {
+		Some(data_home) => Some(Path::new(&data_home).join("calculator").join("history")),
+		None => home
+			.filter(non_empty)
+			.map(|home| Path::new(&home).join(".calculator_history")),
+	}
+}
+
+/// Completes names of built-in functions.
+struct FunctionCompleter;
+
+impl Completer for FunctionCompleter {
+	type Candidate = Pair;
+
+	fn complete(
+		&self,
+		line: &str,
+		pos: usize,
+		_: &rustyline::Context<'_>,
+	) -> rustyline::Result<(usize, Vec<Pair>)> {
+		let (start, names) = complete_function(line, pos);
+		let candidates = names
+			.map(|name| Pair {
+				display: name.to_string(),
+				replacement: format!("{name}("),
+			})
+			.collect();
+		Ok((start, candidates))
+	}
+}
+
+impl Hinter for FunctionCompleter {
+	type Hint = String;
+}
+
+impl Highlighter for FunctionCompleter {}
+
+impl Validator for FunctionCompleter {}
+
+impl Helper for FunctionCompleter {}
+
+/// Start of the name before the cursor and the functions it is the beginning of.
+fn complete_function(line: &str, pos: usize) -> (usize, impl Iterator<Item = &'static str> + '_) {
+	let start = line[..pos]
+		.char_indices()
+		.rev()
+		.take_while(|(_, c)| c.is_alphanumeric() || *c == '_')
+		.last()
+		.map_or(pos, |(index, _)| index);
+	let prefix = &line[start..pos];
+	let names = FUNCTION_NAMES
+		.iter()
+		.copied()
+		.filter(move |name| !prefix.is_empty() && name.starts_with(prefix));
+	(start, names)
+}
+
+fn io_error(error: ReadlineError) -> io::Error {
+	match error {
+		ReadlineError::Io(error) => error,
+		error => io::Error::other(error),
+	}
+}
+
+impl Repl {
+	/// Evaluate expressions like `run`, reading them from the terminal with line editing,
+	/// history and completion of function names. The history is loaded from and saved
+	/// to a file, if there is one. Ctrl+C and Ctrl+D exit.
+	pub fn run_interactive(&mut self, history: Option<&Path>) -> io::Result<()> {
+		let mut editor = Editor::<FunctionCompleter, DefaultHistory>::new().map_err(io_error)?;
+		editor.set_helper(Some(FunctionCompleter));
+		if let Some(history) = history {
+			// There is no history before the first session
+			let _ = editor.load_history(history);
+		}
+		let mut stdout = io::stdout();
+		let mut buffer = String::new();
+		let mut continued = false;
+		loop {
+			let prompt = if continued {
+				CONTINUATION_PROMPT
+			} else {
+				&self.prompt
+			};
+			match editor.readline(prompt) {
+				Ok(line) => {
+					editor.add_history_entry(line.as_str()).map_err(io_error)?;
+					buffer.push_str(&line);
+					buffer.push('\n');
+					continued = match self.process(&mut buffer, &mut stdout)? {
+						Status::Continued => true,
+						Status::Done => false,
+						Status::Quit => break,
+					};
+				}
+				Err(ReadlineError::Interrupted | ReadlineError::Eof) => break,
+				Err(error) => return Err(io_error(error)),
+			}
+		}
+		self.finish(&buffer, &mut stdout)?;
+		if let Some(history) = history {
+			if let Some(parent) = history.parent() {
+				fs::create_dir_all(parent)?;
+			}
+			editor.save_history(history).map_err(io_error)?;
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{complete_function, history_path_from};
+	use std::path::PathBuf;
+
+	#[test]
+	fn history_location() {
+		assert_eq!(
+			history_path_from(Some("/data".into()), Some("/home".into())),
+			Some(PathBuf::from("/data/calculator/history"))
+		);
+		assert_eq!(
+			history_path_from(Some("".into()), Some("/home".into())),
+			Some(PathBuf::from("/home/.calculator_history"))
+		);
+		assert_eq!(history_path_from(None, None), None);
+	}
+
+	#[test]
+	fn completion() {
+		let complete = |line: &str, pos| {
+			let (start, names) = complete_function(line, pos);
+			(start, names.collect::<Vec<_>>())
+		};
+		assert_eq!(complete("2 * s", 5), (4, vec!["sqrt", "sign", "sgn"]));
+		assert_eq!(complete("lo(1)", 2), (0, vec!["log"]));
+		assert_eq!(complete("1 + bit", 7), (4, vec!["bitor", "bitxor"]));
+		assert_eq!(complete("m", 1), (0, vec!["min", "max"]));
+		assert_eq!(complete("1 + ", 4), (4, vec![]));
+		assert_eq!(complete("foo", 3), (0, vec![]));
+	}
+}
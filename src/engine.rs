@@ -1,21 +1,203 @@
-use rust_decimal::{prelude::Signed, Decimal};
+use crate::parser;
+use alloc::{
+	boxed::Box,
+	string::{String, ToString},
+	vec,
+	vec::Vec,
+};
+use core::{cmp::Ordering, convert::Infallible, fmt, ops};
+use rust_decimal::{
+	prelude::{Signed, ToPrimitive},
+	Decimal, MathematicalOps,
+};
 
-#[derive(Debug, PartialEq, Eq)]
+mod context;
+mod dot;
+mod optimize;
+mod sexpr;
+mod trace;
+mod unparse;
+mod visitor;
+
+pub use self::{
+	context::Context,
+	dot::to_dot,
+	optimize::fold_constants,
+	sexpr::{parse_sexpr, to_sexpr, SexprError},
+	trace::evaluate_traced,
+	unparse::unparse,
+	visitor::{CountNodes, Visitor},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+/// Evaluation error.
+pub enum Error {
+	DivisionByZero,
+	Overflow,
+	Underflow,
+	NotAnInteger,
+	UndefinedVariable(String),
+	/// The arguments of the named function contradict each other
+	InvalidArguments(String),
+	/// The operation is not defined for its operands, with the reason
+	Undefined(&'static str),
+	/// Error of a `rust_decimal` operation
+	Other(rust_decimal::Error),
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Error::Other(e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Error::DivisionByZero => write!(f, "Error: Division by zero"),
+			Error::Overflow => write!(f, "Error: Exceeds maximum possible value"),
+			Error::Underflow => write!(f, "Error: Less than minimum possible value"),
+			Error::NotAnInteger => write!(f, "Error: Expected a non-negative integer"),
+			Error::UndefinedVariable(name) => write!(f, "Error: Undefined variable {name}"),
+			Error::InvalidArguments(name) => write!(f, "Error: Invalid arguments for {name}"),
+			Error::Undefined(reason) => write!(f, "Error: {reason}"),
+			Error::Other(e) => write!(f, "{e}"),
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+/// Error converting a node into an integer.
+pub enum ConversionError {
+	/// The node could not be evaluated
+	Evaluation(Error),
+	HasFractionalPart,
+	/// The value does not fit into the integer type
+	OutOfRange,
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ConversionError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			ConversionError::Evaluation(e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+impl fmt::Display for ConversionError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ConversionError::Evaluation(e) => write!(f, "{e}"),
+			ConversionError::HasFractionalPart => write!(f, "Error: Expected an integer"),
+			ConversionError::OutOfRange => write!(f, "Error: Integer out of range"),
+		}
+	}
+}
+
+impl From<Error> for ConversionError {
+	fn from(error: Error) -> Self {
+		ConversionError::Evaluation(error)
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 /// Node containing either a decimal value
 /// or an expression which evaluates to a decimal value.
 pub enum Node {
 	Value(Decimal),
+	/// Named constant or variable, resolved during evaluation
+	Identifier(String),
 	Expr(Box<Expr>), // requires boxing because of circular reference
 }
 
 impl TryFrom<Node> for Decimal {
-	type Error = rust_decimal::Error;
+	type Error = Error;
 
+	/// Evaluate a node, with values of constants from the default context.
 	fn try_from(value: Node) -> Result<Self, Self::Error> {
-		match value {
-			Node::Value(value) => Ok(value),
-			Node::Expr(expr) => Decimal::try_from(*expr),
+		evaluate_with_context(value, &Context::default())
+	}
+}
+
+/// Evaluate a node and convert the value if it has no fractional part.
+fn to_integer<T>(
+	node: Node,
+	convert: impl FnOnce(&Decimal) -> Option<T>,
+) -> Result<T, ConversionError> {
+	let value = Decimal::try_from(node)?;
+	if !value.fract().is_zero() {
+		return Err(ConversionError::HasFractionalPart);
+	}
+	convert(&value).ok_or(ConversionError::OutOfRange)
+}
+
+impl TryFrom<Node> for i64 {
+	type Error = ConversionError;
+
+	/// Evaluate a node with an integer value, such as `2.0`.
+	///
+	/// ```
+	/// use calculator::{engine::ConversionError, Decimal, Node};
+	///
+	/// assert_eq!(i64::try_from(Node::Value(Decimal::new(42, 0))), Ok(42));
+	/// assert_eq!(
+	///     i64::try_from(Node::Value(Decimal::new(15, 1))),
+	///     Err(ConversionError::HasFractionalPart)
+	/// );
+	/// ```
+	fn try_from(value: Node) -> Result<Self, Self::Error> {
+		to_integer(value, Decimal::to_i64)
+	}
+}
+
+impl TryFrom<Node> for u64 {
+	type Error = ConversionError;
+
+	/// Evaluate a node with a non-negative integer value, such as `2.0`.
+	///
+	/// ```
+	/// use calculator::{engine::ConversionError, Decimal, Node};
+	///
+	/// assert_eq!(u64::try_from(Node::Value(Decimal::new(42, 0))), Ok(42));
+	/// assert_eq!(
+	///     u64::try_from(Node::Value(Decimal::NEGATIVE_ONE)),
+	///     Err(ConversionError::OutOfRange)
+	/// );
+	/// ```
+	fn try_from(value: Node) -> Result<Self, Self::Error> {
+		to_integer(value, Decimal::to_u64)
+	}
+}
+
+impl Default for Node {
+	/// Zero value, the neutral element of addition.
+	fn default() -> Self {
+		Node::Value(Decimal::ZERO)
+	}
+}
+
+impl PartialOrd for Node {
+	/// Compare the values of the nodes, evaluated with constants from the default context.
+	/// Only equal trees are equal, like with `==`, so different trees with the same value
+	/// are not comparable, such as `1 + 1` and `2`. Neither are nodes which fail to evaluate.
+	/// See `Node::sort_key` for sorting.
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		if self == other {
+			return Some(Ordering::Equal);
 		}
+		let lhs = Decimal::try_from(self.clone()).ok()?;
+		let rhs = Decimal::try_from(other.clone()).ok()?;
+		Some(lhs.cmp(&rhs)).filter(|ordering| ordering.is_ne())
 	}
 }
 
@@ -25,76 +207,892 @@ impl From<Decimal> for Node {
 	}
 }
 
-#[derive(Debug, PartialEq, Eq)]
+impl From<Node> for Expr {
+	/// Wrap a node in an identity expression, which evaluates to the value of the node.
+	fn from(node: Node) -> Self {
+		Expr::Identity(node)
+	}
+}
+
+impl From<Decimal> for Expr {
+	/// Wrap a value in an identity expression, same as converting it into a node first.
+	fn from(value: Decimal) -> Self {
+		Expr::Identity(Node::Value(value))
+	}
+}
+
+impl From<Node> for f64 {
+	/// Evaluate a node as a floating-point number, see `to_f64_lossy`.
+	fn from(value: Node) -> Self {
+		to_f64_lossy(value)
+	}
+}
+
+impl TryFrom<f64> for Node {
+	type Error = rust_decimal::Error;
+
+	/// Convert a finite floating-point number within the range of `Decimal` to a value.
+	fn try_from(value: f64) -> Result<Self, Self::Error> {
+		Decimal::try_from(value).map(Node::Value)
+	}
+}
+
+impl Node {
+	/// Maximum nesting depth of expressions, where values and identifiers have depth 0.
+	pub fn depth(&self) -> usize {
+		match self {
+			Node::Value(_) | Node::Identifier(_) => 0,
+			Node::Expr(expr) => {
+				let operands = expr.operands().into_iter().map(Node::depth);
+				1 + operands.max().unwrap_or(0)
+			}
+		}
+	}
+
+	/// Key for sorting nodes by their values with `sort_by_key`, evaluated with constants
+	/// from the default context. Nodes which fail to evaluate are ordered after all others.
+	///
+	/// ```
+	/// use calculator::{engine::Node, parser::parse};
+	///
+	/// let mut nodes: Vec<Node> = ["3", "1 / 0", "1 + 1"].map(|input| parse(input).unwrap()).into();
+	/// nodes.sort_by_key(Node::sort_key);
+	/// assert_eq!(nodes[0].to_string(), "(1 + 1)");
+	/// assert_eq!(nodes[2].to_string(), "(1 / 0)");
+	/// ```
+	pub fn sort_key(&self) -> (bool, Decimal) {
+		match Decimal::try_from(self.clone()) {
+			Ok(value) => (false, value),
+			Err(_) => (true, Decimal::ZERO),
+		}
+	}
+
+	/// Number of value leaves in the tree.
+	pub fn count_values(&self) -> usize {
+		match self {
+			Node::Value(_) => 1,
+			Node::Identifier(_) => 0,
+			Node::Expr(expr) => expr.operands().into_iter().map(Node::count_values).sum(),
+		}
+	}
+
+	/// Number of expression nodes in the tree, such as operators and function calls.
+	/// A sum counts as one addition less than it has addends, like the chain it replaces.
+	pub fn count_ops(&self) -> usize {
+		match self {
+			Node::Value(_) | Node::Identifier(_) => 0,
+			Node::Expr(expr) => {
+				let ops = match &**expr {
+					Expr::Sum(values) => values.len().saturating_sub(1),
+					Expr::Identity(_) => 0,
+					_ => 1,
+				};
+				ops + expr
+					.operands()
+					.into_iter()
+					.map(Node::count_ops)
+					.sum::<usize>()
+			}
+		}
+	}
+
+	/// Replace every value leaf with the result of a function, such as
+	/// a conversion of units, leaving identifiers and operations intact.
+	///
+	/// ```
+	/// use calculator::{parser::parse, Decimal};
+	///
+	/// let node = parse("-90").unwrap();
+	/// let radians = node.map_values(|degrees| degrees * Decimal::PI / Decimal::from(180));
+	/// assert_eq!(radians.to_string(), "-1.5707963267948966192313216917");
+	/// ```
+	pub fn map_values<F: Fn(Decimal) -> Decimal>(self, f: F) -> Node {
+		self.map_values_fallible(|value| Ok::<_, Infallible>(f(value)))
+			.unwrap_or_else(|never| match never {})
+	}
+
+	/// Replace every identifier with the name by a copy of another node,
+	/// such as a value of a variable.
+	///
+	/// ```
+	/// use calculator::{parser::parse, Decimal, Node};
+	///
+	/// let node = parse("x * x + y").unwrap();
+	/// let node = node.substitute("x", Node::from(Decimal::new(3, 0)));
+	/// assert_eq!(node.to_string(), "((3 * 3) + y)");
+	/// ```
+	pub fn substitute(self, name: &str, replacement: Node) -> Node {
+		self.substitute_with(name, &replacement)
+	}
+
+	fn substitute_with(self, name: &str, replacement: &Node) -> Node {
+		match self {
+			Node::Identifier(identifier) if identifier == name => replacement.clone(),
+			Node::Value(_) | Node::Identifier(_) => self,
+			Node::Expr(expr) => {
+				let expr = expr
+					.try_map_operands(|operand| {
+						Ok::<_, Infallible>(operand.substitute_with(name, replacement))
+					})
+					.unwrap_or_else(|never| match never {});
+				Node::Expr(Box::new(expr))
+			}
+		}
+	}
+
+	/// Replace every value leaf with the result of a fallible function,
+	/// stopping at the first error.
+	pub fn map_values_fallible<E, F: Fn(Decimal) -> Result<Decimal, E>>(
+		self,
+		f: F,
+	) -> Result<Node, E> {
+		self.try_map_values(&f)
+	}
+
+	fn try_map_values<E, F: Fn(Decimal) -> Result<Decimal, E>>(self, f: &F) -> Result<Node, E> {
+		match self {
+			Node::Value(value) => f(value).map(Node::Value),
+			Node::Identifier(_) => Ok(self),
+			Node::Expr(expr) => {
+				let expr = expr.try_map_operands(|operand| operand.try_map_values(f))?;
+				Ok(Node::Expr(Box::new(expr)))
+			}
+		}
+	}
+}
+
+/// Build an addition node. Like the other operators, it only builds
+/// the tree, so errors such as overflows occur when it is evaluated.
+///
+/// ```
+/// use calculator::{Decimal, Node};
+///
+/// let a = Node::from(Decimal::new(5, 0));
+/// let b = Node::from(Decimal::new(3, 0));
+/// let node = (a.clone() + b.clone()) * (a - b);
+/// assert_eq!(node.to_string(), "((5 + 3) * (5 - 3))");
+/// assert_eq!(Decimal::try_from(node), Ok(Decimal::new(16, 0)));
+///
+/// let overflow = Node::from(Decimal::MAX) * Node::from(Decimal::TWO);
+/// assert!(Decimal::try_from(overflow).is_err());
+/// ```
+impl ops::Add for Node {
+	type Output = Node;
+
+	fn add(self, rhs: Node) -> Node {
+		Node::Expr(Expr::Add(self, rhs).into())
+	}
+}
+
+/// Build a subtraction node.
+impl ops::Sub for Node {
+	type Output = Node;
+
+	fn sub(self, rhs: Node) -> Node {
+		Node::Expr(Expr::Sub(self, rhs).into())
+	}
+}
+
+/// Build a multiplication node.
+impl ops::Mul for Node {
+	type Output = Node;
+
+	fn mul(self, rhs: Node) -> Node {
+		Node::Expr(Expr::Mul(self, rhs).into())
+	}
+}
+
+/// Build a division node.
+impl ops::Div for Node {
+	type Output = Node;
+
+	fn div(self, rhs: Node) -> Node {
+		Node::Expr(Expr::Div(self, rhs).into())
+	}
+}
+
+/// Build a negation node.
+impl ops::Neg for Node {
+	type Output = Node;
+
+	fn neg(self) -> Node {
+		Node::Expr(Expr::Neg(self).into())
+	}
+}
+
+impl TryFrom<&str> for Node {
+	type Error = parser::Error;
+
+	/// Parse an expression, same as `parser::parse`.
+	///
+	/// ```
+	/// use calculator::{Decimal, Node};
+	///
+	/// let node: Node = "1 + 2".try_into().unwrap();
+	/// assert_eq!(Decimal::try_from(node), Ok(Decimal::new(3, 0)));
+	/// ```
+	fn try_from(input: &str) -> Result<Self, Self::Error> {
+		parser::parse(input)
+	}
+}
+
+impl TryFrom<String> for Node {
+	type Error = parser::Error;
+
+	/// Parse an expression, same as `parser::parse`.
+	///
+	/// ```
+	/// use calculator::Node;
+	///
+	/// let input = String::from("2 *");
+	/// assert!(Node::try_from(input).is_err());
+	/// ```
+	fn try_from(input: String) -> Result<Self, Self::Error> {
+		Node::try_from(input.as_str())
+	}
+}
+
+impl fmt::Display for Node {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Node::Value(value) => write!(f, "{value}"),
+			Node::Identifier(name) => write!(f, "{name}"),
+			Node::Expr(expr) => write!(f, "{expr}"),
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 /// An expression describing an arithmetical operation
 /// to perform on its node operand(s).
 pub enum Expr {
+	/// Value of the node unchanged, wrapping a node where an expression is expected
+	Identity(Node),
 	/// Addition
 	Add(Node, Node),
+	/// Sum of any number of addends, such as a chain like `1 + 2 + 3`
+	Sum(Vec<Node>),
 	/// Subtraction
 	Sub(Node, Node),
 	/// Multiplication
 	Mul(Node, Node),
 	/// Division
 	Div(Node, Node),
+	/// Exponentiation
+	Pow(Node, Node),
 	/// Sign inversion
 	Neg(Node),
+	/// Absolute value
+	Abs(Node),
+	/// Square root
+	Sqrt(Node),
+	/// Rounding down to the nearest integer
+	Floor(Node),
+	/// Rounding up to the nearest integer
+	Ceil(Node),
+	/// Sign of the value, one of -1, 0 and 1
+	Signum(Node),
+	/// Rounding half to even to a number of decimal places
+	Round(Node, Node),
+	/// Truncation toward zero to a number of decimal places
+	Truncate(Node, Node),
+	/// Smallest of the values
+	Min(Vec<Node>),
+	/// Largest of the values
+	Max(Vec<Node>),
+	/// Value limited to the range between a minimum and a maximum
+	Clamp(Node, Node, Node),
+	/// Percentage, a hundredth of the value
+	Percent(Node),
+	/// Product of all positive integers up to the value
+	Factorial(Node),
+	/// Natural logarithm
+	Ln(Node),
+	/// Logarithm with a base
+	Log(Node, Node),
+	/// Remainder of floored division, which has the sign of the divisor
+	Rem(Node, Node),
+	/// Greatest common divisor of non-negative integers
+	Gcd(Node, Node),
+	/// Least common multiple of non-negative integers
+	Lcm(Node, Node),
+	/// Bitwise and of non-negative integers
+	BitAnd(Node, Node),
+	/// Bitwise or of non-negative integers
+	BitOr(Node, Node),
+	/// Bitwise exclusive or of non-negative integers
+	BitXor(Node, Node),
+	/// Bitwise not of a non-negative integer within the 96 bits of `Decimal` integers
+	BitNot(Node),
+	/// Equality, one if equal and zero otherwise
+	Eq(Node, Node),
+	/// Inequality, one if not equal and zero otherwise
+	Ne(Node, Node),
+	/// Less than comparison, one or zero
+	Lt(Node, Node),
+	/// Less than or equal comparison, one or zero
+	Le(Node, Node),
+	/// Greater than comparison, one or zero
+	Gt(Node, Node),
+	/// Greater than or equal comparison, one or zero
+	Ge(Node, Node),
 }
 
-impl TryFrom<Expr> for Decimal {
-	type Error = rust_decimal::Error;
+impl Expr {
+	/// Name of the operation, matching the variant name.
+	pub fn name(&self) -> &'static str {
+		match self {
+			Expr::Identity(_) => "Identity",
+			Expr::Add(..) => "Add",
+			Expr::Sum(_) => "Sum",
+			Expr::Sub(..) => "Sub",
+			Expr::Mul(..) => "Mul",
+			Expr::Div(..) => "Div",
+			Expr::Pow(..) => "Pow",
+			Expr::Neg(_) => "Neg",
+			Expr::Abs(_) => "Abs",
+			Expr::Sqrt(_) => "Sqrt",
+			Expr::Floor(_) => "Floor",
+			Expr::Ceil(_) => "Ceil",
+			Expr::Signum(_) => "Signum",
+			Expr::Round(..) => "Round",
+			Expr::Truncate(..) => "Truncate",
+			Expr::Min(_) => "Min",
+			Expr::Max(_) => "Max",
+			Expr::Clamp(..) => "Clamp",
+			Expr::Percent(_) => "Percent",
+			Expr::Factorial(_) => "Factorial",
+			Expr::Ln(_) => "Ln",
+			Expr::Log(..) => "Log",
+			Expr::Rem(..) => "Rem",
+			Expr::Gcd(..) => "Gcd",
+			Expr::Lcm(..) => "Lcm",
+			Expr::BitAnd(..) => "BitAnd",
+			Expr::BitOr(..) => "BitOr",
+			Expr::BitXor(..) => "BitXor",
+			Expr::BitNot(_) => "BitNot",
+			Expr::Eq(..) => "Eq",
+			Expr::Ne(..) => "Ne",
+			Expr::Lt(..) => "Lt",
+			Expr::Le(..) => "Le",
+			Expr::Gt(..) => "Gt",
+			Expr::Ge(..) => "Ge",
+		}
+	}
 
-	fn try_from(value: Expr) -> Result<Self, Self::Error> {
-		match value {
-			Expr::Add(lhs, rhs) => {
-				let lhs = Decimal::try_from(lhs)?;
-				let rhs = Decimal::try_from(rhs)?;
-				// Can overflow
-				lhs.checked_add(rhs)
-					.map(|n| n.normalize())
-					.ok_or(rust_decimal::Error::ExceedsMaximumPossibleValue)
-			}
-			Expr::Sub(lhs, rhs) => {
-				let lhs = Decimal::try_from(lhs)?;
-				let rhs = Decimal::try_from(rhs)?;
-				// Can underflow
-				lhs.checked_sub(rhs)
-					.map(|n| n.normalize())
-					.ok_or(rust_decimal::Error::LessThanMinimumPossibleValue)
+	/// Operand nodes in order of appearance.
+	pub fn operands(&self) -> Vec<&Node> {
+		match self {
+			Expr::Add(lhs, rhs)
+			| Expr::Sub(lhs, rhs)
+			| Expr::Mul(lhs, rhs)
+			| Expr::Div(lhs, rhs)
+			| Expr::Pow(lhs, rhs)
+			| Expr::Round(lhs, rhs)
+			| Expr::Truncate(lhs, rhs)
+			| Expr::Log(lhs, rhs)
+			| Expr::Rem(lhs, rhs)
+			| Expr::Gcd(lhs, rhs)
+			| Expr::Lcm(lhs, rhs)
+			| Expr::BitAnd(lhs, rhs)
+			| Expr::BitOr(lhs, rhs)
+			| Expr::BitXor(lhs, rhs)
+			| Expr::Eq(lhs, rhs)
+			| Expr::Ne(lhs, rhs)
+			| Expr::Lt(lhs, rhs)
+			| Expr::Le(lhs, rhs)
+			| Expr::Gt(lhs, rhs)
+			| Expr::Ge(lhs, rhs) => vec![lhs, rhs],
+			Expr::Identity(value)
+			| Expr::Neg(value)
+			| Expr::Abs(value)
+			| Expr::Sqrt(value)
+			| Expr::Floor(value)
+			| Expr::Ceil(value)
+			| Expr::Signum(value)
+			| Expr::Percent(value)
+			| Expr::Factorial(value)
+			| Expr::Ln(value)
+			| Expr::BitNot(value) => vec![value],
+			Expr::Clamp(value, min, max) => vec![value, min, max],
+			Expr::Sum(values) | Expr::Min(values) | Expr::Max(values) => values.iter().collect(),
+		}
+	}
+
+	/// Replace every operand node with the result of a fallible function,
+	/// keeping the operation itself intact.
+	pub fn try_map_operands<E>(
+		self,
+		mut f: impl FnMut(Node) -> Result<Node, E>,
+	) -> Result<Self, E> {
+		Ok(match self {
+			Expr::Identity(value) => Expr::Identity(f(value)?),
+			Expr::Add(lhs, rhs) => Expr::Add(f(lhs)?, f(rhs)?),
+			Expr::Sum(values) => Expr::Sum(values.into_iter().map(f).collect::<Result<_, _>>()?),
+			Expr::Sub(lhs, rhs) => Expr::Sub(f(lhs)?, f(rhs)?),
+			Expr::Mul(lhs, rhs) => Expr::Mul(f(lhs)?, f(rhs)?),
+			Expr::Div(lhs, rhs) => Expr::Div(f(lhs)?, f(rhs)?),
+			Expr::Pow(lhs, rhs) => Expr::Pow(f(lhs)?, f(rhs)?),
+			Expr::Neg(value) => Expr::Neg(f(value)?),
+			Expr::Abs(value) => Expr::Abs(f(value)?),
+			Expr::Sqrt(value) => Expr::Sqrt(f(value)?),
+			Expr::Floor(value) => Expr::Floor(f(value)?),
+			Expr::Ceil(value) => Expr::Ceil(f(value)?),
+			Expr::Signum(value) => Expr::Signum(f(value)?),
+			Expr::Round(value, decimal_places) => Expr::Round(f(value)?, f(decimal_places)?),
+			Expr::Truncate(value, decimal_places) => Expr::Truncate(f(value)?, f(decimal_places)?),
+			Expr::Min(values) => Expr::Min(values.into_iter().map(f).collect::<Result<_, _>>()?),
+			Expr::Max(values) => Expr::Max(values.into_iter().map(f).collect::<Result<_, _>>()?),
+			Expr::Clamp(value, min, max) => Expr::Clamp(f(value)?, f(min)?, f(max)?),
+			Expr::Percent(value) => Expr::Percent(f(value)?),
+			Expr::Factorial(value) => Expr::Factorial(f(value)?),
+			Expr::Ln(value) => Expr::Ln(f(value)?),
+			Expr::Log(base, value) => Expr::Log(f(base)?, f(value)?),
+			Expr::Rem(lhs, rhs) => Expr::Rem(f(lhs)?, f(rhs)?),
+			Expr::Gcd(lhs, rhs) => Expr::Gcd(f(lhs)?, f(rhs)?),
+			Expr::Lcm(lhs, rhs) => Expr::Lcm(f(lhs)?, f(rhs)?),
+			Expr::BitAnd(lhs, rhs) => Expr::BitAnd(f(lhs)?, f(rhs)?),
+			Expr::BitOr(lhs, rhs) => Expr::BitOr(f(lhs)?, f(rhs)?),
+			Expr::BitXor(lhs, rhs) => Expr::BitXor(f(lhs)?, f(rhs)?),
+			Expr::BitNot(value) => Expr::BitNot(f(value)?),
+			Expr::Eq(lhs, rhs) => Expr::Eq(f(lhs)?, f(rhs)?),
+			Expr::Ne(lhs, rhs) => Expr::Ne(f(lhs)?, f(rhs)?),
+			Expr::Lt(lhs, rhs) => Expr::Lt(f(lhs)?, f(rhs)?),
+			Expr::Le(lhs, rhs) => Expr::Le(f(lhs)?, f(rhs)?),
+			Expr::Gt(lhs, rhs) => Expr::Gt(f(lhs)?, f(rhs)?),
+			Expr::Ge(lhs, rhs) => Expr::Ge(f(lhs)?, f(rhs)?),
+		})
+	}
+}
+
+impl fmt::Display for Expr {
+	/// Every binary expression is wrapped in parentheses
+	/// so the output does not depend on operator precedence.
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Expr::Identity(value) => write!(f, "{value}"),
+			Expr::Add(lhs, rhs) => write!(f, "({lhs} + {rhs})"),
+			Expr::Sum(values) if values.is_empty() => write!(f, "0"),
+			Expr::Sum(values) => {
+				write!(f, "(")?;
+				for (index, value) in values.iter().enumerate() {
+					if index > 0 {
+						write!(f, " + ")?;
+					}
+					write!(f, "{value}")?;
+				}
+				write!(f, ")")
 			}
-			Expr::Mul(lhs, rhs) => {
-				let lhs = Decimal::try_from(lhs)?;
-				let rhs = Decimal::try_from(rhs)?;
-				// Can overflow or underflow depending on operand signs
-				lhs.checked_mul(rhs)
-					.map(|n| n.normalize())
-					.ok_or(if lhs.signum() == rhs.signum() {
-						rust_decimal::Error::ExceedsMaximumPossibleValue
-					} else {
-						rust_decimal::Error::LessThanMinimumPossibleValue
-					})
+			Expr::Sub(lhs, rhs) => write!(f, "({lhs} - {rhs})"),
+			Expr::Mul(lhs, rhs) => write!(f, "({lhs} * {rhs})"),
+			Expr::Div(lhs, rhs) => write!(f, "({lhs} / {rhs})"),
+			// Negation binds looser than powers
+			Expr::Pow(Node::Expr(lhs), rhs) if matches!(**lhs, Expr::Neg(_)) => {
+				write!(f, "(({lhs}) ^ {rhs})")
 			}
-			Expr::Div(lhs, rhs) => {
-				let lhs = Decimal::try_from(lhs)?;
-				let rhs = Decimal::try_from(rhs)?;
-				// Can overflow or underflow (division by zero)
-				lhs.checked_div(rhs)
-					.map(|n| n.normalize())
-					.ok_or(if lhs >= Decimal::ZERO {
-						rust_decimal::Error::ExceedsMaximumPossibleValue
-					} else {
-						rust_decimal::Error::LessThanMinimumPossibleValue
-					})
+			Expr::Pow(lhs, rhs) => write!(f, "({lhs} ^ {rhs})"),
+			Expr::Neg(value) => write!(f, "-{value}"),
+			Expr::Abs(value) => write!(f, "|{value}|"),
+			Expr::Sqrt(value) => write!(f, "sqrt({value})"),
+			Expr::Floor(value) => write!(f, "floor({value})"),
+			Expr::Ceil(value) => write!(f, "ceil({value})"),
+			Expr::Signum(value) => write!(f, "sign({value})"),
+			Expr::Round(value, decimal_places) => write!(f, "round({value}, {decimal_places})"),
+			Expr::Truncate(value, decimal_places) => {
+				write!(f, "trunc({value}, {decimal_places})")
 			}
-			Expr::Neg(value) => Ok(-Decimal::try_from(value)?),
+			Expr::Min(values) => write_call(f, "min", values),
+			Expr::Max(values) => write_call(f, "max", values),
+			Expr::Clamp(value, min, max) => write!(f, "clamp({value}, {min}, {max})"),
+			Expr::Percent(value) => write!(f, "{value}%"),
+			Expr::Factorial(value) => write!(f, "{value}!"),
+			Expr::Ln(value) => write!(f, "ln({value})"),
+			Expr::Log(base, value) => write!(f, "log({base}, {value})"),
+			Expr::Rem(lhs, rhs) => write!(f, "rem({lhs}, {rhs})"),
+			Expr::Gcd(lhs, rhs) => write!(f, "gcd({lhs}, {rhs})"),
+			Expr::Lcm(lhs, rhs) => write!(f, "lcm({lhs}, {rhs})"),
+			Expr::BitAnd(lhs, rhs) => write!(f, "({lhs} & {rhs})"),
+			Expr::BitOr(lhs, rhs) => write!(f, "bitor({lhs}, {rhs})"),
+			Expr::BitXor(lhs, rhs) => write!(f, "bitxor({lhs}, {rhs})"),
+			Expr::BitNot(value) => write!(f, "~{value}"),
+			Expr::Eq(lhs, rhs) => write!(f, "({lhs} == {rhs})"),
+			Expr::Ne(lhs, rhs) => write!(f, "({lhs} != {rhs})"),
+			Expr::Lt(lhs, rhs) => write!(f, "({lhs} < {rhs})"),
+			Expr::Le(lhs, rhs) => write!(f, "({lhs} <= {rhs})"),
+			Expr::Gt(lhs, rhs) => write!(f, "({lhs} > {rhs})"),
+			Expr::Ge(lhs, rhs) => write!(f, "({lhs} >= {rhs})"),
+		}
+	}
+}
+
+/// Write a function call with comma separated arguments.
+fn write_call(f: &mut fmt::Formatter<'_>, name: &str, arguments: &[Node]) -> fmt::Result {
+	write!(f, "{name}(")?;
+	for (index, argument) in arguments.iter().enumerate() {
+		if index > 0 {
+			write!(f, ", ")?;
 		}
+		write!(f, "{argument}")?;
+	}
+	write!(f, ")")
+}
+
+impl TryFrom<Expr> for Decimal {
+	type Error = Error;
+
+	/// Evaluate an expression, with values of constants from the default context.
+	fn try_from(value: Expr) -> Result<Self, Self::Error> {
+		evaluate_expr(value, &Context::default())
+	}
+}
+
+/// Evaluate a node, with values of named constants and variables from the context.
+pub fn evaluate_with_context(node: Node, context: &Context) -> Result<Decimal, Error> {
+	match node {
+		Node::Value(value) => Ok(value),
+		Node::Identifier(name) => context.get(&name).ok_or(Error::UndefinedVariable(name)),
+		Node::Expr(expr) => evaluate_expr(*expr, context),
+	}
+}
+
+/// Evaluate a node with values of constants from the default context
+/// and convert the result to the nearest floating-point number.
+/// Returns NaN if the evaluation fails.
+pub fn to_f64_lossy(node: Node) -> f64 {
+	Decimal::try_from(node)
+		.ok()
+		.and_then(|value| value.to_f64())
+		.unwrap_or(f64::NAN)
+}
+
+/// Evaluate the operands of an expression and apply its operation to their values.
+/// Operands are evaluated by `unary` and `binary`, which keeps the stack frame
+/// of this function small, since it is entered again for every level of the tree.
+fn evaluate_expr(expr: Expr, context: &Context) -> Result<Decimal, Error> {
+	crate::debug!(
+		"Evaluating {} with {} operands",
+		expr.name(),
+		expr.operands().len()
+	);
+	match expr {
+		Expr::Identity(value) => evaluate_with_context(value, context),
+		Expr::Add(lhs, rhs) => binary(lhs, rhs, context, add),
+		Expr::Sum(values) => sum(values, context),
+		Expr::Sub(lhs, rhs) => binary(lhs, rhs, context, sub),
+		Expr::Mul(lhs, rhs) => binary(lhs, rhs, context, mul),
+		Expr::Div(lhs, rhs) => binary(lhs, rhs, context, div),
+		Expr::Pow(lhs, rhs) => binary(lhs, rhs, context, pow),
+		Expr::Neg(value) => unary(value, context, |value| Ok(-value)),
+		Expr::Abs(value) => unary(value, context, |value| Ok(value.abs())),
+		Expr::Sqrt(value) => unary(value, context, sqrt),
+		Expr::Floor(value) => unary(value, context, |value| Ok(value.floor())),
+		Expr::Ceil(value) => unary(value, context, |value| Ok(value.ceil())),
+		Expr::Signum(value) => unary(value, context, |value| Ok(value.signum())),
+		Expr::Round(value, decimal_places) => binary(value, decimal_places, context, |value, n| {
+			Ok(value.round_dp(to_decimal_places(n)?).normalize())
+		}),
+		Expr::Truncate(value, decimal_places) => {
+			binary(value, decimal_places, context, |value, n| {
+				Ok(value.trunc_with_scale(to_decimal_places(n)?).normalize())
+			})
+		}
+		Expr::Min(values) => select(values, context, Decimal::min),
+		Expr::Max(values) => select(values, context, Decimal::max),
+		Expr::Clamp(value, min, max) => {
+			let value = evaluate_with_context(value, context)?;
+			binary(min, max, context, |min, max| clamp(value, min, max))
+		}
+		// Cannot overflow since the divisor is larger than one
+		Expr::Percent(value) => unary(value, context, |value| {
+			Ok((value / Decimal::ONE_HUNDRED).normalize())
+		}),
+		Expr::Factorial(value) => unary(value, context, factorial),
+		Expr::Ln(value) => unary(value, context, ln),
+		Expr::Log(base, value) => {
+			let base = unary(base, context, ln)?;
+			unary(value, context, |value| log(base, ln(value)?))
+		}
+		Expr::Rem(lhs, rhs) => binary(lhs, rhs, context, rem),
+		Expr::Gcd(lhs, rhs) => {
+			let lhs = unary(lhs, context, integer)?;
+			unary(rhs, context, |rhs| {
+				Ok(Decimal::from(gcd(lhs, integer(rhs)?)))
+			})
+		}
+		Expr::Lcm(lhs, rhs) => {
+			let lhs = unary(lhs, context, integer)?;
+			unary(rhs, context, |rhs| lcm(lhs, integer(rhs)?))
+		}
+		// Results of integers within 96 bits stay within 96 bits
+		Expr::BitAnd(lhs, rhs) => binary(lhs, rhs, context, |lhs, rhs| {
+			bitwise(lhs, rhs, |lhs, rhs| lhs & rhs)
+		}),
+		Expr::BitOr(lhs, rhs) => binary(lhs, rhs, context, |lhs, rhs| {
+			bitwise(lhs, rhs, |lhs, rhs| lhs | rhs)
+		}),
+		Expr::BitXor(lhs, rhs) => binary(lhs, rhs, context, |lhs, rhs| {
+			bitwise(lhs, rhs, |lhs, rhs| lhs ^ rhs)
+		}),
+		Expr::BitNot(value) => unary(value, context, |value| {
+			Ok(Decimal::from(!integer(value)? & INTEGER_BITS))
+		}),
+		Expr::Eq(lhs, rhs) => binary(lhs, rhs, context, |lhs, rhs| compare(lhs == rhs)),
+		Expr::Ne(lhs, rhs) => binary(lhs, rhs, context, |lhs, rhs| compare(lhs != rhs)),
+		Expr::Lt(lhs, rhs) => binary(lhs, rhs, context, |lhs, rhs| compare(lhs < rhs)),
+		Expr::Le(lhs, rhs) => binary(lhs, rhs, context, |lhs, rhs| compare(lhs <= rhs)),
+		Expr::Gt(lhs, rhs) => binary(lhs, rhs, context, |lhs, rhs| compare(lhs > rhs)),
+		Expr::Ge(lhs, rhs) => binary(lhs, rhs, context, |lhs, rhs| compare(lhs >= rhs)),
+	}
+}
+
+/// Evaluate an operand and apply an operation to its value.
+fn unary<T>(
+	value: Node,
+	context: &Context,
+	operation: impl FnOnce(Decimal) -> Result<T, Error>,
+) -> Result<T, Error> {
+	operation(evaluate_with_context(value, context)?)
+}
+
+/// Evaluate two operands from left to right and apply an operation to their values.
+fn binary(
+	lhs: Node,
+	rhs: Node,
+	context: &Context,
+	operation: impl FnOnce(Decimal, Decimal) -> Result<Decimal, Error>,
+) -> Result<Decimal, Error> {
+	let lhs = evaluate_with_context(lhs, context)?;
+	operation(lhs, evaluate_with_context(rhs, context)?)
+}
+
+fn add(lhs: Decimal, rhs: Decimal) -> Result<Decimal, Error> {
+	// Can overflow
+	lhs.checked_add(rhs)
+		.map(|n| n.normalize())
+		.ok_or(Error::Overflow)
+}
+
+/// Add values from left to right like a chain of additions.
+fn sum(nodes: Vec<Node>, context: &Context) -> Result<Decimal, Error> {
+	let sum = nodes.into_iter().try_fold(Decimal::ZERO, |sum, node| {
+		sum.checked_add(evaluate_with_context(node, context)?)
+			.ok_or(Error::Overflow)
+	})?;
+	Ok(sum.normalize())
+}
+
+fn sub(lhs: Decimal, rhs: Decimal) -> Result<Decimal, Error> {
+	// Can underflow
+	lhs.checked_sub(rhs)
+		.map(|n| n.normalize())
+		.ok_or(Error::Underflow)
+}
+
+fn mul(lhs: Decimal, rhs: Decimal) -> Result<Decimal, Error> {
+	// Only fails if the magnitude is too large, in the direction of the sign
+	// the product would have, which is positive if the operand signs are equal.
+	// Zero operands never fail, so their sign does not matter.
+	lhs.checked_mul(rhs)
+		.map(|n| n.normalize())
+		.ok_or(if lhs.signum() == rhs.signum() {
+			Error::Overflow
+		} else {
+			Error::Underflow
+		})
+}
+
+fn div(lhs: Decimal, rhs: Decimal) -> Result<Decimal, Error> {
+	if rhs.is_zero() {
+		return Err(Error::DivisionByZero);
+	}
+	// Can overflow or underflow depending on operand signs
+	lhs.checked_div(rhs)
+		.map(|n| n.normalize())
+		.ok_or(if lhs.signum() == rhs.signum() {
+			Error::Overflow
+		} else {
+			Error::Underflow
+		})
+}
+
+fn pow(lhs: Decimal, rhs: Decimal) -> Result<Decimal, Error> {
+	if lhs.is_zero() && rhs.is_sign_negative() {
+		return Err(Error::DivisionByZero);
 	}
+	if lhs.is_sign_negative() && !rhs.fract().is_zero() {
+		return Err(Error::Undefined(
+			"Fractional power of a negative number is undefined",
+		));
+	}
+	// Can overflow, or underflow for negative bases with odd exponents
+	let overflow = if lhs.is_sign_negative() && rhs % Decimal::TWO != Decimal::ZERO {
+		Error::Underflow
+	} else {
+		Error::Overflow
+	};
+	// Integer powers are exact, unlike the approximation of `checked_powd`
+	if let Some(exponent) = rhs.fract().is_zero().then(|| rhs.to_i64()).flatten() {
+		return match lhs.checked_powu(exponent.unsigned_abs()) {
+			Some(power) if exponent >= 0 => Ok(power.normalize()),
+			Some(power) => Decimal::ONE
+				.checked_div(power)
+				.map(|n| n.normalize())
+				.ok_or(overflow),
+			// The inverse of a power too large to represent rounds to zero
+			None if exponent < 0 => Ok(Decimal::ZERO),
+			None => Err(overflow),
+		};
+	}
+	if rhs == Decimal::new(5, 1) {
+		return sqrt(lhs);
+	}
+	match lhs.checked_powd(rhs) {
+		Some(power) => Ok(power.normalize()),
+		// Powers too small to represent round to zero
+		None if (lhs > Decimal::ONE) != rhs.is_sign_positive() => Ok(Decimal::ZERO),
+		None => Err(overflow),
+	}
+}
+
+/// Square root, undefined for negative numbers.
+fn sqrt(value: Decimal) -> Result<Decimal, Error> {
+	value.sqrt().map(|n| n.normalize()).ok_or(Error::Undefined(
+		"Square root of a negative number is undefined",
+	))
+}
+
+fn clamp(value: Decimal, min: Decimal, max: Decimal) -> Result<Decimal, Error> {
+	// An empty range has no value to clamp to
+	if min > max {
+		return Err(Error::InvalidArguments("clamp".to_string()));
+	}
+	Ok(value.clamp(min, max))
+}
+
+/// Factorial, only defined for non-negative integers.
+fn factorial(value: Decimal) -> Result<Decimal, Error> {
+	if value.is_sign_negative() || !value.fract().is_zero() {
+		return Err(Error::NotAnInteger);
+	}
+	let n = value.to_u64().ok_or(Error::Overflow)?;
+	// Overflows quickly, long before `n` gets large
+	(2..=n).try_fold(Decimal::ONE, |product, i| {
+		product.checked_mul(Decimal::from(i)).ok_or(Error::Overflow)
+	})
+}
+
+/// Logarithm of a value to a base, both given as natural logarithms.
+/// Logarithm from natural logarithms, undefined for a base of one like for non-positive bases.
+fn log(ln_base: Decimal, ln_value: Decimal) -> Result<Decimal, Error> {
+	if ln_base.is_zero() {
+		return Err(Error::Undefined("Logarithm to the base one is undefined"));
+	}
+	// Change of base from the natural logarithm
+	ln_value
+		.checked_div(ln_base)
+		.map(|n| n.normalize())
+		.ok_or(Error::Overflow)
+}
+
+fn rem(lhs: Decimal, rhs: Decimal) -> Result<Decimal, Error> {
+	// `Decimal` remainders follow truncated division and have the sign of
+	// the dividend, e.g. -7 rem 3 is -1. Floored division rounds the quotient
+	// towards negative infinity instead, so the remainder has the sign of
+	// the divisor, e.g. 2. Both differ by exactly one divisor when nonzero,
+	// which avoids the rounding of `lhs - floor(lhs / rhs) * rhs`.
+	let remainder = lhs.checked_rem(rhs).ok_or(Error::DivisionByZero)?;
+	if !remainder.is_zero() && remainder.is_sign_negative() != rhs.is_sign_negative() {
+		Ok((remainder + rhs).normalize())
+	} else {
+		Ok(remainder.normalize())
+	}
+}
+
+/// Least common multiple of two integers, zero if either is zero.
+fn lcm(lhs: u128, rhs: u128) -> Result<Decimal, Error> {
+	if lhs == 0 || rhs == 0 {
+		return Ok(Decimal::ZERO);
+	}
+	// Dividing first keeps the intermediate result small
+	let lcm = Decimal::from(lhs / gcd(lhs, rhs)).checked_mul(Decimal::from(rhs));
+	lcm.ok_or(Error::Overflow)
+}
+
+/// Convert a non-negative integer value for integer arithmetic.
+fn integer(value: Decimal) -> Result<u128, Error> {
+	if value.is_sign_negative() || !value.fract().is_zero() {
+		return Err(Error::NotAnInteger);
+	}
+	value.to_u128().ok_or(Error::Overflow)
+}
+
+/// Mask of the 96 bits of `Decimal` integers, which is `Decimal::MAX`.
+const INTEGER_BITS: u128 = (1 << 96) - 1;
+
+/// Apply a bitwise operation to two non-negative integer values.
+fn bitwise(
+	lhs: Decimal,
+	rhs: Decimal,
+	operation: impl FnOnce(u128, u128) -> u128,
+) -> Result<Decimal, Error> {
+	Ok(Decimal::from(operation(integer(lhs)?, integer(rhs)?)))
+}
+
+/// Greatest common divisor by the Euclidean algorithm.
+fn gcd(mut lhs: u128, mut rhs: u128) -> u128 {
+	while rhs != 0 {
+		(lhs, rhs) = (rhs, lhs % rhs);
+	}
+	lhs
+}
+
+/// Value of a comparison, one if true and zero if false.
+fn compare(result: bool) -> Result<Decimal, Error> {
+	Ok(if result { Decimal::ONE } else { Decimal::ZERO })
+}
+
+/// Natural logarithm, undefined for non-positive numbers.
+fn ln(value: Decimal) -> Result<Decimal, Error> {
+	value
+		.checked_ln()
+		.map(|n| n.normalize())
+		.ok_or(Error::Undefined(
+			"Logarithm of a non-positive number is undefined",
+		))
+}
+
+/// Convert a value into a number of decimal places, which must be a non-negative integer.
+fn to_decimal_places(value: Decimal) -> Result<u32, Error> {
+	match value.to_u32() {
+		Some(n) if value.fract().is_zero() => Ok(n),
+		_ => Err(Error::Undefined(
+			"Decimal places must be a non-negative integer",
+		)),
+	}
+}
+
+/// Evaluate all nodes and reduce their values to one by repeatedly selecting between two.
+fn select(
+	nodes: Vec<Node>,
+	context: &Context,
+	selector: fn(Decimal, Decimal) -> Decimal,
+) -> Result<Decimal, Error> {
+	let mut selected = None;
+	for node in nodes {
+		let value = evaluate_with_context(node, context)?;
+		selected = Some(selected.map_or(value, |selected| selector(selected, value)));
+	}
+	selected.ok_or(Error::Undefined("No values to select from"))
 }
 
 #[cfg(test)]
 mod tests {
-	use super::{Expr, Node};
-	use rust_decimal::Decimal;
+	use super::{Context, ConversionError, Error, Expr, Node};
+	use crate::parser::parse;
+	use rust_decimal::{prelude::ToPrimitive, Decimal};
+	use std::{cmp::Ordering, collections::HashSet};
 
 	#[test]
 	fn raw() {
@@ -113,9 +1111,55 @@ mod tests {
 
 	#[test]
 	fn add_overflow() {
-		let error: Result<Decimal, rust_decimal::Error> =
+		let error: Result<Decimal, Error> =
 			Node::Expr(Expr::Add(Decimal::MAX.into(), Decimal::ONE.into()).into()).try_into();
-		assert_eq!(error, Err(rust_decimal::Error::ExceedsMaximumPossibleValue));
+		assert_eq!(error, Err(Error::Overflow));
+	}
+
+	#[test]
+	fn identity() {
+		let expr: Expr = Decimal::TWO.into();
+		assert_eq!(expr, Expr::Identity(Node::Value(Decimal::TWO)));
+		assert_eq!(expr.to_string(), "2");
+		assert_eq!(Decimal::try_from(expr), Ok(Decimal::TWO));
+
+		let expr = Expr::from(parse("1 + x").unwrap());
+		assert_eq!(expr.to_string(), "(1 + x)");
+		let node = Node::Expr(expr.into()) * Node::from(Decimal::TEN);
+		assert_eq!(node.count_ops(), 2);
+		let mut context = Context::new();
+		context.set("x".to_string(), Decimal::ONE);
+		assert_eq!(
+			super::evaluate_with_context(node, &context),
+			Ok(Decimal::new(20, 0))
+		);
+	}
+
+	#[test]
+	fn sum() {
+		let sum = |values: &[Decimal]| {
+			let values = values.iter().map(|&value| value.into()).collect();
+			Decimal::try_from(Node::Expr(Expr::Sum(values).into()))
+		};
+		assert_eq!(sum(&[Decimal::ONE]), Ok(Decimal::ONE));
+		assert_eq!(sum(&[]), Ok(Decimal::ZERO));
+		assert_eq!(
+			sum(&[
+				Decimal::ONE,
+				Decimal::TWO,
+				Decimal::new(3, 0),
+				Decimal::new(4, 0)
+			]),
+			Ok(Decimal::TEN)
+		);
+		assert_eq!(
+			sum(&[Decimal::MAX, Decimal::ONE, Decimal::NEGATIVE_ONE]),
+			Err(Error::Overflow)
+		);
+		let node = parse("1 + 2 + 3 + 4").unwrap();
+		assert_eq!(node.to_string(), "(1 + 2 + 3 + 4)");
+		assert_eq!(node.count_ops(), 3);
+		assert_eq!(Decimal::try_from(node), Ok(Decimal::TEN));
 	}
 
 	#[test]
@@ -130,12 +1174,9 @@ mod tests {
 
 	#[test]
 	fn sub_underflow() {
-		let error: Result<Decimal, rust_decimal::Error> =
+		let error: Result<Decimal, Error> =
 			Node::Expr(Expr::Sub(Decimal::MIN.into(), Decimal::ONE.into()).into()).try_into();
-		assert_eq!(
-			error,
-			Err(rust_decimal::Error::LessThanMinimumPossibleValue)
-		);
+		assert_eq!(error, Err(Error::Underflow));
 	}
 
 	#[test]
@@ -150,18 +1191,34 @@ mod tests {
 
 	#[test]
 	fn mul_overflow() {
-		let error: Result<Decimal, rust_decimal::Error> =
+		let error: Result<Decimal, Error> =
 			Node::Expr(Expr::Mul(Decimal::MAX.into(), Decimal::TWO.into()).into()).try_into();
-		assert_eq!(error, Err(rust_decimal::Error::ExceedsMaximumPossibleValue));
+		assert_eq!(error, Err(Error::Overflow));
 	}
 
 	#[test]
 	fn mul_underflow() {
-		let error: Result<Decimal, rust_decimal::Error> =
+		let error: Result<Decimal, Error> =
 			Node::Expr(Expr::Mul(Decimal::MIN.into(), Decimal::TWO.into()).into()).try_into();
+		assert_eq!(error, Err(Error::Underflow));
+	}
+
+	#[test]
+	fn mul_overflow_direction() {
+		let mul = |lhs: Decimal, rhs: Decimal| {
+			Decimal::try_from(Node::Expr(Expr::Mul(lhs.into(), rhs.into()).into()))
+		};
+		assert_eq!(mul(Decimal::MIN, Decimal::MIN), Err(Error::Overflow));
+		assert_eq!(mul(-Decimal::TWO, Decimal::MIN), Err(Error::Overflow));
+		assert_eq!(mul(Decimal::NEGATIVE_ONE, Decimal::MAX), Ok(Decimal::MIN));
+		assert_eq!(mul(Decimal::MAX, Decimal::NEGATIVE_ONE), Ok(Decimal::MIN));
+		assert_eq!(mul(-Decimal::TWO, Decimal::MAX), Err(Error::Underflow));
+		assert_eq!(mul(Decimal::MAX, -Decimal::TWO), Err(Error::Underflow));
+		assert_eq!(mul(Decimal::MIN, Decimal::ZERO), Ok(Decimal::ZERO));
+		// Products too small to represent are rounded instead
 		assert_eq!(
-			error,
-			Err(rust_decimal::Error::LessThanMinimumPossibleValue)
+			mul(Decimal::new(-1, 28), Decimal::new(1, 28)),
+			Ok(Decimal::ZERO)
 		);
 	}
 
@@ -175,22 +1232,27 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn div_by_zero() {
+		for lhs in [Decimal::ONE, Decimal::ZERO, Decimal::NEGATIVE_ONE] {
+			let error: Result<Decimal, Error> =
+				Node::Expr(Expr::Div(lhs.into(), Decimal::ZERO.into()).into()).try_into();
+			assert_eq!(error, Err(Error::DivisionByZero));
+		}
+	}
+
 	#[test]
 	fn div_overflow() {
-		let error: Result<Decimal, rust_decimal::Error> =
-			Node::Expr(Expr::Div(Decimal::ONE.into(), Decimal::ZERO.into()).into()).try_into();
-		assert_eq!(error, Err(rust_decimal::Error::ExceedsMaximumPossibleValue));
+		let error: Result<Decimal, Error> =
+			Node::Expr(Expr::Div(Decimal::MAX.into(), Decimal::new(1, 1).into()).into()).try_into();
+		assert_eq!(error, Err(Error::Overflow));
 	}
 
 	#[test]
 	fn div_underflow() {
-		let error: Result<Decimal, rust_decimal::Error> =
-			Node::Expr(Expr::Div(Decimal::NEGATIVE_ONE.into(), Decimal::ZERO.into()).into())
-				.try_into();
-		assert_eq!(
-			error,
-			Err(rust_decimal::Error::LessThanMinimumPossibleValue)
-		);
+		let error: Result<Decimal, Error> =
+			Node::Expr(Expr::Div(Decimal::MIN.into(), Decimal::new(1, 1).into()).into()).try_into();
+		assert_eq!(error, Err(Error::Underflow));
 	}
 
 	#[test]
@@ -202,4 +1264,636 @@ mod tests {
 				.unwrap()
 		);
 	}
+
+	#[test]
+	fn abs() {
+		assert_eq!(
+			Decimal::ONE,
+			Node::Expr(Expr::Abs(Decimal::NEGATIVE_ONE.into()).into())
+				.try_into()
+				.unwrap()
+		);
+	}
+
+	#[test]
+	fn display() {
+		let node = Node::Expr(
+			Expr::Add(
+				Decimal::ONE.into(),
+				Node::Expr(Expr::Mul(Decimal::TWO.into(), Decimal::new(3, 0).into()).into()),
+			)
+			.into(),
+		);
+		assert_eq!(node.to_string(), "(1 + (2 * 3))");
+		assert_eq!(
+			Node::Expr(Expr::Neg(Node::Expr(Expr::Abs(Decimal::ONE.into()).into())).into())
+				.to_string(),
+			"-|1|"
+		);
+	}
+
+	#[test]
+	fn display_round_trip() {
+		for input in [
+			"1 + 2 * 3",
+			"(1 - 2) / -3",
+			"-|1 - 2| * 3 - 4 / 5",
+			"(-2) ^ 2 ^ -1",
+		] {
+			let node = parse(input).unwrap();
+			assert_eq!(parse(&node.to_string()).unwrap(), node);
+		}
+	}
+
+	#[test]
+	fn hash() {
+		let node = Node::Expr(Expr::Add(Decimal::ONE.into(), Decimal::TWO.into()).into());
+		let mut nodes = HashSet::new();
+		nodes.insert(node.clone());
+		assert!(nodes.contains(&node));
+		assert!(!nodes.contains(&Node::Value(Decimal::ONE)));
+	}
+
+	#[test]
+	fn sqrt() {
+		let sqrt = |value: Decimal| Decimal::try_from(Node::Expr(Expr::Sqrt(value.into()).into()));
+		assert_eq!(sqrt(Decimal::new(4, 0)), Ok(Decimal::TWO));
+		assert_eq!(sqrt(Decimal::ZERO), Ok(Decimal::ZERO));
+		let two = sqrt(Decimal::TWO).unwrap() * sqrt(Decimal::TWO).unwrap();
+		assert!((two - Decimal::TWO).abs() < Decimal::new(1, 20));
+	}
+
+	#[test]
+	fn sqrt_negative() {
+		let error: Result<Decimal, Error> =
+			Node::Expr(Expr::Sqrt(Decimal::NEGATIVE_ONE.into()).into()).try_into();
+		let error = error.unwrap_err();
+		assert_eq!(
+			error,
+			Error::Undefined("Square root of a negative number is undefined")
+		);
+		assert_eq!(
+			error.to_string(),
+			"Error: Square root of a negative number is undefined"
+		);
+		#[cfg(feature = "std")]
+		assert!(std::error::Error::source(&error).is_none());
+	}
+
+	#[test]
+	fn floor() {
+		assert_eq!(
+			Decimal::ONE,
+			Node::Expr(Expr::Floor(Decimal::new(17, 1).into()).into())
+				.try_into()
+				.unwrap()
+		);
+	}
+
+	#[test]
+	fn ceil() {
+		assert_eq!(
+			Decimal::TWO,
+			Node::Expr(Expr::Ceil(Decimal::new(12, 1).into()).into())
+				.try_into()
+				.unwrap()
+		);
+	}
+
+	#[test]
+	fn signum() {
+		let evaluate = |input| Decimal::try_from(parse(input).unwrap());
+		assert_eq!(evaluate("sign(5)"), Ok(Decimal::ONE));
+		assert_eq!(evaluate("sign(-3)"), Ok(Decimal::NEGATIVE_ONE));
+		assert_eq!(evaluate("sign(0)"), Ok(Decimal::ZERO));
+		assert_eq!(evaluate("sign(-0)"), Ok(Decimal::ZERO));
+		assert_eq!(evaluate("sgn(-0.001)"), Ok(Decimal::NEGATIVE_ONE));
+		assert_eq!(evaluate("sign(-0)").unwrap().to_string(), "0");
+	}
+
+	#[test]
+	fn round() {
+		assert_eq!(
+			Decimal::new(156, 2),
+			Node::Expr(Expr::Round(Decimal::new(1555, 3).into(), Decimal::TWO.into()).into())
+				.try_into()
+				.unwrap()
+		);
+	}
+
+	#[test]
+	fn truncate() {
+		let evaluate = |input| Decimal::try_from(parse(input).unwrap());
+		assert_eq!(evaluate("trunc(3.789, 2)"), Ok(Decimal::new(378, 2)));
+		assert_eq!(evaluate("trunc(-3.789, 2)"), Ok(Decimal::new(-378, 2)));
+		assert_eq!(evaluate("trunc(3.999)"), Ok(Decimal::new(3, 0)));
+		assert_eq!(evaluate("trunc(-3.999)"), Ok(Decimal::new(-3, 0)));
+		assert_eq!(evaluate("trunc(1.5, 10)"), Ok(Decimal::new(15, 1)));
+		for input in ["trunc(3.789, -1)", "trunc(3.789, 0.5)"] {
+			assert_eq!(
+				evaluate(input),
+				Err(Error::Undefined(
+					"Decimal places must be a non-negative integer"
+				))
+			);
+		}
+	}
+
+	#[test]
+	fn round_invalid_decimal_places() {
+		for decimal_places in [Decimal::NEGATIVE_ONE, Decimal::new(15, 1)] {
+			let error: Result<Decimal, Error> =
+				Node::Expr(Expr::Round(Decimal::ONE.into(), decimal_places.into()).into())
+					.try_into();
+			assert!(error.is_err());
+		}
+	}
+
+	#[test]
+	fn min() {
+		assert_eq!(
+			Decimal::ONE,
+			Node::Expr(
+				Expr::Min(vec![
+					Decimal::new(3, 0).into(),
+					Decimal::ONE.into(),
+					Decimal::TWO.into()
+				])
+				.into()
+			)
+			.try_into()
+			.unwrap()
+		);
+	}
+
+	#[test]
+	fn max() {
+		assert_eq!(
+			Decimal::new(3, 0),
+			Node::Expr(
+				Expr::Max(vec![
+					Decimal::new(3, 0).into(),
+					Decimal::ONE.into(),
+					Decimal::TWO.into()
+				])
+				.into()
+			)
+			.try_into()
+			.unwrap()
+		);
+	}
+
+	#[test]
+	fn min_empty() {
+		let error: Result<Decimal, Error> = Node::Expr(Expr::Min(vec![]).into()).try_into();
+		assert!(error.is_err());
+	}
+
+	#[test]
+	fn clamp() {
+		let evaluate = |input| Decimal::try_from(parse(input).unwrap());
+		assert_eq!(evaluate("clamp(5, 1, 10)"), Ok(Decimal::new(5, 0)));
+		assert_eq!(evaluate("clamp(0, 1, 10)"), Ok(Decimal::ONE));
+		assert_eq!(evaluate("clamp(15, 1, 10)"), Ok(Decimal::TEN));
+		assert_eq!(evaluate("clamp(-1.5, -2, -2)"), Ok(Decimal::new(-2, 0)));
+		assert_eq!(
+			evaluate("clamp(5, 10, 1)"),
+			Err(Error::InvalidArguments("clamp".to_string()))
+		);
+	}
+
+	#[test]
+	fn operands() {
+		let expr = Expr::Sub(Decimal::ONE.into(), Decimal::TWO.into());
+		assert_eq!(expr.name(), "Sub");
+		assert_eq!(
+			expr.operands(),
+			[&Node::Value(Decimal::ONE), &Node::Value(Decimal::TWO)]
+		);
+	}
+
+	#[test]
+	fn try_map_operands() {
+		let expr = Expr::Max(vec![Decimal::ONE.into(), Decimal::TWO.into()]);
+		let mapped: Result<Expr, ()> = expr.try_map_operands(|node| match node {
+			Node::Value(value) => Ok(Node::Value(value * Decimal::TEN)),
+			node => Ok(node),
+		});
+		assert_eq!(
+			mapped,
+			Ok(Expr::Max(vec![
+				Decimal::TEN.into(),
+				Decimal::new(20, 0).into()
+			]))
+		);
+		assert_eq!(
+			Expr::Neg(Decimal::ONE.into()).try_map_operands(|_| Err(())),
+			Err(())
+		);
+	}
+
+	#[test]
+	fn percent() {
+		assert_eq!(
+			Decimal::new(5, 1),
+			Node::Expr(Expr::Percent(Decimal::new(50, 0).into()).into())
+				.try_into()
+				.unwrap()
+		);
+	}
+
+	#[test]
+	fn error_display() {
+		assert_eq!(Error::DivisionByZero.to_string(), "Error: Division by zero");
+		assert_eq!(
+			Error::Overflow.to_string(),
+			"Error: Exceeds maximum possible value"
+		);
+		assert_eq!(
+			Error::InvalidArguments("clamp".to_string()).to_string(),
+			"Error: Invalid arguments for clamp"
+		);
+	}
+
+	#[cfg(feature = "serde")]
+	#[test]
+	fn serde_round_trip() {
+		let node = parse("1 + 2 * 3").unwrap();
+		let json = serde_json::to_string(&node).unwrap();
+		assert_eq!(
+			json,
+			r#"{"Expr":{"Add":[{"Value":"1"},{"Expr":{"Mul":[{"Value":"2"},{"Value":"3"}]}}]}}"#
+		);
+		let node: Node = serde_json::from_str(&json).unwrap();
+		assert_eq!(Decimal::try_from(node), Ok(Decimal::new(7, 0)));
+	}
+
+	#[test]
+	fn pow() {
+		let pow = |lhs: Decimal, rhs: Decimal| {
+			Decimal::try_from(Node::Expr(Expr::Pow(lhs.into(), rhs.into()).into()))
+		};
+		assert_eq!(pow(Decimal::TWO, Decimal::TEN), Ok(Decimal::new(1024, 0)));
+		assert_eq!(
+			pow(Decimal::TWO, Decimal::NEGATIVE_ONE),
+			Ok(Decimal::new(5, 1))
+		);
+		assert_eq!(
+			pow(Decimal::NEGATIVE_ONE, Decimal::new(3, 0)),
+			Ok(Decimal::NEGATIVE_ONE)
+		);
+		let three = pow(Decimal::new(9, 0), Decimal::new(5, 1)).unwrap();
+		assert!((three - Decimal::new(3, 0)).abs() < Decimal::new(1, 7));
+		assert_eq!(
+			pow(Decimal::ZERO, Decimal::NEGATIVE_ONE),
+			Err(Error::DivisionByZero)
+		);
+		assert!(matches!(
+			pow(Decimal::NEGATIVE_ONE, Decimal::new(5, 1)),
+			Err(Error::Undefined(_))
+		));
+		assert_eq!(
+			pow(Decimal::TEN, Decimal::ONE_HUNDRED),
+			Err(Error::Overflow)
+		);
+		assert_eq!(
+			pow(-Decimal::TEN, Decimal::new(101, 0)),
+			Err(Error::Underflow)
+		);
+		// Integer and square root powers are exact
+		assert_eq!(
+			pow(Decimal::new(4, 0), Decimal::new(5, 1)),
+			Ok(Decimal::TWO)
+		);
+		assert_eq!(
+			pow(Decimal::TWO, Decimal::new(5, 1)),
+			super::sqrt(Decimal::TWO)
+		);
+		assert_eq!(
+			pow(Decimal::new(-2, 0), Decimal::new(-3, 0)),
+			Ok(Decimal::new(-125, 3))
+		);
+		// Powers too small to represent round to zero
+		assert_eq!(pow(Decimal::TWO, Decimal::new(-100, 0)), Ok(Decimal::ZERO));
+		assert_eq!(
+			pow(Decimal::new(5, 1), Decimal::ONE_HUNDRED),
+			Ok(Decimal::ZERO)
+		);
+		assert_eq!(pow(Decimal::TWO, Decimal::new(-1005, 1)), Ok(Decimal::ZERO));
+		assert_eq!(
+			pow(Decimal::new(5, 1), Decimal::new(-200, 0)),
+			Err(Error::Overflow)
+		);
+	}
+
+	#[test]
+	fn factorial() {
+		let factorial =
+			|value: Decimal| Decimal::try_from(Node::Expr(Expr::Factorial(value.into()).into()));
+		assert_eq!(factorial(Decimal::new(5, 0)), Ok(Decimal::new(120, 0)));
+		assert_eq!(factorial(Decimal::ZERO), Ok(Decimal::ONE));
+		assert_eq!(factorial(Decimal::ONE), Ok(Decimal::ONE));
+		assert_eq!(factorial(Decimal::NEGATIVE_ONE), Err(Error::NotAnInteger));
+		assert_eq!(factorial(Decimal::new(15, 1)), Err(Error::NotAnInteger));
+		assert_eq!(factorial(Decimal::new(100, 0)), Err(Error::Overflow));
+		assert_eq!(factorial(Decimal::MAX), Err(Error::Overflow));
+	}
+
+	#[test]
+	fn ln() {
+		let ln = |value: Decimal| Decimal::try_from(Node::Expr(Expr::Ln(value.into()).into()));
+		assert_eq!(ln(Decimal::ONE), Ok(Decimal::ZERO));
+		assert!((ln(Decimal::E).unwrap() - Decimal::ONE).abs() < Decimal::new(1, 20));
+		assert!(matches!(ln(Decimal::ZERO), Err(Error::Undefined(_))));
+		assert!(matches!(
+			ln(Decimal::NEGATIVE_ONE),
+			Err(Error::Undefined(_))
+		));
+	}
+
+	#[test]
+	fn log() {
+		let log = |base: Decimal, value: Decimal| {
+			Decimal::try_from(Node::Expr(Expr::Log(base.into(), value.into()).into()))
+		};
+		let approx = |value: Result<Decimal, Error>, expected: Decimal| {
+			(value.unwrap() - expected).abs() < Decimal::new(1, 20)
+		};
+		assert!(approx(
+			log(Decimal::TEN, Decimal::ONE_THOUSAND),
+			Decimal::new(3, 0)
+		));
+		assert!(approx(
+			log(Decimal::TWO, Decimal::new(8, 0)),
+			Decimal::new(3, 0)
+		));
+		assert!(approx(
+			log(Decimal::new(4, 0), Decimal::TWO),
+			Decimal::new(5, 1)
+		));
+		assert_eq!(
+			log(Decimal::ONE, Decimal::TEN),
+			Err(Error::Undefined("Logarithm to the base one is undefined"))
+		);
+		assert!(matches!(
+			log(Decimal::NEGATIVE_ONE, Decimal::TEN),
+			Err(Error::Undefined(_))
+		));
+		assert!(matches!(
+			log(Decimal::ZERO, Decimal::TEN),
+			Err(Error::Undefined(_))
+		));
+		assert!(matches!(
+			log(Decimal::TEN, Decimal::ZERO),
+			Err(Error::Undefined(_))
+		));
+	}
+
+	#[test]
+	fn comparisons() {
+		let one = || Node::Value(Decimal::ONE);
+		let two = || Node::Value(Decimal::TWO);
+		for (expr, value) in [
+			(Expr::Eq(one(), one()), Decimal::ONE),
+			(Expr::Eq(one(), Decimal::new(10, 1).into()), Decimal::ONE),
+			(Expr::Ne(one(), one()), Decimal::ZERO),
+			(Expr::Lt(one(), two()), Decimal::ONE),
+			(Expr::Le(two(), one()), Decimal::ZERO),
+			(Expr::Gt(two(), one()), Decimal::ONE),
+			(Expr::Ge(one(), two()), Decimal::ZERO),
+		] {
+			assert_eq!(Decimal::try_from(expr), Ok(value));
+		}
+	}
+
+	#[test]
+	fn try_from_str() {
+		assert_eq!(
+			Node::try_from("1 + 2").unwrap(),
+			Node::Expr(Expr::Add(Decimal::ONE.into(), Decimal::TWO.into()).into())
+		);
+		assert_eq!(
+			Node::try_from("1.5".to_string()).unwrap(),
+			Node::Value(Decimal::new(15, 1))
+		);
+		assert!(Node::try_from("(").is_err());
+	}
+
+	#[test]
+	fn evaluate_with_context() {
+		let mut context = Context::new();
+		context.set("x".to_string(), Decimal::TEN);
+		let node =
+			Node::Expr(Expr::Mul(Node::Identifier("x".to_string()), Decimal::TWO.into()).into());
+		assert_eq!(
+			super::evaluate_with_context(node.clone(), &context),
+			Ok(Decimal::new(20, 0))
+		);
+		assert_eq!(
+			super::evaluate_with_context(node, &Context::new()),
+			Err(Error::UndefinedVariable("x".to_string()))
+		);
+		assert_eq!(
+			Decimal::try_from(Node::Identifier("TAU".to_string())),
+			Ok(Decimal::TWO_PI)
+		);
+	}
+
+	#[test]
+	fn counts() {
+		let node = parse("max(1, x * 3, -(4 - 5))").unwrap();
+		assert_eq!(node.count_values(), 4);
+		assert_eq!(node.count_ops(), 4);
+		assert_eq!(Node::Value(Decimal::ONE).count_values(), 1);
+		assert_eq!(Node::Identifier("x".to_string()).count_ops(), 0);
+	}
+
+	#[test]
+	fn depth() {
+		assert_eq!(Node::Value(Decimal::ONE).depth(), 0);
+		assert_eq!(parse("-1").unwrap().depth(), 1);
+		// Balanced
+		assert_eq!(parse("(1 + 2) * (3 + 4)").unwrap().depth(), 2);
+		assert_eq!(parse("max(1, 2 * 3, -(4 - 5))").unwrap().depth(), 3);
+		// Right-skewed
+		assert_eq!(parse("1 ^ 2 ^ 3 ^ 4 ^ 5").unwrap().depth(), 4);
+		assert_eq!(parse("(((((1 - 1) - 1) - 1) - 1) - 1)").unwrap().depth(), 5);
+		// Additions are flattened into one sum
+		assert_eq!(parse("(((((1 + 1) + 1) + 1) + 1) + 1)").unwrap().depth(), 1);
+	}
+
+	#[test]
+	fn map_values() {
+		let ten = Decimal::new(10, 0);
+		let node = parse("(1 + 2) * 3")
+			.unwrap()
+			.map_values(|value| value * ten);
+		assert_eq!(node.to_string(), "((10 + 20) * 30)");
+		assert_eq!(Decimal::try_from(node), Ok(Decimal::new(900, 0)));
+
+		let node = parse("x + 1").unwrap().map_values(|value| -value);
+		assert_eq!(node.to_string(), "(x + -1)");
+	}
+
+	#[test]
+	fn ordering() {
+		let mut nodes: Vec<_> = ["3", "1 + 1", "1 / 0", "2", "2 * 0.25", "x", "-PI"]
+			.map(|input| parse(input).unwrap())
+			.into();
+		nodes.sort_by_key(Node::sort_key);
+		let sorted: Vec<_> = nodes.iter().map(Node::to_string).collect();
+		// Sorting is stable, so nodes with the same value and those failing to evaluate keep their order
+		assert_eq!(
+			sorted,
+			["-PI", "(2 * 0.25)", "(1 + 1)", "2", "3", "(1 / 0)", "x"]
+		);
+		let compare = |lhs, rhs| parse(lhs).unwrap().partial_cmp(&parse(rhs).unwrap());
+		assert_eq!(compare("1 + 1", "3"), Some(Ordering::Less));
+		assert_eq!(compare("-PI", "-4"), Some(Ordering::Greater));
+		assert_eq!(compare("1 + 1", "1 + 1"), Some(Ordering::Equal));
+		assert_eq!(compare("1 + 1", "2"), None);
+		assert_eq!(compare("1 / 0", "1"), None);
+		assert_eq!(compare("x", "x"), Some(Ordering::Equal));
+		assert_eq!(compare("x", "y"), None);
+	}
+
+	#[test]
+	fn substitute() {
+		let x = || Node::Identifier("x".to_string());
+		let node = Node::Expr(Expr::Mul(x(), x()).into());
+		let node = node.substitute("x", Node::Value(Decimal::new(3, 0)));
+		assert_eq!(Decimal::try_from(node), Ok(Decimal::new(9, 0)));
+
+		// Replacements are nodes of their own, and other names are left alone
+		let node = parse("max(x, y) - xx").unwrap();
+		let node = node.substitute("x", parse("y + 1").unwrap());
+		assert_eq!(node.to_string(), "(max((y + 1), y) - xx)");
+	}
+
+	#[test]
+	fn map_values_fallible() {
+		let node = parse("max(1, 2 ^ 3)").unwrap();
+		let doubled = node
+			.clone()
+			.map_values_fallible(|value| value.checked_mul(Decimal::TWO).ok_or(Error::Overflow));
+		assert_eq!(doubled.unwrap().to_string(), "max(2, (4 ^ 6))");
+		let node = parse("1 + 2 * 3").unwrap();
+		let result = node.map_values_fallible(|value| {
+			if value == Decimal::TWO {
+				Err(Error::Overflow)
+			} else {
+				Ok(value)
+			}
+		});
+		assert_eq!(result, Err(Error::Overflow));
+	}
+
+	#[test]
+	fn integers() {
+		let node = |input| parse(input).unwrap();
+		assert_eq!(i64::try_from(node("6 * 7")), Ok(42));
+		assert_eq!(i64::try_from(node("-3 / 1.5")), Ok(-2));
+		assert_eq!(u64::try_from(node("2 ^ 64 - 1")), Ok(u64::MAX));
+		assert_eq!(
+			i64::try_from(node("1 / 4")),
+			Err(ConversionError::HasFractionalPart)
+		);
+		assert_eq!(
+			i64::try_from(node("2 ^ 63")),
+			Err(ConversionError::OutOfRange)
+		);
+		assert_eq!(u64::try_from(node("-1")), Err(ConversionError::OutOfRange));
+		assert_eq!(
+			u64::try_from(node("1 / 0")),
+			Err(ConversionError::Evaluation(Error::DivisionByZero))
+		);
+	}
+
+	#[test]
+	fn f64() {
+		assert_eq!(f64::from(parse("1 / 4").unwrap()), 0.25);
+		assert_eq!(f64::from(parse("PI").unwrap()), std::f64::consts::PI);
+		assert!(f64::from(parse("1 / 0").unwrap()).is_nan());
+		assert!(f64::from(parse("x").unwrap()).is_nan());
+		assert_eq!(
+			Node::try_from(-2.5).unwrap(),
+			Node::Value(Decimal::new(-25, 1))
+		);
+		let max = Decimal::MAX.to_f64().unwrap();
+		assert_eq!(f64::from(Node::try_from(max).unwrap()), max);
+		for value in [f64::INFINITY, f64::NEG_INFINITY, f64::NAN, f64::MAX] {
+			assert!(Node::try_from(value).is_err(), "{value}");
+		}
+	}
+
+	#[test]
+	fn rem() {
+		let rem = |lhs: i64, rhs: i64| {
+			Decimal::try_from(Node::Expr(
+				Expr::Rem(Decimal::from(lhs).into(), Decimal::from(rhs).into()).into(),
+			))
+		};
+		// Floored, unlike the truncated remainder of `Decimal`
+		assert_eq!(rem(-7, 3), Ok(Decimal::TWO));
+		assert_eq!(Decimal::from(-7) % Decimal::from(3), Decimal::NEGATIVE_ONE);
+		assert_eq!(rem(7, -3), Ok(Decimal::from(-2)));
+		assert_eq!(rem(-7, -3), Ok(Decimal::NEGATIVE_ONE));
+		assert_eq!(rem(7, 3), Ok(Decimal::ONE));
+		assert_eq!(rem(-6, 3), Ok(Decimal::ZERO));
+		assert_eq!(rem(1, 0), Err(Error::DivisionByZero));
+		let node = parse("rem(-7.5, 2)").unwrap();
+		assert_eq!(Decimal::try_from(node), Ok(Decimal::new(5, 1)));
+	}
+
+	#[test]
+	fn operators() {
+		let one = || Node::Value(Decimal::ONE);
+		let two = || Node::Value(Decimal::TWO);
+		assert_eq!(one() + two(), parse("1 + 2").unwrap());
+		assert_eq!(one() - two() * one(), parse("1 - 2 * 1").unwrap());
+		assert_eq!(-(one() / two()), parse("-(1 / 2)").unwrap());
+		assert_eq!(
+			Decimal::try_from(one() / (two() - two())),
+			Err(Error::DivisionByZero)
+		);
+	}
+
+	#[test]
+	fn gcd_and_lcm() {
+		let evaluate = |input| Decimal::try_from(parse(input).unwrap());
+		assert_eq!(evaluate("gcd(12, 8)"), Ok(Decimal::new(4, 0)));
+		assert_eq!(evaluate("lcm(4, 6)"), Ok(Decimal::new(12, 0)));
+		assert_eq!(evaluate("gcd(0, 5)"), Ok(Decimal::new(5, 0)));
+		assert_eq!(evaluate("gcd(0, 0)"), Ok(Decimal::ZERO));
+		assert_eq!(evaluate("lcm(0, 5)"), Ok(Decimal::ZERO));
+		assert_eq!(evaluate("gcd(2.0, 4)"), Ok(Decimal::TWO));
+		assert_eq!(evaluate("gcd(1.5, 2)"), Err(Error::NotAnInteger));
+		assert_eq!(evaluate("lcm(-4, 6)"), Err(Error::NotAnInteger));
+		assert_eq!(
+			evaluate("lcm(79228162514264337593543950335, 2)"),
+			Err(Error::Overflow)
+		);
+	}
+
+	#[test]
+	fn bitwise() {
+		let evaluate = |input| Decimal::try_from(parse(input).unwrap());
+		assert_eq!(evaluate("0xFF & 0x0F"), Ok(Decimal::new(15, 0)));
+		assert_eq!(evaluate("0xFF & 0x0F == 15"), Ok(Decimal::ONE));
+		assert_eq!(evaluate("bitor(1, 2)"), Ok(Decimal::new(3, 0)));
+		assert_eq!(evaluate("bitxor(6, 3)"), Ok(Decimal::new(5, 0)));
+		assert_eq!(evaluate("~0"), Ok(Decimal::MAX));
+		assert_eq!(evaluate("~~5"), Ok(Decimal::new(5, 0)));
+		assert_eq!(evaluate("~0 & 0xF0"), Ok(Decimal::new(240, 0)));
+		// Looser than arithmetic, so this is `6 & (1 + 2)`
+		assert_eq!(evaluate("6 & 1 + 2"), Ok(Decimal::TWO));
+		assert_eq!(evaluate("1.5 & 1"), Err(Error::NotAnInteger));
+		assert_eq!(evaluate("~-1"), Err(Error::NotAnInteger));
+	}
+
+	#[test]
+	fn default() {
+		assert_eq!(Node::default(), Node::Value(Decimal::ZERO));
+		assert_eq!(Decimal::try_from(Node::default()), Ok(Decimal::ZERO));
+	}
 }
@@ -0,0 +1,87 @@
+use crate::parser::FUNCTION_NAMES;
+use alloc::{collections::BTreeMap, string::String};
+use rust_decimal::Decimal;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+/// Session state shared between consecutive calculations.
+pub struct Context {
+	/// Result of the last successful calculation
+	pub last_result: Option<Decimal>,
+	/// Values of named variables
+	pub variables: BTreeMap<String, Decimal>,
+}
+
+impl Context {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Look up the value of a constant or a variable by its name.
+	/// Both `ans` and `_` refer to the last result,
+	/// and `PI`, `E` and `TAU` are mathematical constants.
+	pub fn get(&self, name: &str) -> Option<Decimal> {
+		match name {
+			"ans" | "_" => self.last_result,
+			"PI" => Some(Decimal::PI),
+			"E" => Some(Decimal::E),
+			"TAU" => Some(Decimal::TWO_PI),
+			name => self.variables.get(name).copied(),
+		}
+	}
+
+	/// Determine whether a variable name is built in and cannot be assigned to,
+	/// including the names of functions.
+	pub fn is_reserved(name: &str) -> bool {
+		matches!(name, "ans" | "_" | "PI" | "E" | "TAU") || FUNCTION_NAMES.contains(&name)
+	}
+
+	/// Assign a value to a named variable.
+	pub fn set(&mut self, name: String, value: Decimal) {
+		self.variables.insert(name, value);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Context;
+	use rust_decimal::Decimal;
+
+	#[test]
+	fn last_result() {
+		let mut context = Context::new();
+		assert_eq!(context.get("ans"), None);
+		context.last_result = Some(Decimal::ONE);
+		assert_eq!(context.get("ans"), Some(Decimal::ONE));
+		assert_eq!(context.get("_"), Some(Decimal::ONE));
+		assert_eq!(context.get("x"), None);
+	}
+
+	#[test]
+	fn variables() {
+		let mut context = Context::new();
+		context.set("x".to_string(), Decimal::TWO);
+		assert_eq!(context.get("x"), Some(Decimal::TWO));
+		context.set("x".to_string(), Decimal::TEN);
+		assert_eq!(context.get("x"), Some(Decimal::TEN));
+		assert_eq!(context.get("y"), None);
+	}
+
+	#[test]
+	fn reserved() {
+		assert!(Context::is_reserved("ans"));
+		assert!(Context::is_reserved("_"));
+		assert!(Context::is_reserved("sqrt"));
+		assert!(Context::is_reserved("sgn"));
+		assert!(!Context::is_reserved("x"));
+		assert!(!Context::is_reserved("sqrt2"));
+	}
+
+	#[test]
+	fn constants() {
+		let context = Context::new();
+		assert_eq!(context.get("PI"), Some(Decimal::PI));
+		assert_eq!(context.get("E"), Some(Decimal::E));
+		assert_eq!(context.get("TAU"), Some(Decimal::TWO_PI));
+		assert!(Context::is_reserved("PI"));
+	}
+}
@@ -0,0 +1,66 @@
+use super::Node;
+use alloc::string::String;
+use core::fmt::Write;
+
+/// Render a syntax tree as a GraphViz DOT digraph, such as for `dot -Tpng`.
+/// Expressions are labeled with their names, values with their decimal strings
+/// and identifiers with their names.
+pub fn to_dot(node: &Node) -> String {
+	let mut output = String::from("digraph ast {\n");
+	write_node(&mut output, node, &mut 0);
+	output.push('}');
+	output
+}
+
+/// Write a node with its subtree and return its identifier.
+/// Identifiers are assigned in depth first order.
+fn write_node(output: &mut String, node: &Node, next_id: &mut usize) -> usize {
+	let id = *next_id;
+	*next_id += 1;
+	match node {
+		Node::Value(value) => writeln!(output, "\tn{id} [label=\"{value}\"];").unwrap(),
+		Node::Identifier(name) => writeln!(output, "\tn{id} [label=\"{name}\"];").unwrap(),
+		Node::Expr(expr) => {
+			writeln!(output, "\tn{id} [label=\"{}\"];", expr.name()).unwrap();
+			for operand in expr.operands() {
+				let operand_id = write_node(output, operand, next_id);
+				writeln!(output, "\tn{id} -> n{operand_id};").unwrap();
+			}
+		}
+	}
+	id
+}
+
+#[cfg(test)]
+mod tests {
+	use super::to_dot;
+	use crate::parser::parse;
+
+	#[test]
+	fn dot() {
+		let dot = to_dot(&parse("1 + 2 * 3").unwrap());
+		assert!(dot.starts_with("digraph ast {\n"));
+		assert!(dot.ends_with('}'));
+		for line in [
+			"n0 [label=\"Add\"];",
+			"n1 [label=\"1\"];",
+			"n2 [label=\"Mul\"];",
+			"n3 [label=\"2\"];",
+			"n4 [label=\"3\"];",
+			"n0 -> n1;",
+			"n0 -> n2;",
+			"n2 -> n3;",
+			"n2 -> n4;",
+		] {
+			assert!(dot.contains(line), "{line}");
+		}
+	}
+
+	#[test]
+	fn value() {
+		assert_eq!(
+			to_dot(&parse("-1.5").unwrap()),
+			"digraph ast {\n\tn0 [label=\"Neg\"];\n\tn1 [label=\"1.5\"];\n\tn0 -> n1;\n}"
+		);
+	}
+}
@@ -0,0 +1,77 @@
+use super::Node;
+use core::convert::Infallible;
+use rust_decimal::Decimal;
+
+/// Replace every expression whose operands are all values with its result,
+/// working bottom up. Expressions which fail to evaluate are kept as they are,
+/// so the error is reported when the whole tree is evaluated.
+pub fn fold_constants(node: Node) -> Node {
+	match node {
+		Node::Value(_) | Node::Identifier(_) => node,
+		Node::Expr(expr) => {
+			let expr = match expr
+				.try_map_operands(|operand| Ok::<_, Infallible>(fold_constants(operand)))
+			{
+				Ok(expr) => expr,
+				Err(infallible) => match infallible {},
+			};
+			if expr
+				.operands()
+				.iter()
+				.all(|operand| matches!(operand, Node::Value(_)))
+			{
+				match Decimal::try_from(expr.clone()) {
+					Ok(value) => Node::Value(value),
+					Err(_) => Node::Expr(expr.into()),
+				}
+			} else {
+				Node::Expr(expr.into())
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::fold_constants;
+	use crate::{
+		engine::{Expr, Node},
+		parser::parse,
+	};
+	use rust_decimal::Decimal;
+
+	#[test]
+	fn fold() {
+		let node = parse("2 + 3").unwrap();
+		assert_eq!(
+			node,
+			Node::Expr(Expr::Add(Decimal::TWO.into(), Decimal::new(3, 0).into()).into())
+		);
+		assert_eq!(fold_constants(node), Node::Value(Decimal::new(5, 0)));
+		assert_eq!(
+			fold_constants(parse("max(1, 2) * -(3 - 1)").unwrap()),
+			Node::Value(Decimal::new(-4, 0))
+		);
+	}
+
+	#[test]
+	fn keep_errors() {
+		let node = parse("(1 + 1) / (2 - 2) + 3 * 4").unwrap();
+		assert_eq!(
+			fold_constants(node),
+			Node::Expr(
+				Expr::Add(
+					Node::Expr(Expr::Div(Decimal::TWO.into(), Decimal::ZERO.into()).into()),
+					Decimal::new(12, 0).into()
+				)
+				.into()
+			)
+		);
+	}
+
+	#[test]
+	fn empty_operands() {
+		let node = Node::Expr(Expr::Min(vec![]).into());
+		assert_eq!(fold_constants(node.clone()), node);
+	}
+}
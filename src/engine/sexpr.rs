@@ -0,0 +1,315 @@
+use super::{Expr, Node};
+use crate::parser::DEFAULT_MAX_DEPTH;
+use alloc::{
+	string::{String, ToString},
+	vec::Vec,
+};
+use core::{fmt, iter::Peekable, str::FromStr};
+use rust_decimal::Decimal;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+/// Error parsing an S-expression with `parse_sexpr`.
+pub enum SexprError {
+	/// The input ends before the last list is closed
+	UnexpectedEnd,
+	/// A token which is not valid at its position
+	UnexpectedToken(String),
+	/// The head of a list is not a known operator
+	UnknownOperator(String),
+	/// The operator does not take that many operands
+	InvalidArgumentCount(String),
+	/// Lists are nested deeper than the given number of levels
+	MaxDepthExceeded(usize),
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SexprError {}
+
+impl fmt::Display for SexprError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			SexprError::UnexpectedEnd => write!(f, "Error: Unterminated list"),
+			SexprError::UnexpectedToken(token) => write!(f, "Error: Unexpected {token}"),
+			SexprError::UnknownOperator(symbol) => write!(f, "Error: Unknown operator {symbol}"),
+			SexprError::InvalidArgumentCount(symbol) => {
+				write!(f, "Error: Invalid number of arguments for {symbol}")
+			}
+			SexprError::MaxDepthExceeded(max) => {
+				write!(f, "Error: Lists are nested deeper than {max} levels")
+			}
+		}
+	}
+}
+
+/// Render a syntax tree as an S-expression with prefix operators, such as `(+ 1 (* 2 3))`.
+/// Values are written as decimal strings and identifiers by their names.
+pub fn to_sexpr(node: &Node) -> String {
+	match node {
+		Node::Value(value) => value.to_string(),
+		Node::Identifier(name) => name.clone(),
+		Node::Expr(expr) => {
+			let mut output = String::from("(");
+			output.push_str(symbol(expr));
+			for operand in expr.operands() {
+				output.push(' ');
+				output.push_str(&to_sexpr(operand));
+			}
+			output.push(')');
+			output
+		}
+	}
+}
+
+/// Construct a tree from an S-expression written by `to_sexpr`.
+/// Lists may be nested as deeply as `parser::DEFAULT_MAX_DEPTH` allows for expressions.
+pub fn parse_sexpr(input: &str) -> Result<Node, SexprError> {
+	let mut tokens = tokenize(input).peekable();
+	let node = parse_node(&mut tokens, 0)?;
+	match tokens.next() {
+		Some(token) => Err(SexprError::UnexpectedToken(token.to_string())),
+		None => Ok(node),
+	}
+}
+
+/// Split an input into parentheses and atoms between them.
+fn tokenize(input: &str) -> impl Iterator<Item = &str> {
+	input
+		.split_inclusive(['(', ')'])
+		.flat_map(|chunk| match chunk.strip_suffix(['(', ')']) {
+			Some(atoms) => [atoms, &chunk[atoms.len()..]],
+			None => [chunk, ""],
+		})
+		.flat_map(str::split_whitespace)
+}
+
+/// Construct a node within the given number of enclosing lists.
+fn parse_node<'a>(
+	tokens: &mut Peekable<impl Iterator<Item = &'a str>>,
+	depth: usize,
+) -> Result<Node, SexprError> {
+	match tokens.next() {
+		None => Err(SexprError::UnexpectedEnd),
+		Some("(") if depth == DEFAULT_MAX_DEPTH => {
+			Err(SexprError::MaxDepthExceeded(DEFAULT_MAX_DEPTH))
+		}
+		Some("(") => {
+			let symbol = match tokens.next() {
+				None => return Err(SexprError::UnexpectedEnd),
+				Some(token @ ("(" | ")")) => {
+					return Err(SexprError::UnexpectedToken(token.to_string()))
+				}
+				Some(symbol) => symbol,
+			};
+			let mut operands = Vec::new();
+			loop {
+				match tokens.peek() {
+					None => return Err(SexprError::UnexpectedEnd),
+					Some(&")") => {
+						tokens.next();
+						break;
+					}
+					Some(_) => operands.push(parse_node(tokens, depth + 1)?),
+				}
+			}
+			build(symbol, operands).map(|expr| Node::Expr(expr.into()))
+		}
+		Some(")") => Err(SexprError::UnexpectedToken(")".to_string())),
+		Some(atom) => match Decimal::from_str(atom) {
+			Ok(value) => Ok(Node::Value(value)),
+			Err(_) if atom.chars().all(|c| c.is_alphanumeric() || c == '_') => {
+				Ok(Node::Identifier(atom.to_string()))
+			}
+			Err(_) => Err(SexprError::UnexpectedToken(atom.to_string())),
+		},
+	}
+}
+
+/// Operator of an expression at the head of its list.
+fn symbol(expr: &Expr) -> &'static str {
+	match expr {
+		Expr::Identity(_) => "id",
+		Expr::Add(..) | Expr::Sum(_) => "+",
+		Expr::Sub(..) => "-",
+		Expr::Mul(..) => "*",
+		Expr::Div(..) => "/",
+		Expr::Pow(..) => "^",
+		Expr::Neg(_) => "neg",
+		Expr::Abs(_) => "abs",
+		Expr::Sqrt(_) => "sqrt",
+		Expr::Floor(_) => "floor",
+		Expr::Ceil(_) => "ceil",
+		Expr::Signum(_) => "sign",
+		Expr::Round(..) => "round",
+		Expr::Truncate(..) => "trunc",
+		Expr::Min(_) => "min",
+		Expr::Max(_) => "max",
+		Expr::Clamp(..) => "clamp",
+		Expr::Percent(_) => "percent",
+		Expr::Factorial(_) => "fact",
+		Expr::Ln(_) => "ln",
+		Expr::Log(..) => "log",
+		Expr::Rem(..) => "rem",
+		Expr::Gcd(..) => "gcd",
+		Expr::Lcm(..) => "lcm",
+		Expr::BitAnd(..) => "bitand",
+		Expr::BitOr(..) => "bitor",
+		Expr::BitXor(..) => "bitxor",
+		Expr::BitNot(_) => "bitnot",
+		Expr::Eq(..) => "=",
+		Expr::Ne(..) => "!=",
+		Expr::Lt(..) => "<",
+		Expr::Le(..) => "<=",
+		Expr::Gt(..) => ">",
+		Expr::Ge(..) => ">=",
+	}
+}
+
+/// Build the expression of an operator from its operands, the inverse of `symbol`.
+fn build(symbol: &str, operands: Vec<Node>) -> Result<Expr, SexprError> {
+	let expr = match symbol {
+		"+" if operands.len() == 2 => binary(operands, Expr::Add),
+		"+" => Some(Expr::Sum(operands)),
+		"-" => binary(operands, Expr::Sub),
+		"*" => binary(operands, Expr::Mul),
+		"/" => binary(operands, Expr::Div),
+		"^" => binary(operands, Expr::Pow),
+		"id" => unary(operands, Expr::Identity),
+		"neg" => unary(operands, Expr::Neg),
+		"abs" => unary(operands, Expr::Abs),
+		"sqrt" => unary(operands, Expr::Sqrt),
+		"floor" => unary(operands, Expr::Floor),
+		"ceil" => unary(operands, Expr::Ceil),
+		"sign" => unary(operands, Expr::Signum),
+		"round" => binary(operands, Expr::Round),
+		"trunc" => binary(operands, Expr::Truncate),
+		"min" if operands.len() >= 2 => Some(Expr::Min(operands)),
+		"max" if operands.len() >= 2 => Some(Expr::Max(operands)),
+		"min" | "max" => None,
+		"clamp" => operands
+			.try_into()
+			.ok()
+			.map(|[value, min, max]: [Node; 3]| Expr::Clamp(value, min, max)),
+		"percent" => unary(operands, Expr::Percent),
+		"fact" => unary(operands, Expr::Factorial),
+		"ln" => unary(operands, Expr::Ln),
+		"log" => binary(operands, Expr::Log),
+		"rem" => binary(operands, Expr::Rem),
+		"gcd" => binary(operands, Expr::Gcd),
+		"lcm" => binary(operands, Expr::Lcm),
+		"bitand" => binary(operands, Expr::BitAnd),
+		"bitor" => binary(operands, Expr::BitOr),
+		"bitxor" => binary(operands, Expr::BitXor),
+		"bitnot" => unary(operands, Expr::BitNot),
+		"=" => binary(operands, Expr::Eq),
+		"!=" => binary(operands, Expr::Ne),
+		"<" => binary(operands, Expr::Lt),
+		"<=" => binary(operands, Expr::Le),
+		">" => binary(operands, Expr::Gt),
+		">=" => binary(operands, Expr::Ge),
+		_ => return Err(SexprError::UnknownOperator(symbol.to_string())),
+	};
+	expr.ok_or_else(|| SexprError::InvalidArgumentCount(symbol.to_string()))
+}
+
+fn unary(operands: Vec<Node>, expr: fn(Node) -> Expr) -> Option<Expr> {
+	let [value] = operands.try_into().ok()?;
+	Some(expr(value))
+}
+
+fn binary(operands: Vec<Node>, expr: fn(Node, Node) -> Expr) -> Option<Expr> {
+	let [lhs, rhs] = operands.try_into().ok()?;
+	Some(expr(lhs, rhs))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{parse_sexpr, to_sexpr, SexprError};
+	use crate::{
+		engine::{Expr, Node},
+		parser::{parse, DEFAULT_MAX_DEPTH},
+	};
+	use rust_decimal::Decimal;
+
+	#[test]
+	fn sexpr() {
+		assert_eq!(to_sexpr(&parse("1 + 2 * 3").unwrap()), "(+ 1 (* 2 3))");
+		assert_eq!(
+			to_sexpr(&parse("-x ^ 2 / max(1.5, |y|, 3!)").unwrap()),
+			"(/ (neg (^ x 2)) (max 1.5 (abs y) (fact 3)))"
+		);
+		assert_eq!(to_sexpr(&Node::Value(Decimal::NEGATIVE_ONE)), "-1");
+		assert_eq!(to_sexpr(&parse("1 + 2 + 3").unwrap()), "(+ 1 2 3)");
+		let identity = Node::Expr(Expr::from(Decimal::ONE).into());
+		assert_eq!(to_sexpr(&identity), "(id 1)");
+		assert_eq!(parse_sexpr("(id 1)"), Ok(identity));
+	}
+
+	#[test]
+	fn round_trip() {
+		let sexpr = to_sexpr(&parse("1 + 2 * 3").unwrap());
+		let node = parse_sexpr(&sexpr).unwrap();
+		assert_eq!(Decimal::try_from(node), Ok(Decimal::new(7, 0)));
+		for input in [
+			"-(1 - 2) ^ 3 % <= round(ln(4), 2) != ~5 & 6",
+			"min(gcd(4, 6), lcm(4, 6), rem(-7, 3), log(2, 8)) > floor(x) == ceil(sqrt(y))",
+			"bitor(1, bitxor(2, 3)) < 0 >= -1",
+			"clamp(x, -1, 1 + 1)",
+			"1 + 2 + (3 + 4) + 5",
+		] {
+			let node = parse(input).unwrap();
+			assert_eq!(parse_sexpr(&to_sexpr(&node)).unwrap(), node, "{input}");
+		}
+		assert_eq!(
+			parse_sexpr(" ( +\n1(neg 2 ) ) ").unwrap(),
+			parse("1 + -2").unwrap()
+		);
+	}
+
+	#[test]
+	fn errors() {
+		assert_eq!(parse_sexpr(""), Err(SexprError::UnexpectedEnd));
+		assert_eq!(parse_sexpr("(+ 1 2"), Err(SexprError::UnexpectedEnd));
+		assert_eq!(
+			parse_sexpr("(+ 1 2))"),
+			Err(SexprError::UnexpectedToken(")".to_string()))
+		);
+		assert_eq!(
+			parse_sexpr("((+) 1)"),
+			Err(SexprError::UnexpectedToken("(".to_string()))
+		);
+		assert_eq!(
+			parse_sexpr("(+ 1 2) 3"),
+			Err(SexprError::UnexpectedToken("3".to_string()))
+		);
+		assert_eq!(
+			parse_sexpr("(+ 1 #)"),
+			Err(SexprError::UnexpectedToken("#".to_string()))
+		);
+		assert_eq!(
+			parse_sexpr("(add 1 2)"),
+			Err(SexprError::UnknownOperator("add".to_string()))
+		);
+		assert_eq!(
+			parse_sexpr("(neg 1 2)"),
+			Err(SexprError::InvalidArgumentCount("neg".to_string()))
+		);
+		assert_eq!(
+			parse_sexpr("(max 1)"),
+			Err(SexprError::InvalidArgumentCount("max".to_string()))
+		);
+		let nested = |depth| format!("{}1{}", "(neg ".repeat(depth), ")".repeat(depth));
+		assert_eq!(
+			parse_sexpr(&nested(DEFAULT_MAX_DEPTH)).map(|node| node.depth()),
+			Ok(DEFAULT_MAX_DEPTH)
+		);
+		assert_eq!(
+			parse_sexpr(&nested(DEFAULT_MAX_DEPTH + 1)),
+			Err(SexprError::MaxDepthExceeded(DEFAULT_MAX_DEPTH))
+		);
+		assert_eq!(
+			parse_sexpr(&"(+ 1 ".repeat(200_000)),
+			Err(SexprError::MaxDepthExceeded(DEFAULT_MAX_DEPTH))
+		);
+	}
+}
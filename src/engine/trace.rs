@@ -0,0 +1,68 @@
+use super::{Error, Node};
+use alloc::{
+	format,
+	string::{String, ToString},
+	vec::Vec,
+};
+use rust_decimal::Decimal;
+
+/// Evaluate a node like `Decimal::try_from` does, but also record
+/// every reduction step as a human-readable string, such as `Add(2, 3) → 5`.
+/// Steps are recorded from the innermost expressions outwards.
+pub fn evaluate_traced(node: Node) -> Result<(Decimal, Vec<String>), Error> {
+	let mut steps = Vec::new();
+	let value = reduce(node, &mut steps)?;
+	Ok((value, steps))
+}
+
+/// Recursively reduce a node to its value, appending each step to the trace.
+fn reduce(node: Node, steps: &mut Vec<String>) -> Result<Decimal, Error> {
+	match node {
+		Node::Value(value) => Ok(value),
+		// Looking up a name is not a reduction step
+		Node::Identifier(_) => Decimal::try_from(node),
+		Node::Expr(expr) => {
+			let expr = expr.try_map_operands(|operand| reduce(operand, steps).map(Node::Value))?;
+			let operands: Vec<String> = expr.operands().iter().map(ToString::to_string).collect();
+			let step = format!("{}({})", expr.name(), operands.join(", "));
+			let value = Decimal::try_from(expr)?;
+			steps.push(format!("{step} → {value}"));
+			Ok(value)
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::evaluate_traced;
+	use crate::parser::parse;
+	use rust_decimal::Decimal;
+
+	#[test]
+	fn value() {
+		let (value, steps) = evaluate_traced(parse("1").unwrap()).unwrap();
+		assert_eq!(value, Decimal::ONE);
+		assert!(steps.is_empty());
+	}
+
+	#[test]
+	fn multiple_operators() {
+		let (value, steps) = evaluate_traced(parse("2 + 3 * -1 - max(1, 2)").unwrap()).unwrap();
+		assert_eq!(value, Decimal::new(-3, 0));
+		assert_eq!(
+			steps,
+			[
+				"Neg(1) → -1",
+				"Mul(3, -1) → -3",
+				"Add(2, -3) → -1",
+				"Max(1, 2) → 2",
+				"Sub(-1, 2) → -3",
+			]
+		);
+	}
+
+	#[test]
+	fn error() {
+		assert!(evaluate_traced(parse("1 + 1 / 0").unwrap()).is_err());
+	}
+}
@@ -0,0 +1,246 @@
+use super::{Expr, Node};
+use crate::parser::{Associativity, Operator};
+use alloc::string::String;
+use core::fmt::Write;
+
+/// Precedence of operands which never need parentheses,
+/// such as values, function calls and absolute values.
+const ATOM_PRECEDENCE: u8 = u8::MAX;
+
+/// Convert a node back to an expression string, with parentheses
+/// only where operator precedence or associativity requires them.
+/// Unlike `Display`, the output reads like handwritten input.
+pub fn unparse(node: &Node) -> String {
+	let mut output = String::new();
+	write_node(&mut output, node);
+	output
+}
+
+/// Write a node and return the precedence of its outermost operator.
+fn write_node(output: &mut String, node: &Node) -> u8 {
+	match node {
+		Node::Value(value) => {
+			write!(output, "{value}").unwrap();
+			if value.is_sign_negative() {
+				// Reads like unary minus
+				Operator::NEGATION_PRECEDENCE
+			} else {
+				ATOM_PRECEDENCE
+			}
+		}
+		Node::Identifier(name) => {
+			output.push_str(name);
+			ATOM_PRECEDENCE
+		}
+		Node::Expr(expr) => write_expr(output, expr),
+	}
+}
+
+fn write_expr(output: &mut String, expr: &Expr) -> u8 {
+	let operator = match expr {
+		Expr::Add(..) => Operator::Add,
+		Expr::Sub(..) => Operator::Sub,
+		Expr::Mul(..) => Operator::Mul,
+		Expr::Div(..) => Operator::Div,
+		Expr::Pow(..) => Operator::Pow,
+		Expr::Eq(..) => Operator::Eq,
+		Expr::Ne(..) => Operator::Ne,
+		Expr::Lt(..) => Operator::Lt,
+		Expr::Le(..) => Operator::Le,
+		Expr::Gt(..) => Operator::Gt,
+		Expr::Ge(..) => Operator::Ge,
+		Expr::BitAnd(..) => Operator::BitAnd,
+		Expr::Sum(values) => return write_sum(output, values),
+		Expr::Identity(value) => return write_node(output, value),
+		Expr::Neg(value) => {
+			output.push('-');
+			write_operand(output, value, Operator::NEGATION_PRECEDENCE, false);
+			return Operator::NEGATION_PRECEDENCE;
+		}
+		Expr::BitNot(value) => {
+			output.push('~');
+			write_operand(output, value, Operator::NEGATION_PRECEDENCE, false);
+			return Operator::NEGATION_PRECEDENCE;
+		}
+		Expr::Percent(value) => return write_postfix(output, value, Operator::Percent),
+		Expr::Factorial(value) => return write_postfix(output, value, Operator::Fact),
+		Expr::Abs(value) => {
+			output.push('|');
+			write_node(output, value);
+			output.push('|');
+			return ATOM_PRECEDENCE;
+		}
+		Expr::Sqrt(value) => return write_call(output, "sqrt", [value]),
+		Expr::Floor(value) => return write_call(output, "floor", [value]),
+		Expr::Ceil(value) => return write_call(output, "ceil", [value]),
+		Expr::Signum(value) => return write_call(output, "sign", [value]),
+		Expr::Round(value, decimal_places) => {
+			return write_call(output, "round", [value, decimal_places])
+		}
+		Expr::Truncate(value, decimal_places) => {
+			return write_call(output, "trunc", [value, decimal_places])
+		}
+		Expr::Ln(value) => return write_call(output, "ln", [value]),
+		Expr::Log(base, value) => return write_call(output, "log", [base, value]),
+		Expr::Rem(lhs, rhs) => return write_call(output, "rem", [lhs, rhs]),
+		Expr::Gcd(lhs, rhs) => return write_call(output, "gcd", [lhs, rhs]),
+		Expr::Lcm(lhs, rhs) => return write_call(output, "lcm", [lhs, rhs]),
+		Expr::BitOr(lhs, rhs) => return write_call(output, "bitor", [lhs, rhs]),
+		Expr::BitXor(lhs, rhs) => return write_call(output, "bitxor", [lhs, rhs]),
+		Expr::Min(values) => return write_call(output, "min", values),
+		Expr::Max(values) => return write_call(output, "max", values),
+		Expr::Clamp(value, min, max) => return write_call(output, "clamp", [value, min, max]),
+	};
+	let [lhs, rhs] = match expr.operands()[..] {
+		[lhs, rhs] => [lhs, rhs],
+		_ => unreachable!(),
+	};
+	let precedence = operator.precedence();
+	let associativity = operator.associativity();
+	// Operands grouping against the associativity need parentheses
+	write_operand(
+		output,
+		lhs,
+		precedence,
+		associativity == Associativity::Right,
+	);
+	write!(output, " {operator} ").unwrap();
+	write_operand(
+		output,
+		rhs,
+		precedence,
+		associativity == Associativity::Left,
+	);
+	precedence
+}
+
+/// Write an operand of an operator with the given precedence,
+/// wrapping it in parentheses if it binds looser, or equally when `strict`.
+fn write_operand(output: &mut String, node: &Node, precedence: u8, strict: bool) {
+	let mut operand = String::new();
+	let operand_precedence = write_node(&mut operand, node);
+	if operand_precedence < precedence || strict && operand_precedence == precedence {
+		write!(output, "({operand})").unwrap();
+	} else {
+		output.push_str(&operand);
+	}
+}
+
+/// Write the addends of a sum like a chain of left associative additions.
+fn write_sum(output: &mut String, values: &[Node]) -> u8 {
+	match values {
+		[] => {
+			output.push('0');
+			ATOM_PRECEDENCE
+		}
+		[value] => write_node(output, value),
+		[first, rest @ ..] => {
+			let precedence = Operator::Add.precedence();
+			write_operand(output, first, precedence, false);
+			for value in rest {
+				write!(output, " {} ", Operator::Add).unwrap();
+				write_operand(output, value, precedence, true);
+			}
+			precedence
+		}
+	}
+}
+
+fn write_postfix(output: &mut String, node: &Node, operator: Operator) -> u8 {
+	write_operand(output, node, operator.precedence(), false);
+	write!(output, "{operator}").unwrap();
+	operator.precedence()
+}
+
+fn write_call<'a>(
+	output: &mut String,
+	name: &str,
+	arguments: impl IntoIterator<Item = &'a Node>,
+) -> u8 {
+	write!(output, "{name}(").unwrap();
+	for (index, argument) in arguments.into_iter().enumerate() {
+		if index > 0 {
+			output.push_str(", ");
+		}
+		write_node(output, argument);
+	}
+	output.push(')');
+	ATOM_PRECEDENCE
+}
+
+#[cfg(test)]
+mod tests {
+	use super::unparse;
+	use crate::{
+		engine::{Expr, Node},
+		parser::parse,
+	};
+	use rust_decimal::Decimal;
+
+	#[test]
+	fn minimal_parentheses() {
+		let node = Node::Expr(
+			Expr::Add(
+				Node::Expr(Expr::Mul(Decimal::TWO.into(), Decimal::new(3, 0).into()).into()),
+				Decimal::new(4, 0).into(),
+			)
+			.into(),
+		);
+		assert_eq!(unparse(&node), "2 * 3 + 4");
+		for (input, output) in [
+			("(1 + 2) * 3", "(1 + 2) * 3"),
+			("((1 - 2)) - 3", "1 - 2 - 3"),
+			("1 - (2 - 3)", "1 - (2 - 3)"),
+			("1 / (2 * 3)", "1 / (2 * 3)"),
+			("2 ^ (3 ^ 2)", "2 ^ 3 ^ 2"),
+			("(2 ^ 3) ^ 2", "(2 ^ 3) ^ 2"),
+			("(-2) ^ 2", "(-2) ^ 2"),
+			("-(2 ^ 2)", "-2 ^ 2"),
+			("-(2 * 3)", "-(2 * 3)"),
+			("(-2) * 3", "-2 * 3"),
+			("-(2 + 3)", "-(2 + 3)"),
+			("(1 + 2)%", "(1 + 2)%"),
+			("(-3)! + 2!%", "(-3)! + 2!%"),
+			("(1 < 2) == (1 + 1 >= 2)", "1 < 2 == (1 + 1 >= 2)"),
+			("1 == (2 == 3)", "1 == (2 == 3)"),
+			("(1 & 3) + ~(2 * 3)", "(1 & 3) + ~(2 * 3)"),
+			("1 & (2 + 3) == 1", "1 & 2 + 3 == 1"),
+			("((1 + 2) + 3) + (4 + 5)", "1 + 2 + 3 + (4 + 5)"),
+			("1 + 2 + -3 * 4 + (5 - 6)", "1 + 2 + -3 * 4 + (5 - 6)"),
+			("|(1 + 2)| * max((1), 2 + 3)", "|1 + 2| * max(1, 2 + 3)"),
+		] {
+			assert_eq!(unparse(&parse(input).unwrap()), output, "{input}");
+		}
+	}
+
+	#[test]
+	fn identity() {
+		let sum = Node::Expr(Expr::from(parse("1 + 2").unwrap()).into());
+		let node = Node::Expr(Expr::Mul(sum, Decimal::new(3, 0).into()).into());
+		assert_eq!(unparse(&node), "(1 + 2) * 3");
+	}
+
+	#[test]
+	fn negative_value() {
+		let node = Node::Expr(Expr::Pow(Decimal::NEGATIVE_ONE.into(), Decimal::TWO.into()).into());
+		assert_eq!(unparse(&node), "(-1) ^ 2");
+	}
+
+	#[test]
+	fn round_trip() {
+		for input in [
+			"1 + 2 * 3 - 4 / 5",
+			"(1 + 2) * (3 - 4) / 5",
+			"2 ^ -1 ^ 2 * -3",
+			"-|1 - 2| * 3 - 4 / 5%",
+			"round(1 / 3, 2) + sqrt(16) ^ floor(2.5)",
+			"1 - (2 - (3 - 4))",
+			"1 / (2 / (3 / 4))",
+		] {
+			let node = parse(input).unwrap();
+			let reparsed = parse(&unparse(&node)).unwrap();
+			assert_eq!(reparsed, node, "{input}");
+			assert_eq!(Decimal::try_from(reparsed), Decimal::try_from(node));
+		}
+	}
+}
@@ -0,0 +1,119 @@
+use super::{Expr, Node};
+use rust_decimal::Decimal;
+
+/// Read-only traversal of a syntax tree.
+/// Every method does nothing by default, so implementations
+/// only need to handle the parts of the tree they care about.
+/// Parent expressions are visited before their operands.
+pub trait Visitor {
+	fn visit_value(&mut self, _value: &Decimal) {}
+
+	fn visit_identifier(&mut self, _name: &str) {}
+
+	fn visit_add(&mut self, _lhs: &Node, _rhs: &Node) {}
+
+	/// A chain of additions like `1 + 2 + 3`. By default, every `+` in the chain
+	/// is passed to `visit_add` with the values on either side of it,
+	/// so visitors that only handle `visit_add` still see each addition.
+	fn visit_sum(&mut self, values: &[Node]) {
+		for pair in values.windows(2) {
+			self.visit_add(&pair[0], &pair[1]);
+		}
+	}
+
+	fn visit_sub(&mut self, _lhs: &Node, _rhs: &Node) {}
+
+	fn visit_mul(&mut self, _lhs: &Node, _rhs: &Node) {}
+
+	fn visit_div(&mut self, _lhs: &Node, _rhs: &Node) {}
+
+	fn visit_neg(&mut self, _value: &Node) {}
+
+	/// Any expression without a dedicated method, such as function calls.
+	fn visit_other(&mut self, _expr: &Expr) {}
+}
+
+impl Node {
+	/// Walk the tree depth first, calling the visitor for every node.
+	pub fn visit<V: Visitor>(&self, visitor: &mut V) {
+		match self {
+			Node::Value(value) => visitor.visit_value(value),
+			Node::Identifier(name) => visitor.visit_identifier(name),
+			Node::Expr(expr) => expr.visit(visitor),
+		}
+	}
+}
+
+impl Expr {
+	/// Walk the tree depth first, calling the visitor for this expression
+	/// and then for each of its operands.
+	pub fn visit<V: Visitor>(&self, visitor: &mut V) {
+		match self {
+			Expr::Add(lhs, rhs) => visitor.visit_add(lhs, rhs),
+			Expr::Sum(values) => visitor.visit_sum(values),
+			Expr::Sub(lhs, rhs) => visitor.visit_sub(lhs, rhs),
+			Expr::Mul(lhs, rhs) => visitor.visit_mul(lhs, rhs),
+			Expr::Div(lhs, rhs) => visitor.visit_div(lhs, rhs),
+			Expr::Neg(value) => visitor.visit_neg(value),
+			expr => visitor.visit_other(expr),
+		}
+		for operand in self.operands() {
+			operand.visit(visitor);
+		}
+	}
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+/// Visitor counting the value leaves of a tree.
+pub struct CountNodes {
+	pub count: usize,
+}
+
+impl Visitor for CountNodes {
+	fn visit_value(&mut self, _value: &Decimal) {
+		self.count += 1;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{CountNodes, Visitor};
+	use crate::{engine::Node, parser::parse};
+
+	#[test]
+	fn count_nodes() {
+		for (input, count) in [("1", 1), ("1 + 2 * -3", 3), ("max(1, 2, |3|) / sqrt(4)", 4)] {
+			let mut visitor = CountNodes::default();
+			parse(input).unwrap().visit(&mut visitor);
+			assert_eq!(visitor.count, count);
+		}
+	}
+
+	#[test]
+	fn order() {
+		#[derive(Default)]
+		struct Operations(Vec<&'static str>);
+
+		impl Visitor for Operations {
+			fn visit_add(&mut self, _lhs: &Node, _rhs: &Node) {
+				self.0.push("add");
+			}
+
+			fn visit_mul(&mut self, _lhs: &Node, _rhs: &Node) {
+				self.0.push("mul");
+			}
+
+			fn visit_neg(&mut self, _value: &Node) {
+				self.0.push("neg");
+			}
+		}
+
+		let mut visitor = Operations::default();
+		parse("1 * 2 + -3").unwrap().visit(&mut visitor);
+		assert_eq!(visitor.0, ["add", "mul", "neg"]);
+
+		let mut visitor = Operations::default();
+		parse("1 + 2 + 3").unwrap().visit(&mut visitor);
+		assert_eq!(visitor.0, ["add", "add"]);
+	}
+}
@@ -0,0 +1,311 @@
+//! A simple arithmetic expression calculator.
+//!
+//! ```
+//! use calculator::{evaluate, Decimal};
+//!
+//! assert_eq!(evaluate("2 + 2").unwrap(), Decimal::new(4, 0));
+//! ```
+//!
+//! Without the default `std` feature the library is `no_std`,
+//! but still requires the `alloc` feature for heap allocation.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(feature = "alloc"))]
+compile_error!("the `alloc` feature is required without the `std` feature");
+
+extern crate alloc;
+
+/// Emit a debug trace with the `log` crate if the `logging` feature is enabled,
+/// and nothing at all otherwise.
+macro_rules! debug {
+	($($arg:tt)*) => {
+		#[cfg(feature = "logging")]
+		log::debug!($($arg)*);
+	};
+}
+pub(crate) use debug;
+
+#[cfg(feature = "cli")]
+pub mod cli;
+pub mod engine;
+pub mod parser;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+use alloc::vec::Vec;
+use core::fmt;
+pub use engine::{Expr, Node};
+pub use parser::parse;
+pub use rust_decimal::Decimal;
+
+#[derive(Debug)]
+#[non_exhaustive]
+/// Error of any stage of a calculation, from reading the input to evaluating it.
+pub enum Error {
+	Parse(parser::Error),
+	Math(engine::Error),
+	#[cfg(feature = "std")]
+	Io(std::io::Error),
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+	/// The wrapped error is displayed in place of this one, so its source
+	/// comes next instead of the wrapped error repeating the same message.
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Error::Parse(e) => e.source(),
+			Error::Math(e) => e.source(),
+			Error::Io(e) => e.source(),
+		}
+	}
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Error::Parse(e) => write!(f, "{e}"),
+			Error::Math(e) => write!(f, "{e}"),
+			#[cfg(feature = "std")]
+			Error::Io(e) => write!(f, "{e}"),
+		}
+	}
+}
+
+impl From<parser::Error> for Error {
+	fn from(error: parser::Error) -> Self {
+		Error::Parse(error)
+	}
+}
+
+impl From<engine::Error> for Error {
+	fn from(error: engine::Error) -> Self {
+		Error::Math(error)
+	}
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Error {
+	fn from(error: std::io::Error) -> Self {
+		Error::Io(error)
+	}
+}
+
+/// Evaluate an arithmetic expression:
+/// 1. Parse the input and generate an abstract syntax tree (AST)
+/// 2. Evaluate the AST and return a numeric result
+pub fn evaluate(input: &str) -> Result<Decimal, Error> {
+	let root_node = parse(input)?;
+	Ok(root_node.try_into()?)
+}
+
+/// Evaluate every expression like `evaluate`, without stopping at the first error.
+/// The results are in the order of the expressions.
+///
+/// ```
+/// use calculator::{evaluate_all, Decimal};
+///
+/// let results = evaluate_all(&["1 + 1", "1 / 0", "2 * 3"]);
+/// assert_eq!(results.len(), 3);
+/// assert!(results[1].is_err());
+/// ```
+pub fn evaluate_all<S: AsRef<str>>(expressions: &[S]) -> Vec<Result<Decimal, Error>> {
+	let mut builder = parser::ast::Builder::new();
+	expressions
+		.iter()
+		.map(|expression| evaluate_reusing(expression.as_ref(), &mut builder))
+		.collect()
+}
+
+/// Evaluate every expression like `evaluate_all`, spread across the threads of
+/// the global [rayon](https://docs.rs/rayon) thread pool.
+/// Their default stack of 2 MiB fits the deepest trees allowed by `parser::DEFAULT_MAX_DEPTH`.
+#[cfg(feature = "rayon")]
+pub fn evaluate_all_parallel<S: AsRef<str> + Sync>(
+	expressions: &[S],
+) -> Vec<Result<Decimal, Error>> {
+	use rayon::prelude::*;
+
+	expressions
+		.par_iter()
+		.map_init(parser::ast::Builder::new, |builder, expression| {
+			evaluate_reusing(expression.as_ref(), builder)
+		})
+		.collect()
+}
+
+/// Evaluate an expression like `evaluate`, parsing it with a reused builder.
+fn evaluate_reusing(input: &str, builder: &mut parser::ast::Builder) -> Result<Decimal, Error> {
+	let root_node = parser::parse_reusing(input, builder)?;
+	Ok(root_node.try_into()?)
+}
+
+/// Public enums are non-exhaustive, so matching them without a wildcard arm
+/// does not compile outside of the crate:
+///
+/// ```compile_fail,E0004
+/// use calculator::Node;
+///
+/// fn is_value(node: &Node) -> bool {
+/// 	match node {
+/// 		Node::Value(_) => true,
+/// 		Node::Expr(_) => false,
+/// 	}
+/// }
+/// ```
+#[cfg(doctest)]
+pub struct NonExhaustive;
+
+#[cfg(test)]
+mod tests {
+	use super::{engine, evaluate, evaluate_all, Error};
+	use rust_decimal::Decimal;
+	use std::{error, io};
+
+	#[test]
+	fn evaluate_expression() {
+		assert_eq!(evaluate("(1 + 2) * 3").unwrap(), Decimal::new(9, 0));
+	}
+
+	#[test]
+	fn evaluate_parse_error() {
+		assert!(matches!(evaluate("1 +"), Err(Error::Parse(_))));
+	}
+
+	#[test]
+	fn evaluate_math_error() {
+		assert!(matches!(
+			evaluate("1 / 0"),
+			Err(Error::Math(engine::Error::DivisionByZero))
+		));
+	}
+
+	#[test]
+	fn evaluate_all_expressions() {
+		let expressions = ["1 + 2", "1 +", "(1 + 2) * 3", "1 / 0", "2 ^ 10"];
+		let results = evaluate_all(&expressions);
+		assert_eq!(results.len(), expressions.len());
+		assert_eq!(results[0].as_ref().unwrap(), &Decimal::new(3, 0));
+		assert!(matches!(results[1], Err(Error::Parse(_))));
+		// The builder is reset after the failure
+		assert_eq!(results[2].as_ref().unwrap(), &Decimal::new(9, 0));
+		assert!(matches!(
+			results[3],
+			Err(Error::Math(engine::Error::DivisionByZero))
+		));
+		assert_eq!(results[4].as_ref().unwrap(), &Decimal::new(1024, 0));
+		assert!(evaluate_all::<&str>(&[]).is_empty());
+		let owned = vec![String::from("1"), String::new()];
+		assert_eq!(evaluate_all(&owned).len(), 2);
+	}
+
+	#[cfg(feature = "logging")]
+	#[test]
+	fn logging() {
+		use std::sync::atomic::{AtomicUsize, Ordering};
+
+		/// Logger counting the debug traces of the crate, formatting each of them
+		struct Counter(AtomicUsize);
+
+		impl log::Log for Counter {
+			fn enabled(&self, metadata: &log::Metadata) -> bool {
+				metadata.target().starts_with("calculator")
+			}
+
+			fn log(&self, record: &log::Record) {
+				if self.enabled(record.metadata()) {
+					assert!(!record.args().to_string().is_empty());
+					self.0.fetch_add(1, Ordering::Relaxed);
+				}
+			}
+
+			fn flush(&self) {}
+		}
+
+		static LOGGER: Counter = Counter(AtomicUsize::new(0));
+		log::set_logger(&LOGGER).unwrap();
+		log::set_max_level(log::LevelFilter::Debug);
+		assert_eq!(evaluate("-(1 + 2) * 3!").unwrap(), Decimal::new(-18, 0));
+		assert!(matches!(evaluate("1 / 0"), Err(Error::Math(_))));
+		assert!(matches!(evaluate("(1 +"), Err(Error::Parse(_))));
+		assert!(LOGGER.0.load(Ordering::Relaxed) > 0);
+	}
+
+	#[cfg(feature = "rayon")]
+	#[test]
+	fn evaluate_all_parallel() {
+		let expressions: Vec<String> = (0..1000)
+			.map(|n| {
+				if n % 3 == 0 {
+					format!("{n} +")
+				} else {
+					format!("{n} * 2")
+				}
+			})
+			.collect();
+		let results = super::evaluate_all_parallel(&expressions);
+		assert_eq!(results.len(), expressions.len());
+		for (n, result) in results.iter().enumerate() {
+			match result {
+				Ok(value) => assert_eq!(*value, Decimal::from(n * 2)),
+				Err(_) => assert_eq!(n % 3, 0),
+			}
+		}
+	}
+
+	#[cfg(feature = "rayon")]
+	#[test]
+	fn evaluate_all_parallel_deepest() {
+		let max = super::parser::DEFAULT_MAX_DEPTH;
+		let expressions: Vec<String> = (0..64)
+			.map(|n| match n % 4 {
+				0 => format!("{}1{}", "sqrt(".repeat(max), ")".repeat(max)),
+				1 => format!("{}1{}", "max(0, ".repeat(max), ")".repeat(max)),
+				2 => format!("{}1{}", "|".repeat(max), "|".repeat(max)),
+				_ => format!("{}1", "-".repeat(max)),
+			})
+			.collect();
+		for result in super::evaluate_all_parallel(&expressions) {
+			assert_eq!(result.unwrap(), Decimal::ONE);
+		}
+	}
+
+	#[test]
+	fn error_sources() {
+		// Errors are transparent, showing the wrapped error and continuing with its source
+		let error = Error::from(engine::Error::Overflow);
+		assert_eq!(error.to_string(), engine::Error::Overflow.to_string());
+		assert!(error::Error::source(&error).is_none());
+
+		let error = Error::from(crate::parse(")").unwrap_err());
+		assert_eq!(error.to_string(), "Error: Unexpected group terminator");
+		assert!(error::Error::source(&error).is_none());
+
+		let error = Error::from(crate::parse("1.2.3").unwrap_err());
+		let source = error::Error::source(&error).unwrap();
+		assert!(source.downcast_ref::<rust_decimal::Error>().is_some());
+
+		let error = Error::from(io::Error::new(io::ErrorKind::BrokenPipe, "closed"));
+		assert_eq!(error.to_string(), "closed");
+		assert!(matches!(error, Error::Io(e) if e.kind() == io::ErrorKind::BrokenPipe));
+	}
+
+	#[test]
+	fn error_chain() {
+		let error = evaluate("1.2.3 + 1").unwrap_err();
+		let mut chain = Vec::new();
+		let mut source: Option<&dyn std::error::Error> = Some(&error);
+		while let Some(error) = source {
+			chain.push(error.to_string());
+			source = error.source();
+		}
+		assert_eq!(
+			chain,
+			[
+				"Invalid decimal: two decimal points at column 1",
+				"Invalid decimal: two decimal points",
+			]
+		);
+	}
+}
@@ -1,15 +1,87 @@
-mod cli;
-mod engine;
-mod parser;
+use calculator::cli;
+use std::{env, fs, io, io::IsTerminal, process};
 
 fn main() {
-	println!("Type an arithmetic expression and press Enter to evaluate. Press Ctrl+C to exit.\n");
-	let mut buffer = String::new();
-	loop {
-		match cli::try_calculate(&mut buffer) {
-			Ok(result) => println!("{result}\n"),
-			Err(error) => println!("{error}\n"),
-		}
-		buffer.clear();
+	let options = match cli::Options::from_env(env::args().skip(1)) {
+		Ok(options) => options,
+		Err(error) => {
+			eprintln!("{error}");
+			process::exit(2);
+		}
+	};
+	// Escape codes would end up in files and pipes
+	#[cfg(feature = "colored")]
+	let options = cli::Options {
+		color: io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none(),
+		..options
+	};
+	if options.check {
+		let valid = match &options.file {
+			Some(path) => fs::File::open(path)
+				.and_then(|file| cli::check_all(io::BufReader::new(file), io::stdout())),
+			None => cli::check_all(io::stdin().lock(), io::stdout()),
+		};
+		match valid {
+			Ok(true) => return,
+			Ok(false) => process::exit(1),
+			Err(error) => {
+				eprintln!("{}", cli::Error::Input(error));
+				process::exit(1);
+			}
+		}
+	}
+	if let Some(path) = &options.file {
+		let input = match fs::read_to_string(path) {
+			Ok(input) => input,
+			Err(error) => {
+				eprintln!("{}", cli::Error::Input(error));
+				process::exit(1);
+			}
+		};
+		let lines: Vec<&str> = input.lines().collect();
+		for (line, result) in cli::calculate_lines(&input, &options) {
+			if options.json {
+				let result = result.map(|calculation| calculation.value);
+				println!("{}", cli::json_output(lines[line - 1].trim(), result));
+				continue;
+			}
+			let (output, is_error) = match result {
+				Ok(result) => (result.to_string(), false),
+				Err(error) => (error.to_string(), true),
+			};
+			if options.color {
+				println!("line {line}: {}", cli::colorize_result(&output, is_error));
+			} else {
+				println!("line {line}: {output}");
+			}
+		}
+		return;
+	}
+	// Piped input is evaluated like a filter, with nothing but the results in the output
+	let interactive = options
+		.interactive
+		.unwrap_or_else(|| io::stdin().is_terminal());
+	if interactive {
+		println!("Type an arithmetic expression and press Enter to evaluate. Type :help for help or press Ctrl+C to exit.\n");
+	}
+	let mut repl = cli::Repl::new(cli::Options {
+		interactive: Some(interactive),
+		..options
+	});
+	#[cfg(feature = "readline")]
+	let result = if interactive && io::stdin().is_terminal() {
+		repl.run_interactive(cli::history_path().as_deref())
+	} else {
+		repl.run(io::stdin().lock(), io::stdout())
+	};
+	#[cfg(not(feature = "readline"))]
+	let result = repl.run(io::stdin().lock(), io::stdout());
+	if let Err(error) = result {
+		// Closed streams are not reported, such as when the output is piped into `head`
+		let error = cli::Error::Input(error);
+		if error.is_recoverable() {
+			eprintln!("{error}");
+		}
+		process::exit(1);
 	}
 }
@@ -1,53 +1,361 @@
-use crate::engine::Node;
-use std::iter;
-use tokenizer::{tokenize, Token};
+use crate::engine::{Context, Expr, Node};
+use alloc::{string::String, vec::Vec};
+use core::iter::Peekable;
 
-mod ast;
+pub mod ast;
 mod error;
+mod function;
+mod incremental;
+mod rpn;
 mod tokenizer;
+mod validate;
 
-pub use self::error::Error;
+pub use self::{
+	error::Error,
+	function::NAMES as FUNCTION_NAMES,
+	incremental::Parser,
+	rpn::{parse_rpn, parse_rpn_with_context},
+	tokenizer::{Associativity, ConstantKind, Operator, Span, Token, TokenResult, Tokenizer},
+	validate::validate,
+};
 
 /// Construct a tree of value or expression nodes to be evaluated by the engine.
+/// Names of constants and variables are kept as identifier nodes,
+/// which are resolved during evaluation.
 pub fn parse(input: &str) -> Result<Node, Error> {
-	let tokens = &mut tokenize(input).chain(iter::once(Ok(Token::GroupEnd)));
-	let root_node = parse_tokens(tokens)?;
+	parse_tokens(&mut Tokenizer::new(input).peekable(), &[], None).map(|(node, ..)| node)
+}
+
+/// Construct a tree like `parse`, reusing the buffer of the builder
+/// instead of allocating a new one, such as for many expressions in a row.
+/// The builder is reset first, in case a previous expression failed to parse.
+pub(crate) fn parse_reusing(input: &str, builder: &mut ast::Builder) -> Result<Node, Error> {
+	builder.reset();
+	let tokens = &mut Tokenizer::new(input).peekable();
+	let depth = Depth::new(DEFAULT_MAX_DEPTH);
+	parse_with_builder(builder, tokens, &[], None, depth).map(|(node, ..)| node)
+}
+
+/// Construct a tree from the longest expression at the start of the input,
+/// which may continue with anything else, and return the byte offset after its last token.
+/// Example: `1 + 2; foo` is `1 + 2` followed by `; foo` at offset 5
+pub fn parse_partial(input: &str) -> Result<(Node, usize), Error> {
+	// Characters which are not part of any token end the expression
+	let end = input
+		.find(|c| !tokenizer::is_expression_char(c))
+		.unwrap_or(input.len());
+	let mut tokens = Vec::new();
+	let mut error = None;
+	for token in Tokenizer::new(&input[..end]) {
+		match token {
+			Ok(token) => tokens.push(token),
+			Err((value, span)) => {
+				error = Some(Error::Value(value, span));
+				break;
+			}
+		}
+	}
+	// Only a prefix of the tokens might form a valid expression,
+	// the longest one ends after the last token completing an expression
+	let mut offset = None;
+	let _ = validate::validate_tokens(Tokenizer::from_tokens(tokens.clone()), |end| {
+		offset = Some(end);
+	});
+	if let Some(offset) = offset {
+		tokens.retain(|(_, span)| span.end <= offset);
+		let prefix = &mut Tokenizer::from_tokens(tokens).peekable();
+		if let Ok((node, ..)) = parse_tokens(prefix, &[], None) {
+			return Ok((node, offset));
+		}
+	}
+	match error {
+		Some(error) => Err(error),
+		None => parse(&input[..end]).map(|node| (node, end)),
+	}
+}
+
+/// Default of `ParseOptions::max_depth`. The deepest trees it allows are parsed
+/// and evaluated within the 2 MiB stack of a spawned thread, even in unoptimized builds.
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Limits and number syntax of `parse_with_options`.
+pub struct ParseOptions {
+	/// Maximum number of expression nodes, see `Node::count_ops`
+	pub max_ops: Option<usize>,
+	/// Maximum depth of the tree, see `Node::depth`, and nesting depth
+	/// of groups, absolute values and function arguments
+	pub max_depth: usize,
+	/// Read commas directly between digits as thousands separators, such as `1,000`,
+	/// see `Tokenizer::with_thousands_separators`
+	pub thousands_separators: bool,
+}
+
+impl Default for ParseOptions {
+	fn default() -> Self {
+		Self {
+			max_ops: None,
+			max_depth: DEFAULT_MAX_DEPTH,
+			thousands_separators: false,
+		}
+	}
+}
 
-	if tokens.next().is_none() {
-		Ok(root_node)
+/// Construct a tree like `parse` and check it against the limits of the options.
+pub fn parse_with_options(input: &str, options: &ParseOptions) -> Result<Node, Error> {
+	let tokens = if options.thousands_separators {
+		Tokenizer::with_thousands_separators(input)
 	} else {
-		Err(Error::UninitializedGroup)
+		Tokenizer::new(input)
+	};
+	let tokens = &mut tokens.peekable();
+	let (node, ..) = parse_nested(tokens, &[], None, Depth::new(options.max_depth))?;
+	match options.max_ops {
+		Some(max) if node.count_ops() > max => Err(Error::ExpressionTooComplex {
+			ops: node.count_ops(),
+			max,
+		}),
+		_ => Ok(node),
+	}
+}
+
+/// Construct a tree of value or expression nodes to be evaluated by the engine,
+/// resolving variables with values from the context.
+pub fn parse_with_context(input: &str, context: &Context) -> Result<Node, Error> {
+	parse_tokens(&mut Tokenizer::new(input).peekable(), &[], Some(context)).map(|(node, ..)| node)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+/// A parsed line of input.
+pub enum ParseResult {
+	/// An expression to evaluate
+	Expression(Node),
+	/// A `let <name> = <expression>` statement
+	Assignment { name: String, value: Node },
+}
+
+/// Parse either an expression or a variable assignment statement,
+/// resolving variables with values from the context.
+pub fn parse_statement(input: &str, context: &Context) -> Result<ParseResult, Error> {
+	let tokens = &mut Tokenizer::new(input).peekable();
+	let let_span = match tokens.peek() {
+		Some(Ok((Token::Let, span))) => *span,
+		_ => {
+			return parse_tokens(tokens, &[], Some(context))
+				.map(|(node, ..)| ParseResult::Expression(node))
+		}
+	};
+	tokens.next();
+	let name = match tokens.next() {
+		Some(Ok((Token::Identifier(name), _))) if !Context::is_reserved(&name) => name,
+		Some(Ok((_, span)) | Err((_, span))) => return Err(Error::InvalidAssignment(span)),
+		None => return Err(Error::InvalidAssignment(let_span)),
+	};
+	match tokens.next() {
+		Some(Ok((Token::Assign, _))) => {}
+		Some(Ok((_, span)) | Err((_, span))) => return Err(Error::InvalidAssignment(span)),
+		None => return Err(Error::InvalidAssignment(let_span)),
 	}
+	let (value, ..) = parse_tokens(tokens, &[], Some(context))?;
+	Ok(ParseResult::Assignment { name, value })
 }
 
-/// Convert a stream of tokens into a root tree node.
+/// Convert a stream of tokens into a root tree node, its depth and the span of consumed tokens.
+/// The stream is consumed up to and including the first of the `terminators` (also returned),
+/// or until it is exhausted if there are no terminators.
+/// Variables are resolved with values from the context, if there is one.
 fn parse_tokens(
-	tokens: &mut impl Iterator<Item = Result<Token, rust_decimal::Error>>,
-) -> Result<Node, Error> {
-	let mut builder = ast::Builder::new();
-	let mut is_terminated = false;
+	tokens: &mut Peekable<Tokenizer<'_>>,
+	terminators: &[Token],
+	context: Option<&Context>,
+) -> Result<(Node, usize, Span, Option<Token>), Error> {
+	parse_nested(tokens, terminators, context, Depth::new(DEFAULT_MAX_DEPTH))
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Nesting depth of the tokens being parsed, limited to avoid overflowing the stack.
+struct Depth {
+	current: usize,
+	max: usize,
+}
+
+impl Depth {
+	fn new(max: usize) -> Self {
+		Self { current: 0, max }
+	}
+
+	/// Depth of a group nested one level deeper.
+	fn nested(self) -> Result<Self, Error> {
+		if self.current < self.max {
+			Ok(Self {
+				current: self.current + 1,
+				..self
+			})
+		} else {
+			Err(Error::MaxDepthExceeded(self.max))
+		}
+	}
+}
+
+/// `parse_tokens` at the given depth of nested groups.
+fn parse_nested(
+	tokens: &mut Peekable<Tokenizer<'_>>,
+	terminators: &[Token],
+	context: Option<&Context>,
+	depth: Depth,
+) -> Result<(Node, usize, Span, Option<Token>), Error> {
+	parse_with_builder(
+		&mut ast::Builder::with_max_depth(depth.max),
+		tokens,
+		terminators,
+		context,
+		depth,
+	)
+}
+
+/// `parse_nested` with a builder for the outermost level of the tokens,
+/// which must be empty. Nested groups still use builders of their own.
+fn parse_with_builder(
+	builder: &mut ast::Builder,
+	tokens: &mut Peekable<Tokenizer<'_>>,
+	terminators: &[Token],
+	context: Option<&Context>,
+	depth: Depth,
+) -> Result<(Node, usize, Span, Option<Token>), Error> {
+	let mut consumed: Option<Span> = None;
+	let mut terminator = None;
 	while let Some(token) = tokens.next() {
-		match token.map_err(Error::Value)? {
-			Token::Value(value) => builder.add_node(Node::Value(value))?,
-			Token::Operator(operator) => builder.add_operator(operator)?,
-			Token::GroupStart => builder.add_node(parse_tokens(tokens)?)?,
-			Token::GroupEnd => {
-				is_terminated = true;
+		let (token, span) = token.map_err(|(error, span)| Error::Value(error, span))?;
+		consumed = Some(consumed.map_or(span, |consumed| consumed.join(span)));
+		let (node, span, node_depth) = match token {
+			Token::Value(value) => (Node::Value(value), span, 0),
+			Token::Constant(constant) => (Node::Value(constant.value()), span, 0),
+			Token::Identifier(name)
+				if matches!(tokens.peek(), Some(Ok((Token::GroupStart, _)))) =>
+			{
+				// Function call
+				tokens.next();
+				let (arguments, arguments_depth, arguments_span) =
+					parse_arguments(tokens, context, depth.nested()?)?;
+				let expr = function::call(name, arguments, span)?;
+				let node = Node::Expr(expr.into());
+				(node, span.join(arguments_span), arguments_depth + 1)
+			}
+			Token::Identifier(name) => match context {
+				Some(context) => match context.get(&name) {
+					Some(value) => (Node::Value(value), span, 0),
+					None => return Err(Error::UndefinedVariable(name, span)),
+				},
+				None => (Node::Identifier(name), span, 0),
+			},
+			Token::Operator(_) => {
+				builder.try_add_token(token, span)?;
+				continue;
+			}
+			Token::GroupStart => {
+				let (node, node_depth, group_span, _) =
+					parse_nested(tokens, &[Token::GroupEnd], context, depth.nested()?)?;
+				(node, span.join(group_span), node_depth)
+			}
+			Token::AbsStart => {
+				let (node, node_depth, group_span, _) =
+					parse_nested(tokens, &[Token::AbsEnd], context, depth.nested()?)?;
+				let node = Node::Expr(Expr::Abs(node).into());
+				(node, span.join(group_span), node_depth + 1)
+			}
+			Token::Let | Token::Assign => return Err(Error::InvalidAssignment(span)),
+			token if terminators.contains(&token) => {
+				terminator = Some(token);
 				break;
 			}
+			Token::Comma => return Err(Error::UnexpectedComma(span)),
+			Token::GroupEnd | Token::AbsEnd => return Err(Error::UninitializedGroup),
+		};
+		let (node, span, node_depth) = parse_postfix(tokens, node, span, node_depth, depth.max)?;
+		consumed = consumed.map(|consumed| consumed.join(span));
+		builder.add_node_with_depth(node, span, node_depth)?;
+		// An operand directly followed by a group, a function call, a constant or a variable
+		// is an implicit multiplication. Example: `2(3 + 4)` -> `2 * (3 + 4)`
+		if let Some(Ok((
+			Token::GroupStart | Token::Constant(_) | Token::Identifier(_),
+			next_span,
+		))) = tokens.peek()
+		{
+			let span = Span::new(next_span.start, next_span.start);
+			builder.add_operator(Operator::Mul, span)?;
 		}
 	}
-	if is_terminated {
-		builder.build()
+	if terminator.is_some() || terminators.is_empty() {
+		let (node, node_depth) = builder.finish_with_depth()?;
+		Ok((node, node_depth, consumed.unwrap_or_default(), terminator))
 	} else {
 		Err(Error::UnterminatedGroup)
 	}
 }
 
+/// Apply postfix operators directly following an operand node of the given depth.
+/// They bind tighter than any other operator, so they are applied
+/// before the node is combined with anything else.
+fn parse_postfix(
+	tokens: &mut Peekable<Tokenizer<'_>>,
+	mut node: Node,
+	mut span: Span,
+	mut depth: usize,
+	max_depth: usize,
+) -> Result<(Node, Span, usize), Error> {
+	while let Some(Ok((Token::Operator(operator), operator_span))) = tokens.peek() {
+		let expr = match operator {
+			Operator::Percent => Expr::Percent(node),
+			Operator::Fact => Expr::Factorial(node),
+			_ => break,
+		};
+		if depth >= max_depth {
+			return Err(Error::MaxDepthExceeded(max_depth));
+		}
+		depth += 1;
+		span = span.join(*operator_span);
+		node = Node::Expr(expr.into());
+		tokens.next();
+	}
+	Ok((node, span, depth))
+}
+
+/// Convert a stream of comma separated function arguments into nodes
+/// and the depth of the deepest one.
+/// The stream is consumed up to and including the closing parenthesis.
+fn parse_arguments(
+	tokens: &mut Peekable<Tokenizer<'_>>,
+	context: Option<&Context>,
+	depth: Depth,
+) -> Result<(Vec<Node>, usize, Span), Error> {
+	let mut arguments = Vec::new();
+	if let Some(Ok((Token::GroupEnd, span))) = tokens.peek() {
+		let span = *span;
+		tokens.next();
+		return Ok((arguments, 0, span));
+	}
+	let mut consumed: Option<Span> = None;
+	let mut max_depth = 0;
+	loop {
+		let (argument, argument_depth, span, terminator) =
+			parse_nested(tokens, &[Token::Comma, Token::GroupEnd], context, depth)?;
+		arguments.push(argument);
+		max_depth = max_depth.max(argument_depth);
+		consumed = Some(consumed.map_or(span, |consumed| consumed.join(span)));
+		if terminator == Some(Token::GroupEnd) {
+			return Ok((arguments, max_depth, consumed.unwrap_or_default()));
+		}
+	}
+}
+
 #[cfg(test)]
 mod tests {
-	use super::parse;
-	use crate::engine::{Expr, Node};
+	use super::{
+		parse, parse_partial, parse_statement, parse_with_context, parse_with_options, validate,
+		Error, Operator, ParseOptions, ParseResult, Span, DEFAULT_MAX_DEPTH,
+	};
+	use crate::engine::{self, Context, Expr, Node};
 	use rust_decimal::Decimal;
 
 	#[test]
@@ -95,9 +403,726 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn unary_plus() {
+		let evaluate = |input| Decimal::try_from(parse(input).unwrap()).unwrap();
+		assert_eq!(parse("+1").unwrap(), Node::Value(Decimal::ONE));
+		assert_eq!(evaluate("1 + +2"), Decimal::new(3, 0));
+		assert_eq!(evaluate("+(-1)"), Decimal::NEGATIVE_ONE);
+		assert_eq!(evaluate("2 * +-3"), Decimal::new(-6, 0));
+		assert_eq!(
+			parse("++1"),
+			Err(Error::UnexpectedOperator {
+				op: Operator::Add,
+				span: Span::new(1, 2)
+			})
+		);
+		assert_eq!(
+			parse("1 +"),
+			Err(Error::UnexpectedOperator {
+				op: Operator::Add,
+				span: Span::new(2, 3)
+			})
+		);
+	}
+
 	#[test]
 	fn raw() {
 		let node = parse("1000").unwrap();
 		assert_eq!(node, Node::Value(Decimal::ONE_THOUSAND));
 	}
+
+	#[test]
+	fn radix_prefixes() {
+		let node = parse("0b1010 * 0o10").unwrap();
+		assert_eq!(
+			node,
+			Node::Expr(
+				Expr::Mul(Node::Value(Decimal::TEN), Node::Value(Decimal::new(8, 0))).into()
+			)
+		);
+	}
+
+	#[test]
+	fn empty() {
+		for input in ["", "\n", " \r\n\t\n"] {
+			assert_eq!(parse(input), Err(Error::Empty), "{input:?}");
+		}
+	}
+
+	#[test]
+	fn invalid_digit() {
+		assert_eq!(
+			parse("0b2"),
+			Err(Error::Value(
+				rust_decimal::Error::ErrorString("Invalid decimal: invalid character".to_string()),
+				Span::new(0, 3)
+			))
+		);
+	}
+
+	#[test]
+	fn scientific() {
+		let node = parse("1e3 + 1").unwrap();
+		assert_eq!(
+			node,
+			Node::Expr(
+				Expr::Add(
+					Node::Value(Decimal::ONE_THOUSAND),
+					Node::Value(Decimal::ONE)
+				)
+				.into()
+			)
+		);
+		assert_eq!(Decimal::try_from(node), Ok(Decimal::new(1001, 0)));
+		for (input, value) in [
+			("1e0", Decimal::ONE),
+			("1E+0", Decimal::ONE),
+			("1E-0", Decimal::ONE),
+			("0e0", Decimal::ZERO),
+			("2.5e0 * 2", Decimal::new(5, 0)),
+			("1e0 + 1E+0 - 1E-0", Decimal::ONE),
+		] {
+			let result = Decimal::try_from(parse(input).unwrap()).unwrap();
+			assert_eq!(result, value, "{input}");
+			// Zero exponents do not leave trailing zeros behind
+			assert_eq!(result.to_string(), value.to_string(), "{input}");
+		}
+	}
+
+	#[test]
+	fn digit_separators() {
+		let node = parse("1_000 + 1_000").unwrap();
+		assert_eq!(Decimal::try_from(node), Ok(Decimal::new(2000, 0)));
+	}
+
+	#[test]
+	fn abs() {
+		let node = parse("|-5|").unwrap();
+		assert_eq!(
+			node,
+			Node::Expr(
+				Expr::Abs(Node::Expr(
+					Expr::Neg(Node::Value(Decimal::new(5, 0))).into()
+				))
+				.into()
+			)
+		);
+		assert_eq!(Decimal::try_from(node), Ok(Decimal::new(5, 0)));
+		assert_eq!(
+			Decimal::try_from(parse("|3|").unwrap()),
+			Ok(Decimal::new(3, 0))
+		);
+	}
+
+	#[test]
+	fn nested_abs() {
+		let node = parse("|-(2+3)|").unwrap();
+		assert_eq!(Decimal::try_from(node), Ok(Decimal::new(5, 0)));
+		let node = parse("||-1| - |-2||").unwrap();
+		assert_eq!(Decimal::try_from(node), Ok(Decimal::ONE));
+	}
+
+	#[test]
+	fn unbalanced_groups() {
+		assert_eq!(parse("(1"), Err(Error::UnterminatedGroup));
+		assert_eq!(parse("1)"), Err(Error::UninitializedGroup));
+		assert_eq!(parse("(1))"), Err(Error::UninitializedGroup));
+	}
+
+	#[test]
+	fn mismatched_abs() {
+		assert_eq!(parse("|1)"), Err(Error::UninitializedGroup));
+		assert_eq!(parse("(1|"), Err(Error::UninitializedGroup));
+		assert_eq!(parse("|1"), Err(Error::UnterminatedGroup));
+	}
+
+	#[test]
+	fn error_spans() {
+		assert_eq!(
+			parse("1 + 2 * / 3"),
+			Err(Error::UnexpectedOperator {
+				op: Operator::Div,
+				span: Span::new(8, 9)
+			})
+		);
+		assert_eq!(
+			parse("1 + (2 * 3) 4.5"),
+			Err(Error::UnexpectedNode {
+				node: Node::Value(Decimal::new(45, 1)),
+				span: Span::new(12, 15)
+			})
+		);
+		assert_eq!(
+			parse("1 + 1.2.3"),
+			Err(Error::Value(
+				rust_decimal::Error::ErrorString("Invalid decimal: two decimal points".to_string()),
+				Span::new(4, 9)
+			))
+		);
+	}
+
+	#[test]
+	fn error_column() {
+		assert_eq!(
+			parse("1 + foo(2)").unwrap_err().to_string(),
+			"Error: Unknown function foo at column 5"
+		);
+	}
+
+	#[test]
+	fn variables() {
+		let mut context = Context::new();
+		context.last_result = Some(Decimal::TWO);
+		let node = parse_with_context("ans * _", &context).unwrap();
+		assert_eq!(
+			node,
+			Node::Expr(Expr::Mul(Node::Value(Decimal::TWO), Node::Value(Decimal::TWO)).into())
+		);
+	}
+
+	#[test]
+	fn undefined_variable() {
+		assert_eq!(
+			parse_with_context("1 + ans", &Context::new()),
+			Err(Error::UndefinedVariable("ans".to_string(), Span::new(4, 7)))
+		);
+	}
+
+	#[test]
+	fn identifiers() {
+		let node = parse("2x + PI").unwrap();
+		assert_eq!(
+			node,
+			Node::Expr(
+				Expr::Add(
+					Node::Expr(
+						Expr::Mul(Node::Value(Decimal::TWO), Node::Identifier("x".to_string()))
+							.into()
+					),
+					Node::Identifier("PI".to_string())
+				)
+				.into()
+			)
+		);
+		let mut context = Context::new();
+		context.set("x".to_string(), Decimal::ONE);
+		assert_eq!(
+			engine::evaluate_with_context(node.clone(), &context),
+			Ok(Decimal::TWO + Decimal::PI)
+		);
+		assert_eq!(
+			Decimal::try_from(node),
+			Err(engine::Error::UndefinedVariable("x".to_string()))
+		);
+	}
+
+	#[test]
+	fn constants() {
+		for (input, value) in [
+			("PI * 2 == TAU", Decimal::ONE),
+			("PI", Decimal::PI),
+			("ln(E)", Decimal::ONE),
+			("2E", Decimal::TWO * Decimal::E),
+		] {
+			assert_eq!(
+				Decimal::try_from(parse(input).unwrap()).map(|n| n.round_dp(20)),
+				Ok(value.round_dp(20)),
+				"{input}"
+			);
+		}
+		assert_eq!(
+			parse_statement("let PI = 3", &Context::new()),
+			Err(Error::InvalidAssignment(Span::new(4, 6)))
+		);
+	}
+
+	#[test]
+	fn lowercase_constants() {
+		let evaluate = |input| Decimal::try_from(parse(input).unwrap());
+		for input in [
+			"pi > 3",
+			"pi < 3.2",
+			"e > 2",
+			"e < 3",
+			"tau == 2 * pi",
+			"phi == 1 + 1 / phi",
+		] {
+			assert_eq!(evaluate(input), Ok(Decimal::ONE), "{input}");
+		}
+		assert_eq!(evaluate("pi"), Ok(Decimal::PI));
+		assert_eq!(evaluate("2pi"), evaluate("tau"));
+		assert_eq!(evaluate("ln(e)").map(|n| n.round_dp(20)), Ok(Decimal::ONE));
+		assert_eq!(
+			parse("pi * r * r").unwrap().to_string(),
+			"((3.1415926535897932384626433833 * r) * r)"
+		);
+		assert_eq!(
+			parse_statement("let e = 3", &Context::new()),
+			Err(Error::InvalidAssignment(Span::new(4, 5)))
+		);
+		// A trailing `e` is the constant, while `e` followed by digits is an exponent
+		assert_eq!(evaluate("2e"), evaluate("2 * e"));
+		assert_eq!(evaluate("2e5"), Ok(Decimal::new(200_000, 0)));
+		assert_eq!(evaluate("2e-1"), Ok(Decimal::new(2, 1)));
+		assert_eq!(evaluate("2e - 1"), evaluate("2 * e - 1"));
+		// Constants take precedence over variables of the same name
+		let mut context = Context::new();
+		context.set("e".to_string(), Decimal::new(3, 0));
+		assert_eq!(
+			parse_with_context("e", &context),
+			Ok(Node::Value(Decimal::E))
+		);
+	}
+
+	#[test]
+	fn assignment() {
+		let result = parse_statement("let a = 5", &Context::new()).unwrap();
+		assert_eq!(
+			result,
+			ParseResult::Assignment {
+				name: "a".to_string(),
+				value: Node::Value(Decimal::new(5, 0))
+			}
+		);
+		let result = parse_statement("5", &Context::new()).unwrap();
+		assert_eq!(
+			result,
+			ParseResult::Expression(Node::Value(Decimal::new(5, 0)))
+		);
+	}
+
+	#[test]
+	fn invalid_assignment() {
+		let context = Context::new();
+		for (input, start, end) in [
+			("let", 0, 3),
+			("let 1 = 1", 4, 5),
+			("let a", 0, 3),
+			("let a 1", 6, 7),
+			("let ans = 1", 4, 7),
+			("let sqrt = 2", 4, 8),
+			("let max = 1", 4, 7),
+			("1 = 1", 2, 3),
+			("let a = let b = 1", 8, 11),
+		] {
+			assert_eq!(
+				parse_statement(input, &context),
+				Err(Error::InvalidAssignment(Span::new(start, end))),
+				"{input}"
+			);
+		}
+		assert_eq!(parse_statement("let a =", &context), Err(Error::Empty));
+	}
+
+	#[test]
+	fn function_call() {
+		let node = parse("1 + sqrt(2 * 2)").unwrap();
+		assert_eq!(
+			node,
+			Node::Expr(
+				Expr::Add(
+					Node::Value(Decimal::ONE),
+					Node::Expr(
+						Expr::Sqrt(Node::Expr(
+							Expr::Mul(Node::Value(Decimal::TWO), Node::Value(Decimal::TWO)).into()
+						))
+						.into()
+					)
+				)
+				.into()
+			)
+		);
+	}
+
+	#[test]
+	fn unknown_function() {
+		assert_eq!(
+			parse("foo(1)"),
+			Err(Error::UnknownFunction("foo".to_string(), Span::new(0, 3)))
+		);
+	}
+
+	#[test]
+	fn function_arguments() {
+		let node = parse("round(1.555, 1 + 1)").unwrap();
+		assert_eq!(
+			node,
+			Node::Expr(
+				Expr::Round(
+					Node::Value(Decimal::new(1555, 3)),
+					Node::Expr(
+						Expr::Add(Node::Value(Decimal::ONE), Node::Value(Decimal::ONE)).into()
+					)
+				)
+				.into()
+			)
+		);
+		assert_eq!(Decimal::try_from(node), Ok(Decimal::new(156, 2)));
+		assert_eq!(
+			Decimal::try_from(parse("floor(1.7) + ceil(1.2)").unwrap()),
+			Ok(Decimal::new(3, 0))
+		);
+	}
+
+	#[test]
+	fn invalid_function_arguments() {
+		assert_eq!(
+			parse("floor(1, 2)"),
+			Err(Error::InvalidArgumentCount(
+				"floor".to_string(),
+				Span::new(0, 5)
+			))
+		);
+		assert_eq!(
+			parse("floor()"),
+			Err(Error::InvalidArgumentCount(
+				"floor".to_string(),
+				Span::new(0, 5)
+			))
+		);
+		assert_eq!(parse("round(1,)"), Err(Error::Empty));
+		assert_eq!(parse("round(1, 2"), Err(Error::UnterminatedGroup));
+		assert_eq!(
+			parse("(1, 2)"),
+			Err(Error::UnexpectedComma(Span::new(2, 3)))
+		);
+		assert_eq!(parse("1, 2"), Err(Error::UnexpectedComma(Span::new(1, 2))));
+	}
+
+	#[test]
+	fn variadic_function() {
+		assert_eq!(
+			Decimal::try_from(parse("min(3, 1, 2)").unwrap()),
+			Ok(Decimal::ONE)
+		);
+		assert_eq!(
+			Decimal::try_from(parse("max(3, 1, 2) + sqrt(4)").unwrap()),
+			Ok(Decimal::new(5, 0))
+		);
+		for input in ["min(5)", "min()"] {
+			assert_eq!(
+				parse(input),
+				Err(Error::TooFewArguments("min".to_string(), Span::new(0, 3))),
+				"{input}"
+			);
+		}
+	}
+
+	#[test]
+	fn percent() {
+		let node = parse("50%").unwrap();
+		assert_eq!(
+			node,
+			Node::Expr(Expr::Percent(Node::Value(Decimal::new(50, 0))).into())
+		);
+		assert_eq!(Decimal::try_from(node), Ok(Decimal::new(5, 1)));
+		assert_eq!(
+			Decimal::try_from(parse("100% + 50%").unwrap()),
+			Ok(Decimal::new(15, 1))
+		);
+		assert_eq!(
+			Decimal::try_from(parse("200% * 3").unwrap()),
+			Ok(Decimal::new(6, 0))
+		);
+	}
+
+	#[test]
+	fn percent_binds_tightest() {
+		assert_eq!(
+			Decimal::try_from(parse("1 / 50%").unwrap()),
+			Ok(Decimal::TWO)
+		);
+		assert_eq!(
+			Decimal::try_from(parse("(1 + 1)% * |-100|%").unwrap()),
+			Ok(Decimal::new(2, 2))
+		);
+	}
+
+	#[test]
+	fn unexpected_percent() {
+		assert_eq!(
+			parse("%1"),
+			Err(Error::UnexpectedOperator {
+				op: Operator::Percent,
+				span: Span::new(0, 1)
+			})
+		);
+		assert_eq!(
+			parse("1 + %"),
+			Err(Error::UnexpectedOperator {
+				op: Operator::Percent,
+				span: Span::new(4, 5)
+			})
+		);
+	}
+
+	#[test]
+	fn pow() {
+		for (input, value) in [
+			("2 ^ 3 ^ 2", Decimal::new(512, 0)),
+			("-2 ^ 2", Decimal::new(-4, 0)),
+			("(-2) ^ 2", Decimal::new(4, 0)),
+			("2 * 3 ^ 2", Decimal::new(18, 0)),
+			("2 ^ -1", Decimal::new(5, 1)),
+		] {
+			assert_eq!(Decimal::try_from(parse(input).unwrap()), Ok(value));
+		}
+	}
+
+	#[test]
+	fn malformed_input() {
+		let long = "1 + ".repeat(10_000) + "1";
+		assert!(parse(&long).is_ok());
+		for input in [
+			"+-*/^%",
+			"--",
+			"1 +",
+			"1 + 2 +",
+			"((((((((",
+			"))))",
+			"||||",
+			"1 2",
+			",",
+			"max(,)",
+			"\u{0}\u{FFFD}",
+		] {
+			assert!(parse(input).is_err(), "{input}");
+		}
+	}
+
+	#[test]
+	fn implicit_multiplication() {
+		let context = Context {
+			variables: [("x".to_string(), Decimal::TEN)].into(),
+			..Context::new()
+		};
+		for (input, value) in [
+			("2(3)", Decimal::new(6, 0)),
+			("(2)(3)", Decimal::new(6, 0)),
+			("2(1+1)(3)", Decimal::new(12, 0)),
+			("2(3 + 4)", Decimal::new(14, 0)),
+			("2sqrt(4)", Decimal::new(4, 0)),
+			("3 max(1, 2)", Decimal::new(6, 0)),
+			("2x", Decimal::new(20, 0)),
+			("1.5x^2", Decimal::new(150, 0)),
+			("1 / 2(4)", Decimal::TWO),
+			("50%(2)", Decimal::ONE),
+		] {
+			let node = parse_with_context(input, &context).unwrap();
+			assert_eq!(Decimal::try_from(node), Ok(value), "{input}");
+		}
+		assert!(parse("1 2").is_err());
+		assert!(parse("(1) 2").is_err());
+	}
+
+	#[test]
+	fn factorial() {
+		for (input, value) in [
+			("5!", Decimal::new(120, 0)),
+			("0!", Decimal::ONE),
+			("3!!", Decimal::new(720, 0)),
+			("2 * 3! ^ 2", Decimal::new(72, 0)),
+			("-3!", Decimal::new(-6, 0)),
+		] {
+			assert_eq!(
+				Decimal::try_from(parse(input).unwrap()),
+				Ok(value),
+				"{input}"
+			);
+		}
+		for input in ["(-1)!", "1.5!"] {
+			assert_eq!(
+				Decimal::try_from(parse(input).unwrap()),
+				Err(engine::Error::NotAnInteger),
+				"{input}"
+			);
+		}
+		assert_eq!(
+			parse("!1"),
+			Err(Error::UnexpectedOperator {
+				op: Operator::Fact,
+				span: Span::new(0, 1)
+			})
+		);
+	}
+
+	#[test]
+	fn max_ops() {
+		let options = ParseOptions {
+			max_ops: Some(2),
+			..ParseOptions::default()
+		};
+		assert!(parse_with_options("1 + 2 * 3", &options).is_ok());
+		assert_eq!(
+			parse_with_options("-(1 + 2 * 3)", &options),
+			Err(Error::ExpressionTooComplex { ops: 3, max: 2 })
+		);
+		let options = ParseOptions::default();
+		assert!(parse_with_options(&format!("{}1", "1 + ".repeat(1000)), &options).is_ok());
+	}
+
+	#[test]
+	fn max_depth() {
+		let nested = |depth| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+		let max = DEFAULT_MAX_DEPTH;
+		let options = ParseOptions::default();
+		assert!(parse_with_options(&nested(max), &options).is_ok());
+		assert_eq!(
+			parse_with_options(&nested(max + 1), &options),
+			Err(Error::MaxDepthExceeded(max))
+		);
+		assert_eq!(parse(&nested(max + 1)), Err(Error::MaxDepthExceeded(max)));
+		assert_eq!(
+			validate(&nested(max + 1)),
+			Err(Error::MaxDepthExceeded(max))
+		);
+		assert_eq!(
+			parse(&"(".repeat(100_000)),
+			Err(Error::MaxDepthExceeded(max))
+		);
+		// The deepest trees allowed by default are evaluated within the stack
+		// of a test thread, even in unoptimized builds
+		for input in [
+			format!("{}1{}", "sqrt(".repeat(max), ")".repeat(max)),
+			format!("{}1{}", "max(1, ".repeat(max), ")".repeat(max)),
+			format!("{}1{}", "|".repeat(max), "|".repeat(max)),
+			format!("{}1", "-".repeat(max)),
+		] {
+			let node = parse(&input).unwrap();
+			assert_eq!(node.depth(), max);
+			assert!(validate(&input).is_ok());
+			assert!(!node.to_string().is_empty());
+			assert_eq!(
+				engine::evaluate_with_context(node, &Context::new()),
+				Ok(Decimal::ONE)
+			);
+		}
+		let options = ParseOptions {
+			max_depth: 2,
+			..ParseOptions::default()
+		};
+		assert!(parse_with_options("(|sqrt(1)|)", &options).is_err());
+		assert!(parse_with_options("(|1|) + max(1, 2)", &options).is_ok());
+	}
+
+	#[test]
+	fn thousands_separators() {
+		let options = ParseOptions {
+			thousands_separators: true,
+			..ParseOptions::default()
+		};
+		let evaluate = |input| {
+			let node = parse_with_options(input, &options).unwrap();
+			engine::evaluate_with_context(node, &Context::new()).unwrap()
+		};
+		assert_eq!(evaluate("1,000,000 + 1"), Decimal::new(1_000_001, 0));
+		assert_eq!(evaluate("(1,000) * 2"), Decimal::new(2000, 0));
+		// Commas between arguments are never separators
+		for (input, value) in [
+			("trunc(3,141)", Decimal::new(3, 0)),
+			("min(5,2)", Decimal::TWO),
+			("max(1,2)", Decimal::TWO),
+			("round(1.25,1)", Decimal::new(12, 1)),
+			("max(1,(2,000))", Decimal::new(2000, 0)),
+		] {
+			assert_eq!(evaluate(input), value, "{input}");
+		}
+		assert!(parse_with_options("1,00", &options).is_err());
+		assert!(parse("1,000").is_err());
+	}
+
+	#[test]
+	fn operator_chains() {
+		// Operators nest as deeply as groups, even without any
+		let max = DEFAULT_MAX_DEPTH;
+		for input in [
+			format!("{}1", "-".repeat(20_000)),
+			format!("1{}", "^1".repeat(200_000)),
+			format!("1{}", "!".repeat(200_000)),
+			format!("1{}", " - 1".repeat(20_000)),
+		] {
+			assert_eq!(parse(&input), Err(Error::MaxDepthExceeded(max)));
+			assert_eq!(validate(&input), Err(Error::MaxDepthExceeded(max)));
+		}
+		let chain = |depth| format!("1{}", "!".repeat(depth));
+		assert!(parse(&chain(max)).is_ok());
+		assert_eq!(parse(&chain(max + 1)), Err(Error::MaxDepthExceeded(max)));
+		let options = ParseOptions {
+			max_depth: 2,
+			..ParseOptions::default()
+		};
+		assert!(parse_with_options("-(1 - 2)", &options).is_ok());
+		assert!(parse_with_options("-(1 - 2)!", &options).is_err());
+		assert!(parse_with_options("1 + 2 + 3 + 4", &options).is_ok());
+		assert!(parse_with_options("|1 + 2 + 3|", &options).is_ok());
+		assert!(parse_with_options("|1 + 2 + 3 * 4|", &options).is_err());
+	}
+
+	#[test]
+	fn partial() {
+		let input = "1 + 2; foo";
+		let (node, offset) = parse_partial(input).unwrap();
+		assert_eq!(node, parse("1 + 2").unwrap());
+		assert_eq!(offset, 5);
+		assert_eq!(&input[offset..], "; foo");
+		for (input, expression, rest) in [
+			("sqrt(4) * 2 ", "sqrt(4) * 2", " "),
+			("1 + 2 3", "1 + 2", " 3"),
+			("(1 + 2))", "(1 + 2)", ")"),
+			("x -", "x", " -"),
+			("3 → 4", "3", " → 4"),
+			("1 + 2 == 3", "1 + 2 == 3", ""),
+			("2 * (3 + 4", "2", " * (3 + 4"),
+			("2 sqrt(", "2 sqrt", "("),
+		] {
+			let (node, offset) = parse_partial(input).unwrap();
+			assert_eq!(node, parse(expression).unwrap(), "{input}");
+			assert_eq!(&input[offset..], rest, "{input}");
+		}
+		assert_eq!(parse_partial("; 1"), Err(Error::Empty));
+		assert_eq!(
+			parse_partial("* 1"),
+			Err(Error::UnexpectedOperator {
+				op: Operator::Mul,
+				span: Span::new(0, 1)
+			})
+		);
+		assert_eq!(
+			parse_partial("1.2.3 + 1"),
+			Err(Error::Value(
+				rust_decimal::Error::ErrorString("Invalid decimal: two decimal points".to_string()),
+				Span::new(0, 5)
+			))
+		);
+	}
+
+	#[test]
+	fn comparisons() {
+		for (input, value) in [
+			("1 < 2 == 1", Decimal::ONE),
+			("3 >= 3 == 1", Decimal::ONE),
+			("1 == 2 == 0", Decimal::ONE),
+			("(2 + 3) != 6 == 1", Decimal::ONE),
+			("1 + 1 > 2", Decimal::ZERO),
+			("2 <= 1 * 3", Decimal::ONE),
+			("5! == 120", Decimal::ONE),
+			("2 × 3 − 1 == 5", Decimal::ONE),
+			("−8 ÷ 2 == -4", Decimal::ONE),
+		] {
+			assert_eq!(
+				Decimal::try_from(parse(input).unwrap()),
+				Ok(value),
+				"{input}"
+			);
+		}
+	}
+
+	#[test]
+	fn rpn() {
+		assert_eq!(
+			super::parse_rpn("3 4 + 2 *").unwrap(),
+			parse("(3 + 4) * 2").unwrap()
+		);
+	}
 }
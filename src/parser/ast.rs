@@ -1,142 +1,426 @@
-use super::{error::Error, tokenizer::Operator};
+use super::{
+	error::Error,
+	tokenizer::{Associativity, Operator, Span, Token, TokenResult},
+	Depth, DEFAULT_MAX_DEPTH,
+};
 use crate::engine::{Expr, Node};
-use std::collections::VecDeque;
+use alloc::{collections::VecDeque, vec};
+use core::mem;
 
 #[derive(Debug, PartialEq, Eq)]
 enum Element {
-	Node(Node),
-	Operator(Operator),
+	/// Node with the depth of its tree, see `Node::depth`
+	Node(Node, Span, usize),
+	/// Binary operator
+	Operator(Operator, Span),
+	/// Unary minus (`Operator::Sub`) or a prefix operator
+	Prefix(Operator, Span),
 }
 
-#[derive(Default)]
 /// Abstract syntax tree (AST) builder.
 /// It combines incoming nodes and operators into parent nodes.
 pub struct Builder {
 	buffer: VecDeque<Element>,
+	/// Unary plus waiting for its operand, which it leaves unchanged
+	plus: Option<Span>,
+	/// Maximum depth of the tree, since evaluating and dropping it are recursive
+	max_depth: usize,
+}
+
+impl Default for Builder {
+	/// Empty builder, ready for the first element of an expression. Same as `Builder::new`.
+	fn default() -> Self {
+		Self::new()
+	}
 }
 
 impl Builder {
 	pub fn new() -> Self {
-		Self::default()
+		Self::with_max_depth(DEFAULT_MAX_DEPTH)
+	}
+
+	/// Empty builder which rejects trees deeper than the given depth, see `Node::depth`,
+	/// with `Error::MaxDepthExceeded`. `Builder::new` uses `DEFAULT_MAX_DEPTH`.
+	pub fn with_max_depth(max_depth: usize) -> Self {
+		Self {
+			buffer: VecDeque::new(),
+			plus: None,
+			max_depth,
+		}
 	}
 
 	/// Adds a node element. The order of addition is important and
 	/// the operation can fail depending on the previous state.
-	pub fn add_node(&mut self, node: Node) -> Result<(), Error> {
-		match self.buffer.len() {
-			0 => self.buffer.push_back(Element::Node(node)),
-			1 => match self.buffer[0] {
-				Element::Operator(Operator::Sub) => {
-					// Previous minus was unary
-					self.buffer.pop_back();
-					self.add_node(Node::Expr(Expr::Neg(node).into()))?;
-				}
-				_ => return Err(Error::LeftoverElements),
-			},
-			n => match [&self.buffer[n - 2], &self.buffer[n - 1]] {
-				[Element::Operator(_), Element::Operator(Operator::Sub)] => {
-					// Previous minus was unary
-					self.buffer.pop_back();
-					self.add_node(Node::Expr(Expr::Neg(node).into()))?;
-				}
-				[Element::Node(_), Element::Operator(operator)] => match operator {
-					Operator::Mul => {
-						self.buffer.pop_back();
-						// Transfer ownership of the matched element
-						let prev_node = match self.buffer.pop_back() {
-							Some(Element::Node(prev_node)) => prev_node,
-							_ => unreachable!(),
-						};
-						self.add_node(Node::Expr(Expr::Mul(prev_node, node).into()))?;
-					}
-					Operator::Div => {
-						self.buffer.pop_back();
-						// Transfer ownership of the matched element
-						let prev_node = match self.buffer.pop_back() {
-							Some(Element::Node(prev_node)) => prev_node,
-							_ => unreachable!(),
-						};
-						self.add_node(Node::Expr(Expr::Div(prev_node, node).into()))?;
-					}
-					_ => {
-						// Defer add and sub expression building until the end
-						// because future operators might have a higher priority
-						self.buffer.push_back(Element::Node(node));
-					}
-				},
-				_ => return Err(Error::UnexpectedNode(node)),
-			},
+	pub fn add_node(&mut self, node: Node, span: Span) -> Result<(), Error> {
+		let depth = node.depth();
+		self.add_node_with_depth(node, span, depth)
+	}
+
+	/// Adds a node like `add_node`, with the depth of its tree already known.
+	pub(super) fn add_node_with_depth(
+		&mut self,
+		node: Node,
+		span: Span,
+		depth: usize,
+	) -> Result<(), Error> {
+		// Only the span, since formatting the node would walk its whole tree
+		crate::debug!(
+			"Adding node at {}..{} after {} elements",
+			span.start,
+			span.end,
+			self.buffer.len()
+		);
+		match self.buffer.back() {
+			None | Some(Element::Operator(..) | Element::Prefix(..)) => {
+				self.check_depth(depth)?;
+				self.plus = None;
+				self.buffer.push_back(Element::Node(node, span, depth));
+				Ok(())
+			}
+			Some(Element::Node(..)) if self.buffer.len() == 1 => Err(Error::LeftoverElements),
+			Some(Element::Node(..)) => Err(Error::UnexpectedNode { node, span }),
 		}
-		Ok(())
+	}
+
+	/// Adds the root node of a parenthesized sub-expression, built separately beforehand.
+	/// It is an operand like any other node, so this is the same as `add_node`.
+	#[doc(alias = "add_node")]
+	pub fn add_group(&mut self, node: Node, span: Span) -> Result<(), Error> {
+		self.add_node(node, span)
 	}
 
 	/// Adds an operator element. The order of addition is important and
 	/// the operation can fail depending on the previous state.
-	pub fn add_operator(&mut self, operator: Operator) -> Result<(), Error> {
-		if operator != Operator::Sub
-			&& matches!(self.buffer.back(), None | Some(Element::Operator(_)))
-		{
-			Err(Error::UnexpectedOperator(operator))
-		} else {
-			self.buffer.push_back(Element::Operator(operator));
+	/// Postfix operators are not accepted, they have to be applied beforehand.
+	pub fn add_operator(&mut self, operator: Operator, span: Span) -> Result<(), Error> {
+		crate::debug!(
+			"Adding operator {operator} after {} elements",
+			self.buffer.len()
+		);
+		let follows_node = matches!(self.buffer.back(), Some(Element::Node(..)));
+		if operator.is_postfix() || operator.is_prefix() && follows_node {
+			Err(Error::UnexpectedOperator { op: operator, span })
+		} else if follows_node {
+			// Everything binding tighter than the new operator can be combined,
+			// since the new operator cannot take its operands anymore
+			self.reduce(operator.precedence(), operator.associativity())?;
+			self.buffer.push_back(Element::Operator(operator, span));
+			Ok(())
+		} else if operator == Operator::Sub || operator.is_prefix() {
+			self.plus = None;
+			self.buffer.push_back(Element::Prefix(operator, span));
 			Ok(())
+		} else if operator == Operator::Add && self.plus.is_none() {
+			// Repeated unary plus (`++1`) is rejected
+			self.plus = Some(span);
+			Ok(())
+		} else {
+			Err(Error::UnexpectedOperator { op: operator, span })
+		}
+	}
+
+	/// Adds a value, a constant or a name as a node, or an operator, like `add_node` and `add_operator`.
+	/// Other tokens, such as groups, have to be handled beforehand.
+	pub fn try_add_token(&mut self, token: Token, span: Span) -> Result<(), Error> {
+		match token {
+			Token::Value(value) => self.add_node(Node::Value(value), span),
+			Token::Constant(constant) => self.add_node(Node::Value(constant.value()), span),
+			Token::Identifier(name) => self.add_node(Node::Identifier(name), span),
+			Token::Operator(operator) => self.add_operator(operator, span),
+			token => Err(Error::UnexpectedToken(token, span)),
 		}
 	}
 
+	/// Adds tokens straight from a tokenizer, like `try_add_token`, until a `GroupEnd`
+	/// or the end of the stream. Groups are built recursively and added with `add_group`,
+	/// and postfix operators are applied to the preceding node.
+	/// Groups may be nested as deeply as the maximum depth of the tree.
+	/// Function calls and absolute values are not supported, see `parse` for those.
+	///
+	/// ```
+	/// use calculator::parser::{ast::Builder, Tokenizer};
+	///
+	/// let mut builder = Builder::new();
+	/// builder.add_from_tokens(Tokenizer::new("(1 + 2) * 3!")).unwrap();
+	/// assert_eq!(builder.build().unwrap().to_string(), "((1 + 2) * 3!)");
+	/// ```
+	pub fn add_from_tokens(
+		&mut self,
+		mut tokens: impl Iterator<Item = TokenResult>,
+	) -> Result<(), Error> {
+		let depth = Depth::new(self.max_depth);
+		self.add_tokens_until_group_end(&mut tokens, depth)
+			.map(|_| ())
+	}
+
+	/// Adds tokens like `add_from_tokens` at the given depth of nested groups,
+	/// returning the span of the `GroupEnd` if there was one.
+	fn add_tokens_until_group_end<I: Iterator<Item = TokenResult>>(
+		&mut self,
+		tokens: &mut I,
+		depth: Depth,
+	) -> Result<Option<Span>, Error> {
+		while let Some(token) = tokens.next() {
+			let (token, span) = token.map_err(|(error, span)| Error::Value(error, span))?;
+			match token {
+				Token::GroupStart => {
+					let mut group = Builder::with_max_depth(self.max_depth);
+					let end = group
+						.add_tokens_until_group_end(tokens, depth.nested()?)?
+						.ok_or(Error::UnterminatedGroup)?;
+					let (node, depth) = group.finish_with_depth()?;
+					self.add_node_with_depth(node, span.join(end), depth)?;
+				}
+				Token::GroupEnd => return Ok(Some(span)),
+				Token::Operator(operator) if operator.is_postfix() => {
+					self.add_postfix(operator, span)?;
+				}
+				token => self.try_add_token(token, span)?,
+			}
+		}
+		Ok(None)
+	}
+
+	/// Applies a postfix operator to the last node, which binds tighter than anything
+	/// buffered before it.
+	pub(super) fn add_postfix(&mut self, operator: Operator, span: Span) -> Result<(), Error> {
+		let max_depth = self.max_depth;
+		let Some(Element::Node(node, node_span, depth)) = self.buffer.back_mut() else {
+			return Err(Error::UnexpectedOperator { op: operator, span });
+		};
+		if *depth >= max_depth {
+			return Err(Error::MaxDepthExceeded(max_depth));
+		}
+		*depth += 1;
+		let operand = mem::take(node);
+		let expr = match operator {
+			Operator::Percent => Expr::Percent(operand),
+			Operator::Fact => Expr::Factorial(operand),
+			_ => unreachable!(),
+		};
+		*node = Node::Expr(expr.into());
+		*node_span = node_span.join(span);
+		Ok(())
+	}
+
+	/// Most recently added node, which may already be combined into a parent node.
+	pub fn last_node(&self) -> Option<&Node> {
+		self.buffer.iter().rev().find_map(|element| match element {
+			Element::Node(node, ..) => Some(node),
+			_ => None,
+		})
+	}
+
+	/// Most recently added operator which has not been combined yet.
+	/// Unary minus is reported as `Operator::Sub` and unary plus as `Operator::Add`.
+	pub fn last_operator(&self) -> Option<Operator> {
+		if self.plus.is_some() {
+			return Some(Operator::Add);
+		}
+		self.buffer.iter().rev().find_map(|element| match element {
+			Element::Operator(operator, _) => Some(*operator),
+			Element::Prefix(operator, _) => Some(*operator),
+			Element::Node(..) => None,
+		})
+	}
+
+	/// Number of buffered nodes and operators. It shrinks when
+	/// operators binding tighter than a new one are combined with their operands.
+	pub fn len(&self) -> usize {
+		self.buffer.len()
+	}
+
+	/// Whether nothing has been added yet.
+	pub fn is_empty(&self) -> bool {
+		self.buffer.is_empty()
+	}
+
+	/// Remove everything added so far, keeping the allocated buffer for reuse.
+	pub fn reset(&mut self) {
+		self.buffer.clear();
+		self.plus = None;
+	}
+
 	/// Flushes the element buffer and creates a tree root node.
 	pub fn build(mut self) -> Result<Node, Error> {
-		// Buffer contents are already verified in `add` methods.
-		// It is safe to assume `element -> operator [-> element]` order.
-		match self.buffer.len() {
-			0 | 1 => match self.buffer.pop_back() {
-				Some(Element::Node(node)) => Ok(node),
-				Some(Element::Operator(opeator)) => Err(Error::UnexpectedOperator(opeator)),
-				None => Err(Error::Empty),
-			},
-			2 => Err(Error::LeftoverElements),
-			_ => {
-				// Transfer ownership of the matched element
-				let mut prev_node = match self.buffer.pop_front() {
-					Some(Element::Node(prev_node)) => prev_node,
-					_ => unreachable!(),
-				};
-				// Transfer ownership of the matched element
-				let mut prev_operator = match self.buffer.pop_front() {
-					Some(Element::Operator(prev_operator)) => prev_operator,
-					_ => unreachable!(),
-				};
-				while let Some(element) = self.buffer.pop_front() {
-					match element {
-						Element::Node(node) => match prev_operator {
-							Operator::Add => {
-								prev_node = Node::Expr(Expr::Add(prev_node, node).into());
-							}
-							Operator::Sub => {
-								prev_node = Node::Expr(Expr::Sub(prev_node, node).into());
-							}
-							_ => unreachable!(),
-						},
-						Element::Operator(operator) => prev_operator = operator,
-					}
+		self.finish()
+	}
+
+	/// Flushes the element buffer and creates a tree root node like `build`,
+	/// but keeps the builder for the next expression. It is reset even on failure.
+	pub fn finish(&mut self) -> Result<Node, Error> {
+		self.finish_with_depth().map(|(root, _)| root)
+	}
+
+	/// Flushes the element buffer like `finish`, returning the depth of the tree too.
+	pub(super) fn finish_with_depth(&mut self) -> Result<(Node, usize), Error> {
+		let root = self.flush();
+		self.reset();
+		root
+	}
+
+	fn flush(&mut self) -> Result<(Node, usize), Error> {
+		if let Some(span) = self.plus {
+			return Err(Error::UnexpectedOperator {
+				op: Operator::Add,
+				span,
+			});
+		}
+		match self.buffer.back() {
+			None => return Err(Error::Empty),
+			Some(Element::Operator(operator, span)) => {
+				return Err(Error::UnexpectedOperator {
+					op: *operator,
+					span: *span,
+				})
+			}
+			Some(Element::Prefix(operator, span)) => {
+				return Err(Error::UnexpectedOperator {
+					op: *operator,
+					span: *span,
+				})
+			}
+			Some(Element::Node(..)) => self.reduce(0, Associativity::Left)?,
+		}
+		match self.buffer.pop_back() {
+			Some(Element::Node(node, _, depth)) if self.buffer.is_empty() => Ok((node, depth)),
+			_ => Err(Error::LeftoverElements),
+		}
+	}
+
+	/// Combine trailing elements into nodes for as long as the operator preceding
+	/// the last node binds tighter than an operator of the given precedence.
+	/// The buffer must end with a node.
+	fn reduce(&mut self, precedence: u8, associativity: Associativity) -> Result<(), Error> {
+		let binds_tighter = |other: u8| {
+			other > precedence || other == precedence && associativity == Associativity::Left
+		};
+		loop {
+			let n = self.buffer.len();
+			match self.buffer.get(n.wrapping_sub(2)) {
+				Some(Element::Prefix(..)) if binds_tighter(Operator::NEGATION_PRECEDENCE) => {
+					let (node, span, depth) = self.pop_node();
+					self.check_depth(depth + 1)?;
+					let (operator, prev_span) = match self.buffer.pop_back() {
+						Some(Element::Prefix(operator, prev_span)) => (operator, prev_span),
+						_ => unreachable!(),
+					};
+					self.buffer.push_back(Element::Node(
+						Node::Expr(unary(operator, node).into()),
+						prev_span.join(span),
+						depth + 1,
+					));
+				}
+				Some(Element::Operator(operator, _)) if binds_tighter(operator.precedence()) => {
+					let (rhs, rhs_span, rhs_depth) = self.pop_node();
+					let operator = match self.buffer.pop_back() {
+						Some(Element::Operator(operator, _)) => operator,
+						_ => unreachable!(),
+					};
+					let (lhs, lhs_span, lhs_depth) = self.pop_node();
+					let expr = binary(operator, lhs, rhs);
+					// Addends of a sum are one level below it, like the operands of an addition
+					let depth = match expr {
+						Expr::Sum(_) => lhs_depth.max(rhs_depth + 1),
+						_ => lhs_depth.max(rhs_depth) + 1,
+					};
+					self.check_depth(depth)?;
+					self.buffer.push_back(Element::Node(
+						Node::Expr(expr.into()),
+						lhs_span.join(rhs_span),
+						depth,
+					));
 				}
-				Ok(prev_node)
+				_ => return Ok(()),
 			}
 		}
 	}
+
+	/// Transfer ownership of the last element, which must be a node.
+	fn pop_node(&mut self) -> (Node, Span, usize) {
+		match self.buffer.pop_back() {
+			Some(Element::Node(node, span, depth)) => (node, span, depth),
+			_ => unreachable!(),
+		}
+	}
+
+	fn check_depth(&self, depth: usize) -> Result<(), Error> {
+		if depth > self.max_depth {
+			Err(Error::MaxDepthExceeded(self.max_depth))
+		} else {
+			Ok(())
+		}
+	}
+}
+
+/// Create an expression for a binary operator.
+pub(super) fn binary(operator: Operator, lhs: Node, rhs: Node) -> Expr {
+	match operator {
+		Operator::Add => add(lhs, rhs),
+		Operator::Sub => Expr::Sub(lhs, rhs),
+		Operator::Mul => Expr::Mul(lhs, rhs),
+		Operator::Div => Expr::Div(lhs, rhs),
+		Operator::Pow => Expr::Pow(lhs, rhs),
+		Operator::Eq => Expr::Eq(lhs, rhs),
+		Operator::Ne => Expr::Ne(lhs, rhs),
+		Operator::Lt => Expr::Lt(lhs, rhs),
+		Operator::Le => Expr::Le(lhs, rhs),
+		Operator::Gt => Expr::Gt(lhs, rhs),
+		Operator::Ge => Expr::Ge(lhs, rhs),
+		Operator::BitAnd => Expr::BitAnd(lhs, rhs),
+		Operator::Percent | Operator::Fact | Operator::BitNot => unreachable!(),
+	}
+}
+
+/// Create an addition, accumulating the addends of a chain like `1 + 2 + 3` into a sum.
+fn add(lhs: Node, rhs: Node) -> Expr {
+	let Node::Expr(expr) = lhs else {
+		return Expr::Add(lhs, rhs);
+	};
+	match *expr {
+		Expr::Add(first, second) => Expr::Sum(vec![first, second, rhs]),
+		Expr::Sum(mut values) => {
+			values.push(rhs);
+			Expr::Sum(values)
+		}
+		expr => Expr::Add(Node::Expr(expr.into()), rhs),
+	}
+}
+
+/// Create an expression for unary minus (`Operator::Sub`) or a prefix operator.
+pub(super) fn unary(operator: Operator, node: Node) -> Expr {
+	match operator {
+		Operator::Sub => Expr::Neg(node),
+		Operator::BitNot => Expr::BitNot(node),
+		_ => unreachable!(),
+	}
 }
 
 #[cfg(test)]
 mod tests {
-	use super::{super::tokenizer::Operator, Builder};
-	use crate::engine::{Expr, Node};
+	use super::{
+		super::{
+			error::Error,
+			tokenizer::{Operator, Span, Token, Tokenizer},
+			DEFAULT_MAX_DEPTH,
+		},
+		Builder,
+	};
+	use crate::{
+		engine::{Expr, Node},
+		parser::parse,
+	};
 	use rust_decimal::Decimal;
 
+	/// Spans do not affect the shape of the tree
+	const SPAN: Span = Span::new(0, 0);
+
 	#[test]
 	fn add() {
 		let mut builder = Builder::new();
-		builder.add_node(Node::Value(Decimal::ONE)).unwrap();
-		builder.add_operator(Operator::Add).unwrap();
-		builder.add_node(Node::Value(Decimal::ONE)).unwrap();
+		builder.add_node(Node::Value(Decimal::ONE), SPAN).unwrap();
+		builder.add_operator(Operator::Add, SPAN).unwrap();
+		builder.add_node(Node::Value(Decimal::ONE), SPAN).unwrap();
 		let node = builder.build().unwrap();
 
 		assert_eq!(
@@ -145,12 +429,95 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn group() {
+		let mut group = Builder::new();
+		group.add_node(Node::Value(Decimal::ONE), SPAN).unwrap();
+		group.add_operator(Operator::Add, SPAN).unwrap();
+		group.add_node(Node::Value(Decimal::TWO), SPAN).unwrap();
+		let mut builder = Builder::new();
+		builder.add_group(group.build().unwrap(), SPAN).unwrap();
+		builder.add_operator(Operator::Mul, SPAN).unwrap();
+		builder
+			.add_node(Node::Value(Decimal::new(3, 0)), SPAN)
+			.unwrap();
+		let node = builder.build().unwrap();
+
+		assert_eq!(
+			node,
+			Node::Expr(
+				Expr::Mul(
+					Node::Expr(
+						Expr::Add(Node::Value(Decimal::ONE), Node::Value(Decimal::TWO)).into()
+					),
+					Node::Value(Decimal::new(3, 0))
+				)
+				.into()
+			)
+		);
+		assert_eq!(Decimal::try_from(node), Ok(Decimal::new(9, 0)));
+	}
+
+	#[test]
+	fn from_tokens() {
+		let mut builder = Builder::new();
+		builder
+			.add_from_tokens(Tokenizer::new("-(1 + 2 * (3 - 4)) ^ 2 + 50%"))
+			.unwrap();
+		let node = builder.build().unwrap();
+		assert_eq!(node, parse("-(1 + 2 * (3 - 4)) ^ 2 + 50%").unwrap());
+
+		// The rest of the stream after a closing parenthesis is left over
+		let mut tokens = Tokenizer::new("1 + 2) * 3");
+		let mut builder = Builder::new();
+		builder.add_from_tokens(&mut tokens).unwrap();
+		assert_eq!(builder.build().unwrap().to_string(), "(1 + 2)");
+		assert_eq!(tokens.count(), 2);
+	}
+
+	#[test]
+	fn from_tokens_error() {
+		let add = |input: &str| Builder::new().add_from_tokens(Tokenizer::new(input));
+		assert_eq!(add("(1 + 2"), Err(Error::UnterminatedGroup));
+		assert_eq!(
+			add("1 + 2 3"),
+			Err(Error::UnexpectedNode {
+				node: Node::Value(Decimal::new(3, 0)),
+				span: Span::new(6, 7)
+			})
+		);
+		assert_eq!(
+			add("!"),
+			Err(Error::UnexpectedOperator {
+				op: Operator::Fact,
+				span: Span::new(0, 1)
+			})
+		);
+		assert_eq!(
+			add(&"(".repeat(200_000)),
+			Err(Error::MaxDepthExceeded(DEFAULT_MAX_DEPTH))
+		);
+		let nested = |depth| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+		assert!(add(&nested(DEFAULT_MAX_DEPTH)).is_ok());
+		assert_eq!(
+			add(&nested(DEFAULT_MAX_DEPTH + 1)),
+			Err(Error::MaxDepthExceeded(DEFAULT_MAX_DEPTH))
+		);
+		assert_eq!(
+			add("0b2"),
+			Err(Error::Value(
+				rust_decimal::Error::ErrorString("Invalid decimal: invalid character".to_string()),
+				Span::new(0, 3)
+			))
+		);
+	}
+
 	#[test]
 	fn sub() {
 		let mut builder = Builder::new();
-		builder.add_node(Node::Value(Decimal::ONE)).unwrap();
-		builder.add_operator(Operator::Sub).unwrap();
-		builder.add_node(Node::Value(Decimal::ONE)).unwrap();
+		builder.add_node(Node::Value(Decimal::ONE), SPAN).unwrap();
+		builder.add_operator(Operator::Sub, SPAN).unwrap();
+		builder.add_node(Node::Value(Decimal::ONE), SPAN).unwrap();
 		let node = builder.build().unwrap();
 
 		assert_eq!(
@@ -162,9 +529,9 @@ mod tests {
 	#[test]
 	fn mul() {
 		let mut builder = Builder::new();
-		builder.add_node(Node::Value(Decimal::ONE)).unwrap();
-		builder.add_operator(Operator::Mul).unwrap();
-		builder.add_node(Node::Value(Decimal::TWO)).unwrap();
+		builder.add_node(Node::Value(Decimal::ONE), SPAN).unwrap();
+		builder.add_operator(Operator::Mul, SPAN).unwrap();
+		builder.add_node(Node::Value(Decimal::TWO), SPAN).unwrap();
 		let node = builder.build().unwrap();
 
 		assert_eq!(
@@ -176,9 +543,9 @@ mod tests {
 	#[test]
 	fn div() {
 		let mut builder = Builder::new();
-		builder.add_node(Node::Value(Decimal::ONE)).unwrap();
-		builder.add_operator(Operator::Div).unwrap();
-		builder.add_node(Node::Value(Decimal::TWO)).unwrap();
+		builder.add_node(Node::Value(Decimal::ONE), SPAN).unwrap();
+		builder.add_operator(Operator::Div, SPAN).unwrap();
+		builder.add_node(Node::Value(Decimal::TWO), SPAN).unwrap();
 		let node = builder.build().unwrap();
 
 		assert_eq!(
@@ -190,8 +557,8 @@ mod tests {
 	#[test]
 	fn neg() {
 		let mut builder = Builder::new();
-		builder.add_operator(Operator::Sub).unwrap();
-		builder.add_node(Node::Value(Decimal::ONE)).unwrap();
+		builder.add_operator(Operator::Sub, SPAN).unwrap();
+		builder.add_node(Node::Value(Decimal::ONE), SPAN).unwrap();
 		let node = builder.build().unwrap();
 
 		assert_eq!(
@@ -204,10 +571,440 @@ mod tests {
 	fn raw() {
 		let mut builder = Builder::new();
 		builder
-			.add_node(Node::Value(Decimal::ONE_THOUSAND))
+			.add_node(Node::Value(Decimal::ONE_THOUSAND), SPAN)
 			.unwrap();
 		let node = builder.build().unwrap();
 
 		assert_eq!(node, Node::Value(Decimal::ONE_THOUSAND));
 	}
+
+	#[test]
+	fn unexpected_operator_span() {
+		let mut builder = Builder::new();
+		builder
+			.add_node(Node::Value(Decimal::ONE), Span::new(0, 1))
+			.unwrap();
+		builder
+			.add_operator(Operator::Mul, Span::new(2, 3))
+			.unwrap();
+		let error = builder
+			.add_operator(Operator::Div, Span::new(4, 5))
+			.unwrap_err();
+
+		assert!(matches!(
+			error,
+			Error::UnexpectedOperator {
+				op: Operator::Div,
+				span: Span {
+					start: 4,
+					end: 5,
+					..
+				}
+			}
+		));
+	}
+
+	#[test]
+	fn unexpected_node_span() {
+		let mut builder = Builder::new();
+		builder
+			.add_node(Node::Value(Decimal::ONE), Span::new(0, 1))
+			.unwrap();
+		builder
+			.add_operator(Operator::Add, Span::new(2, 3))
+			.unwrap();
+		builder
+			.add_node(Node::Value(Decimal::ONE), Span::new(4, 5))
+			.unwrap();
+		let error = builder
+			.add_node(Node::Value(Decimal::TWO), Span::new(6, 7))
+			.unwrap_err();
+
+		assert!(matches!(
+			error,
+			Error::UnexpectedNode {
+				span: Span {
+					start: 6,
+					end: 7,
+					..
+				},
+				..
+			}
+		));
+	}
+
+	#[test]
+	fn postfix_operator() {
+		let mut builder = Builder::new();
+		builder.add_node(Node::Value(Decimal::ONE), SPAN).unwrap();
+		assert!(matches!(
+			builder.add_operator(Operator::Percent, SPAN),
+			Err(Error::UnexpectedOperator {
+				op: Operator::Percent,
+				..
+			})
+		));
+	}
+
+	fn build(elements: &[Result<Decimal, Operator>]) -> Result<Node, Error> {
+		let mut builder = Builder::new();
+		for element in elements {
+			match element {
+				Ok(value) => builder.add_node(Node::Value(*value), SPAN)?,
+				Err(operator) => builder.add_operator(*operator, SPAN)?,
+			}
+		}
+		builder.build()
+	}
+
+	#[test]
+	fn left_associative() {
+		let one = || Node::Value(Decimal::ONE);
+		for (operator, expr) in [
+			(Operator::Sub, Expr::Sub as fn(Node, Node) -> Expr),
+			(Operator::Mul, Expr::Mul),
+			(Operator::Div, Expr::Div),
+		] {
+			assert_eq!(
+				build(&[
+					Ok(Decimal::ONE),
+					Err(operator),
+					Ok(Decimal::ONE),
+					Err(operator),
+					Ok(Decimal::ONE)
+				])
+				.unwrap(),
+				Node::Expr(expr(Node::Expr(expr(one(), one()).into()), one()).into())
+			);
+		}
+	}
+
+	#[test]
+	fn sum() {
+		let value = |n| Node::Value(Decimal::new(n, 0));
+		let node = build(&[
+			Ok(Decimal::ONE),
+			Err(Operator::Add),
+			Ok(Decimal::TWO),
+			Err(Operator::Add),
+			Ok(Decimal::new(3, 0)),
+			Err(Operator::Add),
+			Ok(Decimal::new(4, 0)),
+		])
+		.unwrap();
+		assert_eq!(
+			node,
+			Node::Expr(Expr::Sum(vec![value(1), value(2), value(3), value(4)]).into())
+		);
+		assert_eq!(Decimal::try_from(node), Ok(Decimal::TEN));
+		// A single addition stays binary
+		assert_eq!(
+			build(&[Ok(Decimal::ONE), Err(Operator::Add), Ok(Decimal::TWO)]).unwrap(),
+			Node::Expr(Expr::Add(value(1), value(2)).into())
+		);
+		// Only the left operand is accumulated, the right one is a group
+		assert_eq!(
+			parse("1 - 2 + 3 + (4 + 5)").unwrap(),
+			Node::Expr(
+				Expr::Sum(vec![
+					Node::Expr(Expr::Sub(value(1), value(2)).into()),
+					value(3),
+					Node::Expr(Expr::Add(value(4), value(5)).into()),
+				])
+				.into()
+			)
+		);
+	}
+
+	#[test]
+	fn right_associative() {
+		let two = || Node::Value(Decimal::TWO);
+		assert_eq!(
+			build(&[
+				Ok(Decimal::TWO),
+				Err(Operator::Pow),
+				Ok(Decimal::TWO),
+				Err(Operator::Pow),
+				Ok(Decimal::TWO)
+			])
+			.unwrap(),
+			Node::Expr(Expr::Pow(two(), Node::Expr(Expr::Pow(two(), two()).into())).into())
+		);
+	}
+
+	#[test]
+	fn precedence() {
+		let two = || Node::Value(Decimal::TWO);
+		// Lower precedence first and last
+		for (low, high, low_expr, high_expr) in [
+			(
+				Operator::Add,
+				Operator::Mul,
+				Expr::Add as fn(Node, Node) -> Expr,
+				Expr::Mul as fn(Node, Node) -> Expr,
+			),
+			(Operator::Sub, Operator::Div, Expr::Sub, Expr::Div),
+			(Operator::Mul, Operator::Pow, Expr::Mul, Expr::Pow),
+			(Operator::Lt, Operator::Add, Expr::Lt, Expr::Add),
+		] {
+			assert_eq!(
+				build(&[
+					Ok(Decimal::TWO),
+					Err(low),
+					Ok(Decimal::TWO),
+					Err(high),
+					Ok(Decimal::TWO)
+				])
+				.unwrap(),
+				Node::Expr(low_expr(two(), Node::Expr(high_expr(two(), two()).into())).into())
+			);
+			assert_eq!(
+				build(&[
+					Ok(Decimal::TWO),
+					Err(high),
+					Ok(Decimal::TWO),
+					Err(low),
+					Ok(Decimal::TWO)
+				])
+				.unwrap(),
+				Node::Expr(low_expr(Node::Expr(high_expr(two(), two()).into()), two()).into())
+			);
+		}
+	}
+
+	#[test]
+	fn negation_precedence() {
+		let two = || Node::Value(Decimal::TWO);
+		let neg = |node| Node::Expr(Expr::Neg(node).into());
+		assert_eq!(
+			build(&[
+				Err(Operator::Sub),
+				Ok(Decimal::TWO),
+				Err(Operator::Pow),
+				Ok(Decimal::TWO)
+			])
+			.unwrap(),
+			neg(Node::Expr(Expr::Pow(two(), two()).into()))
+		);
+		assert_eq!(
+			build(&[
+				Err(Operator::Sub),
+				Ok(Decimal::TWO),
+				Err(Operator::Mul),
+				Ok(Decimal::TWO)
+			])
+			.unwrap(),
+			Node::Expr(Expr::Mul(neg(two()), two()).into())
+		);
+		assert_eq!(
+			build(&[
+				Ok(Decimal::TWO),
+				Err(Operator::Pow),
+				Err(Operator::Sub),
+				Ok(Decimal::TWO)
+			])
+			.unwrap(),
+			Node::Expr(Expr::Pow(two(), neg(two())).into())
+		);
+	}
+
+	#[test]
+	fn trailing_operator() {
+		assert!(matches!(
+			build(&[
+				Ok(Decimal::ONE),
+				Err(Operator::Add),
+				Ok(Decimal::ONE),
+				Err(Operator::Add)
+			]),
+			Err(Error::UnexpectedOperator {
+				op: Operator::Add,
+				..
+			})
+		));
+		assert!(matches!(
+			build(&[Ok(Decimal::ONE), Err(Operator::Mul), Err(Operator::Sub)]),
+			Err(Error::UnexpectedOperator {
+				op: Operator::Sub,
+				..
+			})
+		));
+	}
+
+	#[test]
+	fn inspection() {
+		let mut builder = Builder::new();
+		assert!(builder.is_empty());
+		assert_eq!(builder.last_node(), None);
+		assert_eq!(builder.last_operator(), None);
+
+		builder.add_node(Node::Value(Decimal::ONE), SPAN).unwrap();
+		assert!(!builder.is_empty());
+		assert_eq!(builder.last_node(), Some(&Node::Value(Decimal::ONE)));
+		assert_eq!(builder.last_operator(), None);
+
+		builder.add_operator(Operator::Add, SPAN).unwrap();
+		assert_eq!(builder.last_node(), Some(&Node::Value(Decimal::ONE)));
+		assert_eq!(builder.last_operator(), Some(Operator::Add));
+
+		builder.add_operator(Operator::Sub, SPAN).unwrap();
+		assert_eq!(builder.last_operator(), Some(Operator::Sub));
+
+		builder.add_node(Node::Value(Decimal::TWO), SPAN).unwrap();
+		assert_eq!(builder.last_node(), Some(&Node::Value(Decimal::TWO)));
+		assert_eq!(builder.last_operator(), Some(Operator::Sub));
+
+		builder.add_operator(Operator::Mul, SPAN).unwrap();
+		assert_eq!(
+			builder.last_node(),
+			Some(&Node::Expr(Expr::Neg(Node::Value(Decimal::TWO)).into()))
+		);
+		assert_eq!(builder.last_operator(), Some(Operator::Mul));
+	}
+
+	#[test]
+	fn len() {
+		let mut builder = Builder::new();
+		assert_eq!(builder.len(), 0);
+		assert!(builder.is_empty());
+		builder.add_node(Node::Value(Decimal::ONE), SPAN).unwrap();
+		assert_eq!(builder.len(), 1);
+		builder.add_operator(Operator::Add, SPAN).unwrap();
+		assert_eq!(builder.len(), 2);
+		builder.add_node(Node::Value(Decimal::TWO), SPAN).unwrap();
+		assert_eq!(builder.len(), 3);
+		assert!(!builder.is_empty());
+		// The previous addition binds at least as tight, so it is combined first
+		builder.add_operator(Operator::Sub, SPAN).unwrap();
+		assert_eq!(builder.len(), 2);
+	}
+
+	#[test]
+	fn reset() {
+		let mut builder = Builder::new();
+		builder.reset();
+		assert!(builder.is_empty());
+		builder.add_node(Node::Value(Decimal::ONE), SPAN).unwrap();
+		builder.add_operator(Operator::Add, SPAN).unwrap();
+		builder.reset();
+		builder.reset();
+		assert!(builder.is_empty());
+		builder.add_node(Node::Value(Decimal::TWO), SPAN).unwrap();
+		assert_eq!(builder.finish().unwrap(), Node::Value(Decimal::TWO));
+		assert!(builder.is_empty());
+		builder.add_operator(Operator::Sub, SPAN).unwrap();
+		assert!(builder.finish().is_err());
+		assert!(builder.is_empty());
+		builder.add_node(Node::Value(Decimal::ONE), SPAN).unwrap();
+		assert_eq!(builder.build().unwrap(), Node::Value(Decimal::ONE));
+	}
+
+	#[test]
+	fn unary_plus() {
+		let mut builder = Builder::new();
+		builder.add_operator(Operator::Add, SPAN).unwrap();
+		assert_eq!(builder.last_operator(), Some(Operator::Add));
+		builder.add_node(Node::Value(Decimal::ONE), SPAN).unwrap();
+		assert_eq!(builder.last_operator(), None);
+		assert_eq!(builder.build().unwrap(), Node::Value(Decimal::ONE));
+
+		let mut builder = Builder::new();
+		builder.add_operator(Operator::Add, SPAN).unwrap();
+		assert!(matches!(
+			builder.add_operator(Operator::Add, SPAN),
+			Err(Error::UnexpectedOperator {
+				op: Operator::Add,
+				..
+			})
+		));
+		builder.reset();
+		builder.add_operator(Operator::Add, SPAN).unwrap();
+		assert!(matches!(
+			builder.build(),
+			Err(Error::UnexpectedOperator {
+				op: Operator::Add,
+				..
+			})
+		));
+	}
+
+	#[test]
+	fn try_add_token() {
+		let mut builder = Builder::new();
+		builder
+			.try_add_token(Token::Value(Decimal::TWO), SPAN)
+			.unwrap();
+		builder
+			.try_add_token(Token::Operator(Operator::Mul), SPAN)
+			.unwrap();
+		builder
+			.try_add_token(Token::Identifier("x".to_string()), SPAN)
+			.unwrap();
+		assert!(matches!(
+			builder.try_add_token(Token::Operator(Operator::Fact), SPAN),
+			Err(Error::UnexpectedOperator {
+				op: Operator::Fact,
+				..
+			})
+		));
+		for token in [
+			Token::GroupStart,
+			Token::GroupEnd,
+			Token::AbsStart,
+			Token::Comma,
+		] {
+			assert!(matches!(
+				builder.try_add_token(token.clone(), SPAN),
+				Err(Error::UnexpectedToken(unexpected, _)) if unexpected == token
+			));
+		}
+		assert_eq!(
+			builder.build().unwrap(),
+			Node::Expr(Expr::Mul(Decimal::TWO.into(), Node::Identifier("x".to_string())).into())
+		);
+	}
+
+	#[test]
+	fn default() {
+		let mut builder = Builder::default();
+		assert!(builder.is_empty());
+		assert_eq!(builder.last_operator(), None);
+		builder.add_node(Node::Value(Decimal::ONE), SPAN).unwrap();
+		assert_eq!(builder.build().unwrap(), Node::Value(Decimal::ONE));
+		assert!(matches!(Builder::default().build(), Err(Error::Empty)));
+	}
+
+	#[test]
+	fn max_depth() {
+		let mut builder = Builder::with_max_depth(2);
+		builder.add_operator(Operator::Sub, SPAN).unwrap();
+		builder.add_operator(Operator::Sub, SPAN).unwrap();
+		builder.add_node(Node::Value(Decimal::ONE), SPAN).unwrap();
+		assert_eq!(builder.finish().unwrap().depth(), 2);
+		builder.add_node(Node::Value(Decimal::ONE), SPAN).unwrap();
+		builder.add_postfix(Operator::Fact, SPAN).unwrap();
+		builder.add_postfix(Operator::Fact, SPAN).unwrap();
+		assert!(matches!(
+			builder.add_postfix(Operator::Fact, SPAN),
+			Err(Error::MaxDepthExceeded(2))
+		));
+		builder.reset();
+		for _ in 0..3 {
+			builder.add_operator(Operator::Sub, SPAN).unwrap();
+		}
+		builder.add_node(Node::Value(Decimal::ONE), SPAN).unwrap();
+		assert!(matches!(builder.finish(), Err(Error::MaxDepthExceeded(2))));
+		// Addends of a sum stay at the same depth
+		for _ in 0..10 {
+			builder.add_node(Node::Value(Decimal::ONE), SPAN).unwrap();
+			builder.add_operator(Operator::Add, SPAN).unwrap();
+		}
+		builder.add_node(Node::Value(Decimal::ONE), SPAN).unwrap();
+		assert_eq!(builder.finish().unwrap().depth(), 1);
+		let deep = parse("-(-1)").unwrap();
+		assert!(matches!(
+			Builder::with_max_depth(1).add_node(deep, SPAN),
+			Err(Error::MaxDepthExceeded(1))
+		));
+	}
 }
@@ -1,32 +1,318 @@
-use super::tokenizer::Operator;
+use super::tokenizer::{Operator, Span, Token};
 use crate::engine::Node;
-use std::{error, fmt};
+use alloc::string::String;
+use core::fmt;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
+#[non_exhaustive]
 pub enum Error {
-	Value(rust_decimal::Error),
+	Value(rust_decimal::Error, Span),
 	UninitializedGroup,
 	UnterminatedGroup,
-	UnexpectedOperator(Operator),
-	UnexpectedNode(Node),
+	UnexpectedOperator {
+		op: Operator,
+		span: Span,
+	},
+	UnexpectedNode {
+		node: Node,
+		span: Span,
+	},
+	UnexpectedToken(Token, Span),
+	UndefinedVariable(String, Span),
+	UnknownFunction(String, Span),
+	InvalidArgumentCount(String, Span),
+	TooFewArguments(String, Span),
+	UnexpectedComma(Span),
+	InvalidAssignment(Span),
 	Empty,
 	LeftoverElements,
+	/// The expression has more operations than `ParseOptions::max_ops`
+	ExpressionTooComplex {
+		ops: usize,
+		max: usize,
+	},
+	/// Groups or operations are nested deeper than `ParseOptions::max_depth`
+	MaxDepthExceeded(usize),
 }
 
-impl error::Error for Error {}
+impl Error {
+	/// Location of the offending part of the input, if the error is caused by one.
+	pub fn span(&self) -> Option<Span> {
+		match self {
+			Self::Value(_, span)
+			| Self::UnexpectedOperator { span, .. }
+			| Self::UnexpectedNode { span, .. }
+			| Self::UnexpectedToken(_, span)
+			| Self::UndefinedVariable(_, span)
+			| Self::UnknownFunction(_, span)
+			| Self::InvalidArgumentCount(_, span)
+			| Self::TooFewArguments(_, span)
+			| Self::UnexpectedComma(span)
+			| Self::InvalidAssignment(span) => Some(*span),
+			Self::UninitializedGroup
+			| Self::UnterminatedGroup
+			| Self::Empty
+			| Self::LeftoverElements
+			| Self::ExpressionTooComplex { .. }
+			| Self::MaxDepthExceeded(_) => None,
+		}
+	}
+
+	/// Byte offset of the start of the offending part of the input, such as for
+	/// underlining it in an editor.
+	pub fn column_hint(&self) -> Option<usize> {
+		self.span().map(|span| span.start)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Self::Value(decimal_error, _) => Some(decimal_error),
+			_ => None,
+		}
+	}
+}
+
+// Conversion into `Box<dyn error::Error + Send + Sync>` is covered by the standard library
+
+#[cfg(feature = "std")]
+impl From<Error> for std::io::Error {
+	/// Parse errors are caused by invalid input.
+	fn from(error: Error) -> Self {
+		std::io::Error::new(std::io::ErrorKind::InvalidInput, error)
+	}
+}
+
+impl From<Error> for fmt::Error {
+	/// Formatting errors carry no details, so the cause is discarded.
+	fn from(_: Error) -> Self {
+		fmt::Error
+	}
+}
 
 impl fmt::Display for Error {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		match self {
-			Self::Value(decimal_error) => write!(f, "{decimal_error}"),
+			Self::Value(decimal_error, span) => write!(f, "{decimal_error} at {span}"),
 			Self::UninitializedGroup => write!(f, "Error: Unexpected group terminator"),
 			Self::UnterminatedGroup => write!(f, "Error: Unterminated group"),
-			Self::UnexpectedOperator(operator) => {
-				write!(f, "Error: Unexpected {:?} operator", operator)
+			Self::UnexpectedOperator { op, span } => {
+				write!(f, "Error: '{op}' is not valid at {span}")
+			}
+			Self::UnexpectedNode { node, span } => {
+				write!(
+					f,
+					"Error: Unexpected value {node} where an operator was expected at {span}"
+				)
 			}
-			Self::UnexpectedNode(node) => write!(f, "Error: Unexpected {:?} node", node),
+			Self::UnexpectedToken(token, span) => {
+				write!(f, "Error: Unexpected '{token}' at {span}")
+			}
+			Self::UndefinedVariable(name, span) => {
+				write!(f, "Error: Undefined variable {name} at {span}")
+			}
+			Self::UnknownFunction(name, span) => {
+				write!(f, "Error: Unknown function {name} at {span}")
+			}
+			Self::InvalidArgumentCount(name, span) => {
+				write!(f, "Error: Invalid number of arguments for {name} at {span}")
+			}
+			Self::TooFewArguments(name, span) => {
+				write!(f, "Error: Too few arguments for {name} at {span}")
+			}
+			Self::UnexpectedComma(span) => write!(f, "Error: Unexpected comma at {span}"),
+			Self::InvalidAssignment(span) => write!(f, "Error: Invalid assignment at {span}"),
 			Self::Empty => write!(f, "Error: Empty expression"),
 			Self::LeftoverElements => write!(f, "Error: Unterminated expression"),
+			Self::ExpressionTooComplex { ops, max } => {
+				write!(f, "Error: Expression has {ops} operations, more than {max}")
+			}
+			Self::MaxDepthExceeded(max) => {
+				write!(f, "Error: Expression is nested deeper than {max} levels")
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Error;
+	use crate::{
+		engine::{Context, Node},
+		parser::{ast::Builder, parse, parse_statement, parse_with_context, Operator, Span, Token},
+	};
+	use rust_decimal::Decimal;
+	use std::{error, fmt, io};
+
+	#[test]
+	fn span() {
+		let context = Context::new();
+		for (error, start, end) in [
+			(parse("1 + 1.2.3"), 4, 9),
+			(parse("1 + * 2"), 4, 5),
+			(parse("1 + 2 3"), 6, 7),
+			(parse_with_context("2 * x", &context), 4, 5),
+			(parse("1 + foo(2)"), 4, 7),
+			(parse("1 + sqrt(1, 2)"), 4, 8),
+			(parse("max(1)"), 0, 3),
+			(parse("1, 2"), 1, 2),
+			(
+				parse_statement("let 1 = 2", &context).map(|_| Node::default()),
+				4,
+				5,
+			),
+		] {
+			let error = error.unwrap_err();
+			assert_eq!(error.span(), Some(Span::new(start, end)), "{error}");
+			assert_eq!(error.column_hint(), Some(start), "{error}");
+		}
+		let mut builder = Builder::new();
+		builder.add_node(Node::default(), Span::new(0, 1)).unwrap();
+		let error = builder
+			.try_add_token(Token::Comma, Span::new(2, 3))
+			.unwrap_err();
+		assert_eq!(error.column_hint(), Some(2));
+
+		for input in ["", "(1", ")", "1 2"] {
+			let error = parse(input).unwrap_err();
+			assert_eq!(error.span(), None, "{input}");
+			assert_eq!(error.column_hint(), None, "{input}");
+		}
+		assert_eq!(Error::MaxDepthExceeded(1).column_hint(), None);
+	}
+
+	#[test]
+	fn source() {
+		let error = parse("1 + 1.2.3").unwrap_err();
+		assert!(matches!(error, Error::Value(..)));
+		let source = error::Error::source(&error).unwrap();
+		assert_eq!(source.to_string(), "Invalid decimal: two decimal points");
+		assert!(error::Error::source(&parse("1 +").unwrap_err()).is_none());
+	}
+
+	#[test]
+	fn conversions() {
+		fn boxed() -> Result<(), Box<dyn error::Error + Send + Sync>> {
+			parse("1 + 1.2.3")?;
+			Ok(())
+		}
+		let error = boxed().unwrap_err();
+		assert!(error.source().is_some());
+
+		fn io() -> io::Result<()> {
+			parse(")")?;
+			Ok(())
+		}
+		assert_eq!(io().unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+		fn fmt() -> fmt::Result {
+			parse(")")?;
+			Ok(())
+		}
+		assert_eq!(fmt(), Err(fmt::Error));
+	}
+
+	#[test]
+	fn unexpected_operator() {
+		let error = parse("* 1").unwrap_err();
+		assert!(matches!(
+			error,
+			Error::UnexpectedOperator {
+				op: crate::parser::Operator::Mul,
+				span: crate::parser::Span {
+					start: 0,
+					end: 1,
+					..
+				}
+			}
+		));
+		assert_eq!(
+			parse("1 + 2 - ").unwrap_err().to_string(),
+			"Error: '-' is not valid at column 7"
+		);
+		assert_eq!(
+			parse("1 + (2 * 3) 4.5").unwrap_err().to_string(),
+			"Error: Unexpected value 4.5 where an operator was expected at column 13"
+		);
+	}
+
+	#[test]
+	fn display() {
+		let span = Span::new(4, 5);
+		let name = || "f".to_string();
+		for (error, message) in [
+			(
+				Error::Value(rust_decimal::Error::ErrorString("Invalid".into()), span),
+				"Invalid at column 5",
+			),
+			(
+				Error::UninitializedGroup,
+				"Error: Unexpected group terminator",
+			),
+			(Error::UnterminatedGroup, "Error: Unterminated group"),
+			(
+				Error::UnexpectedOperator {
+					op: Operator::Mul,
+					span,
+				},
+				"Error: '*' is not valid at column 5",
+			),
+			(
+				Error::UnexpectedNode {
+					node: Node::Value(Decimal::TEN),
+					span,
+				},
+				"Error: Unexpected value 10 where an operator was expected at column 5",
+			),
+			(
+				Error::UnexpectedToken(Token::GroupStart, span),
+				"Error: Unexpected '(' at column 5",
+			),
+			(
+				Error::UndefinedVariable(name(), span),
+				"Error: Undefined variable f at column 5",
+			),
+			(
+				Error::UnknownFunction(name(), span),
+				"Error: Unknown function f at column 5",
+			),
+			(
+				Error::InvalidArgumentCount(name(), span),
+				"Error: Invalid number of arguments for f at column 5",
+			),
+			(
+				Error::TooFewArguments(name(), span),
+				"Error: Too few arguments for f at column 5",
+			),
+			(
+				Error::UnexpectedComma(span),
+				"Error: Unexpected comma at column 5",
+			),
+			(
+				Error::InvalidAssignment(span),
+				"Error: Invalid assignment at column 5",
+			),
+			(Error::Empty, "Error: Empty expression"),
+			(Error::LeftoverElements, "Error: Unterminated expression"),
+			(
+				Error::ExpressionTooComplex { ops: 3, max: 2 },
+				"Error: Expression has 3 operations, more than 2",
+			),
+			(
+				Error::MaxDepthExceeded(512),
+				"Error: Expression is nested deeper than 512 levels",
+			),
+		] {
+			assert_eq!(error.to_string(), message);
+			// Every located error names the same one-based column as its hint
+			if let Some(column) = error.column_hint() {
+				assert!(
+					message.ends_with(&format!("at column {}", column + 1)),
+					"{message}"
+				);
+			}
 		}
 	}
 }
@@ -0,0 +1,238 @@
+use super::{error::Error, tokenizer::Span};
+use crate::engine::{Expr, Node};
+use alloc::{string::String, vec::Vec};
+
+/// Names of all built-in functions.
+pub const NAMES: &[&str] = &[
+	"sqrt", "floor", "ceil", "round", "ln", "log", "rem", "gcd", "lcm", "bitor", "bitxor", "min",
+	"max", "clamp", "trunc", "sign", "sgn",
+];
+
+/// Build a built-in function call expression from the function name and its arguments.
+pub fn call(name: String, arguments: Vec<Node>, span: Span) -> Result<Expr, Error> {
+	let expr = match name.as_str() {
+		"sqrt" => exactly(arguments).map(|[value]| Expr::Sqrt(value)),
+		"floor" => exactly(arguments).map(|[value]| Expr::Floor(value)),
+		"ceil" => exactly(arguments).map(|[value]| Expr::Ceil(value)),
+		"round" => {
+			exactly(arguments).map(|[value, decimal_places]| Expr::Round(value, decimal_places))
+		}
+		"ln" => exactly(arguments).map(|[value]| Expr::Ln(value)),
+		"log" => exactly(arguments).map(|[base, value]| Expr::Log(base, value)),
+		"rem" => exactly(arguments).map(|[lhs, rhs]| Expr::Rem(lhs, rhs)),
+		"gcd" => exactly(arguments).map(|[lhs, rhs]| Expr::Gcd(lhs, rhs)),
+		"lcm" => exactly(arguments).map(|[lhs, rhs]| Expr::Lcm(lhs, rhs)),
+		"bitor" => exactly(arguments).map(|[lhs, rhs]| Expr::BitOr(lhs, rhs)),
+		"bitxor" => exactly(arguments).map(|[lhs, rhs]| Expr::BitXor(lhs, rhs)),
+		"min" => return at_least(arguments, 2, name, span).map(Expr::Min),
+		"max" => return at_least(arguments, 2, name, span).map(Expr::Max),
+		"clamp" => exactly(arguments).map(|[value, min, max]| Expr::Clamp(value, min, max)),
+		// Truncates to an integer unless decimal places are given
+		"trunc" => match arguments.len() {
+			1 => exactly(arguments).map(|[value]| Expr::Truncate(value, Node::default())),
+			_ => exactly(arguments)
+				.map(|[value, decimal_places]| Expr::Truncate(value, decimal_places)),
+		},
+		"sign" | "sgn" => exactly(arguments).map(|[value]| Expr::Signum(value)),
+		_ => return Err(Error::UnknownFunction(name, span)),
+	};
+	expr.ok_or(Error::InvalidArgumentCount(name, span))
+}
+
+/// Convert arguments into a fixed size array if there are exactly `N` of them.
+fn exactly<const N: usize>(arguments: Vec<Node>) -> Option<[Node; N]> {
+	arguments.try_into().ok()
+}
+
+/// Pass variadic arguments of the named function through if there are at least `min` of them.
+fn at_least(
+	arguments: Vec<Node>,
+	min: usize,
+	name: String,
+	span: Span,
+) -> Result<Vec<Node>, Error> {
+	if arguments.len() < min {
+		Err(Error::TooFewArguments(name, span))
+	} else {
+		Ok(arguments)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{super::tokenizer::Span, call, Error, NAMES};
+	use crate::engine::{Expr, Node};
+	use rust_decimal::Decimal;
+
+	const SPAN: Span = Span::new(0, 0);
+
+	#[test]
+	fn unary() {
+		assert_eq!(
+			call("sqrt".to_string(), vec![Node::Value(Decimal::ONE)], SPAN).unwrap(),
+			Expr::Sqrt(Node::Value(Decimal::ONE))
+		);
+		assert_eq!(
+			call("floor".to_string(), vec![Node::Value(Decimal::ONE)], SPAN).unwrap(),
+			Expr::Floor(Node::Value(Decimal::ONE))
+		);
+		assert_eq!(
+			call("ceil".to_string(), vec![Node::Value(Decimal::ONE)], SPAN).unwrap(),
+			Expr::Ceil(Node::Value(Decimal::ONE))
+		);
+		for name in ["sign", "sgn"] {
+			assert_eq!(
+				call(name.to_string(), vec![Node::Value(Decimal::ONE)], SPAN).unwrap(),
+				Expr::Signum(Node::Value(Decimal::ONE))
+			);
+		}
+	}
+
+	#[test]
+	fn binary() {
+		assert_eq!(
+			call(
+				"round".to_string(),
+				vec![Node::Value(Decimal::ONE), Node::Value(Decimal::TWO)],
+				SPAN
+			)
+			.unwrap(),
+			Expr::Round(Node::Value(Decimal::ONE), Node::Value(Decimal::TWO))
+		);
+		assert_eq!(
+			call(
+				"rem".to_string(),
+				vec![Node::Value(Decimal::ONE), Node::Value(Decimal::TWO)],
+				SPAN
+			)
+			.unwrap(),
+			Expr::Rem(Node::Value(Decimal::ONE), Node::Value(Decimal::TWO))
+		);
+	}
+
+	#[test]
+	fn invalid_argument_count() {
+		assert!(matches!(
+			call("sqrt".to_string(), vec![], SPAN),
+			Err(Error::InvalidArgumentCount(..))
+		));
+		assert!(matches!(
+			call("round".to_string(), vec![Node::Value(Decimal::ONE)], SPAN),
+			Err(Error::InvalidArgumentCount(..))
+		));
+	}
+
+	#[test]
+	fn optional_argument() {
+		let value = Node::Value(Decimal::new(15, 1));
+		assert_eq!(
+			call("trunc".to_string(), vec![value.clone()], SPAN).unwrap(),
+			Expr::Truncate(value.clone(), Node::Value(Decimal::ZERO))
+		);
+		assert_eq!(
+			call(
+				"trunc".to_string(),
+				vec![value.clone(), Node::Value(Decimal::ONE)],
+				SPAN
+			)
+			.unwrap(),
+			Expr::Truncate(value.clone(), Node::Value(Decimal::ONE))
+		);
+		assert!(matches!(
+			call(
+				"trunc".to_string(),
+				vec![value.clone(), value.clone(), value],
+				SPAN
+			),
+			Err(Error::InvalidArgumentCount(..))
+		));
+	}
+
+	#[test]
+	fn unknown() {
+		assert!(matches!(
+			call("foo".to_string(), vec![Node::Value(Decimal::ONE)], SPAN),
+			Err(Error::UnknownFunction(..))
+		));
+	}
+
+	#[test]
+	fn variadic() {
+		let arguments = vec![
+			Node::Value(Decimal::ONE),
+			Node::Value(Decimal::TWO),
+			Node::Value(Decimal::TEN),
+		];
+		assert_eq!(
+			call("min".to_string(), arguments.clone(), SPAN).unwrap(),
+			Expr::Min(arguments.clone())
+		);
+		assert_eq!(
+			call("max".to_string(), arguments.clone(), SPAN).unwrap(),
+			Expr::Max(arguments)
+		);
+	}
+
+	#[test]
+	fn ternary() {
+		let arguments = [Decimal::new(5, 0), Decimal::ONE, Decimal::TEN].map(Node::Value);
+		assert_eq!(
+			call("clamp".to_string(), arguments.to_vec(), SPAN).unwrap(),
+			Expr::Clamp(
+				arguments[0].clone(),
+				arguments[1].clone(),
+				arguments[2].clone()
+			)
+		);
+		assert!(matches!(
+			call("clamp".to_string(), arguments[..2].to_vec(), SPAN),
+			Err(Error::InvalidArgumentCount(..))
+		));
+	}
+
+	#[test]
+	fn too_few_arguments() {
+		assert!(matches!(
+			call("min".to_string(), vec![Node::Value(Decimal::ONE)], SPAN),
+			Err(Error::TooFewArguments(..))
+		));
+		assert!(matches!(
+			call("max".to_string(), vec![], SPAN),
+			Err(Error::TooFewArguments(..))
+		));
+	}
+
+	#[test]
+	fn logarithms() {
+		assert_eq!(
+			call("ln".to_string(), vec![Node::Value(Decimal::ONE)], SPAN).unwrap(),
+			Expr::Ln(Node::Value(Decimal::ONE))
+		);
+		assert_eq!(
+			call(
+				"log".to_string(),
+				vec![Node::Value(Decimal::TEN), Node::Value(Decimal::ONE)],
+				SPAN
+			)
+			.unwrap(),
+			Expr::Log(Node::Value(Decimal::TEN), Node::Value(Decimal::ONE))
+		);
+		assert!(matches!(
+			call("log".to_string(), vec![Node::Value(Decimal::TEN)], SPAN),
+			Err(Error::InvalidArgumentCount(..))
+		));
+	}
+
+	#[test]
+	fn names() {
+		for name in NAMES {
+			assert!(
+				!matches!(
+					call(name.to_string(), vec![], SPAN),
+					Err(Error::UnknownFunction(..))
+				),
+				"{name}"
+			);
+		}
+	}
+}
@@ -0,0 +1,162 @@
+use super::{
+	error::Error,
+	parse_tokens,
+	tokenizer::{Span, Token, Tokenizer},
+};
+use crate::engine::{Context, Node};
+use alloc::{string::String, vec::Vec};
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+/// Parser for input arriving in chunks, such as from a pipe or a socket.
+/// Chunks are tokenized as soon as their tokens are complete,
+/// so only the unfinished end of the input is buffered.
+pub struct Parser {
+	/// Input following the last whitespace, which may end in an unfinished token
+	pending: String,
+	/// Byte offset of the pending input in the whole input
+	offset: usize,
+	/// Tokens of the input before the pending input
+	tokens: Vec<(Token, Span)>,
+}
+
+impl Parser {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Append a chunk of input. Invalid numbers in complete tokens
+	/// are reported immediately, everything else when finishing.
+	pub fn feed(&mut self, chunk: &str) -> Result<(), Error> {
+		self.pending.push_str(chunk);
+		// Tokens never span whitespace, so everything up to it is complete
+		if let Some((index, c)) = self
+			.pending
+			.char_indices()
+			.rfind(|(_, c)| c.is_whitespace())
+		{
+			let end = index + c.len_utf8();
+			self.tokenize(end)?;
+			self.pending.drain(..end);
+			self.offset += end;
+		}
+		Ok(())
+	}
+
+	/// Parse the whole input, with values of variables from the context.
+	pub fn finish_with_context(self, context: &Context) -> Result<Node, Error> {
+		self.parse(Some(context))
+	}
+
+	/// Parse the whole input, keeping names as identifier nodes like `parse` does.
+	pub fn finish(self) -> Result<Node, Error> {
+		self.parse(None)
+	}
+
+	fn parse(mut self, context: Option<&Context>) -> Result<Node, Error> {
+		self.tokenize(self.pending.len())?;
+		let tokens = &mut Tokenizer::from_tokens(self.tokens).peekable();
+		parse_tokens(tokens, &[], context).map(|(node, ..)| node)
+	}
+
+	/// Tokenize the pending input up to a byte index.
+	fn tokenize(&mut self, end: usize) -> Result<(), Error> {
+		// The same `|` closes an absolute value group if it follows an operand
+		let follows_operand = self
+			.tokens
+			.last()
+			.is_some_and(|(token, _)| token.ends_operand());
+		for token in Tokenizer::resume(&self.pending[..end], self.offset, follows_operand) {
+			let token = token.map_err(|(error, span)| Error::Value(error, span))?;
+			self.tokens.push(token);
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Parser;
+	use crate::{
+		engine::{Context, Node},
+		parser::{parse, Error, Span},
+	};
+	use rust_decimal::Decimal;
+
+	/// Feed the input one character at a time.
+	fn feed_chars(input: &str) -> Parser {
+		let mut parser = Parser::new();
+		for c in input.chars() {
+			parser.feed(c.encode_utf8(&mut [0; 4])).unwrap();
+		}
+		parser
+	}
+
+	#[test]
+	fn single_character_chunks() {
+		let node = feed_chars("1 + 2 * 3").finish().unwrap();
+		assert_eq!(node, parse("1 + 2 * 3").unwrap());
+		assert_eq!(Decimal::try_from(node), Ok(Decimal::new(7, 0)));
+	}
+
+	#[test]
+	fn tokens_split_across_chunks() {
+		for input in [
+			"12.5 * 1e+2",
+			"|1| * | -2 |",
+			"sqrt(16) <= 3max(1, 2)",
+			"2 ^ 10 != 1_024",
+		] {
+			assert_eq!(
+				feed_chars(input).finish().unwrap(),
+				parse(input).unwrap(),
+				"{input}"
+			);
+		}
+	}
+
+	#[test]
+	fn buffers_only_unfinished_token() {
+		let mut parser = Parser::new();
+		parser.feed("1 + 23").unwrap();
+		assert_eq!(parser.pending, "23");
+		parser.feed("4 ").unwrap();
+		assert_eq!(parser.pending, "");
+		assert_eq!(parser.tokens.len(), 3);
+	}
+
+	#[test]
+	fn invalid_number_reported_early() {
+		let mut parser = Parser::new();
+		parser.feed("1 + 1.2").unwrap();
+		assert!(matches!(
+			parser.feed(".3 "),
+			Err(Error::Value(
+				_,
+				Span {
+					start: 4,
+					end: 9,
+					..
+				}
+			))
+		));
+	}
+
+	#[test]
+	fn context() {
+		let mut context = Context::new();
+		context.set("x".into(), Decimal::TWO);
+		let parser = feed_chars("x ^ 3");
+		assert_eq!(
+			Decimal::try_from(parser.finish_with_context(&context).unwrap()),
+			Ok(Decimal::new(8, 0))
+		);
+		assert_eq!(
+			feed_chars("x").finish().unwrap(),
+			Node::Identifier("x".to_string())
+		);
+		assert!(matches!(
+			feed_chars("y").finish_with_context(&context),
+			Err(Error::UndefinedVariable(..))
+		));
+	}
+}
@@ -0,0 +1,163 @@
+use super::{
+	ast,
+	error::Error,
+	tokenizer::{Operator, Token, Tokenizer},
+	DEFAULT_MAX_DEPTH,
+};
+use crate::engine::{Context, Expr, Node};
+use alloc::vec::Vec;
+
+/// Construct a tree from an input in reverse Polish notation, where operators follow
+/// their operands. Example: `3 4 + 2 *` is `(3 + 4) * 2`
+/// Names are kept as identifier nodes like `parse` does.
+pub fn parse_rpn(input: &str) -> Result<Node, Error> {
+	parse_tokens(input, None)
+}
+
+/// Construct a tree from an input in reverse Polish notation,
+/// resolving variables with values from the context.
+pub fn parse_rpn_with_context(input: &str, context: &Context) -> Result<Node, Error> {
+	parse_tokens(input, Some(context))
+}
+
+/// Each value is pushed onto a stack and each operator replaces its operands
+/// on the top of the stack with their expression, so no groups are needed.
+/// Nodes are stacked with the depth of their tree, which is limited to `DEFAULT_MAX_DEPTH`.
+fn parse_tokens(input: &str, context: Option<&Context>) -> Result<Node, Error> {
+	let mut stack: Vec<(Node, usize)> = Vec::new();
+	for token in Tokenizer::new(input) {
+		let (token, span) = token.map_err(|(error, span)| Error::Value(error, span))?;
+		let (node, depth) = match token {
+			Token::Value(value) => (Node::Value(value), 0),
+			Token::Constant(constant) => (Node::Value(constant.value()), 0),
+			Token::Identifier(name) => match context {
+				Some(context) => match context.get(&name) {
+					Some(value) => (Node::Value(value), 0),
+					None => return Err(Error::UndefinedVariable(name, span)),
+				},
+				None => (Node::Identifier(name), 0),
+			},
+			Token::Operator(operator) if operator.is_postfix() => {
+				let (value, depth) = stack
+					.pop()
+					.ok_or(Error::UnexpectedOperator { op: operator, span })?;
+				let expr = match operator {
+					Operator::Percent => Expr::Percent(value),
+					_ => Expr::Factorial(value),
+				};
+				(Node::Expr(expr.into()), depth + 1)
+			}
+			Token::Operator(operator) if operator.is_prefix() => {
+				let (value, depth) = stack
+					.pop()
+					.ok_or(Error::UnexpectedOperator { op: operator, span })?;
+				(Node::Expr(ast::unary(operator, value).into()), depth + 1)
+			}
+			Token::Operator(operator) => {
+				let (Some((rhs, rhs_depth)), Some((lhs, lhs_depth))) = (stack.pop(), stack.pop())
+				else {
+					return Err(Error::UnexpectedOperator { op: operator, span });
+				};
+				let expr = ast::binary(operator, lhs, rhs);
+				let depth = match expr {
+					Expr::Sum(_) => lhs_depth.max(rhs_depth + 1),
+					_ => lhs_depth.max(rhs_depth) + 1,
+				};
+				(Node::Expr(expr.into()), depth)
+			}
+			token => return Err(Error::UnexpectedToken(token, span)),
+		};
+		if depth > DEFAULT_MAX_DEPTH {
+			return Err(Error::MaxDepthExceeded(DEFAULT_MAX_DEPTH));
+		}
+		stack.push((node, depth));
+	}
+	let (root, _) = stack.pop().ok_or(Error::Empty)?;
+	if stack.is_empty() {
+		Ok(root)
+	} else {
+		Err(Error::LeftoverElements)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::parse_rpn_with_context;
+	use crate::{
+		engine::{Context, Expr, Node},
+		parser::{Error, Operator, Token, DEFAULT_MAX_DEPTH},
+	};
+	use rust_decimal::Decimal;
+
+	fn evaluate(input: &str) -> Decimal {
+		Decimal::try_from(parse_rpn_with_context(input, &Context::new()).unwrap()).unwrap()
+	}
+
+	#[test]
+	fn operators() {
+		assert_eq!(evaluate("3 4 + 2 *"), Decimal::new(14, 0));
+		assert_eq!(evaluate("5 1 2 + 4 * + 3 -"), Decimal::new(14, 0));
+		assert_eq!(evaluate("2 3 2 ^ ^"), Decimal::new(512, 0));
+		assert_eq!(evaluate("3 ! 50 % *"), Decimal::new(3, 0));
+		assert_eq!(evaluate("0 ~ 0xF &"), Decimal::new(15, 0));
+		assert_eq!(evaluate("1.5"), Decimal::new(15, 1));
+	}
+
+	#[test]
+	fn operand_order() {
+		assert_eq!(
+			parse_rpn_with_context("1 2 -", &Context::new()).unwrap(),
+			Node::Expr(Expr::Sub(Decimal::ONE.into(), Decimal::TWO.into()).into())
+		);
+	}
+
+	#[test]
+	fn variables() {
+		let mut context = Context::new();
+		context.set("x".to_string(), Decimal::TEN);
+		assert_eq!(
+			Decimal::try_from(parse_rpn_with_context("x 2 /", &context).unwrap()),
+			Ok(Decimal::new(5, 0))
+		);
+		assert!(matches!(
+			parse_rpn_with_context("y", &context),
+			Err(Error::UndefinedVariable(..))
+		));
+	}
+
+	#[test]
+	fn malformed_input() {
+		let parse = |input| parse_rpn_with_context(input, &Context::new());
+		assert!(matches!(parse(""), Err(Error::Empty)));
+		assert!(matches!(parse("1 2"), Err(Error::LeftoverElements)));
+		assert!(matches!(
+			parse("1 +"),
+			Err(Error::UnexpectedOperator {
+				op: Operator::Add,
+				..
+			})
+		));
+		assert!(matches!(
+			parse("!"),
+			Err(Error::UnexpectedOperator {
+				op: Operator::Fact,
+				..
+			})
+		));
+		assert!(matches!(
+			parse("(1 2 +)"),
+			Err(Error::UnexpectedToken(Token::GroupStart, _))
+		));
+	}
+
+	#[test]
+	fn max_depth() {
+		let parse = |input: &str| parse_rpn_with_context(input, &Context::new());
+		assert!(parse(&format!("1{}", " !".repeat(DEFAULT_MAX_DEPTH))).is_ok());
+		assert_eq!(
+			parse(&format!("1{}", " 1 -".repeat(100_000))),
+			Err(Error::MaxDepthExceeded(DEFAULT_MAX_DEPTH))
+		);
+		assert!(parse(&format!("1{}", " 1 +".repeat(100_000))).is_ok());
+	}
+}
@@ -1,31 +1,331 @@
-use rust_decimal::Decimal;
-use std::str::FromStr;
+use alloc::{
+	borrow::Cow,
+	boxed::Box,
+	string::{String, ToString},
+	vec::Vec,
+};
+use core::{fmt, iter, str::FromStr};
+use rust_decimal::{Decimal, MathematicalOps};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum Token {
 	Value(Decimal),
+	/// Lowercase name of a mathematical constant, such as `pi`
+	Constant(ConstantKind),
+	Identifier(String),
 	Operator(Operator),
 	GroupStart,
 	GroupEnd,
+	AbsStart,
+	AbsEnd,
+	Let,
+	Assign,
+	Comma,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum Operator {
 	Add,
 	Sub,
 	Mul,
 	Div,
+	Pow,
+	/// Postfix percentage
+	Percent,
+	/// Postfix factorial
+	Fact,
+	/// Equal to `==`
+	Eq,
+	/// Not equal to `!=`
+	Ne,
+	/// Less than `<`
+	Lt,
+	/// Less than or equal to `<=`
+	Le,
+	/// Greater than `>`
+	Gt,
+	/// Greater than or equal to `>=`
+	Ge,
+	/// Bitwise and `&`
+	BitAnd,
+	/// Prefix bitwise not `~`
+	BitNot,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+/// Mathematical constant recognized by its name.
+pub enum ConstantKind {
+	/// Ratio of a circle's circumference to its diameter `pi`
+	Pi,
+	/// Euler's number `e`, the base of the natural logarithm
+	E,
+	/// Ratio of a circle's circumference to its radius `tau`, two pi
+	Tau,
+	/// Golden ratio `phi`
+	Phi,
+}
+
+impl ConstantKind {
+	/// Golden ratio to the 28 decimal places of the other constants of `Decimal`.
+	const PHI: Decimal = Decimal::from_parts(3068950840, 3775324002, 877137982, false, 28);
+
+	/// Value of the constant, as precise as `Decimal` allows.
+	pub fn value(self) -> Decimal {
+		match self {
+			ConstantKind::Pi => Decimal::PI,
+			ConstantKind::E => Decimal::E,
+			ConstantKind::Tau => Decimal::TWO_PI,
+			ConstantKind::Phi => Self::PHI,
+		}
+	}
+
+	/// Name of the constant as it is written in the input.
+	pub fn name(self) -> &'static str {
+		match self {
+			ConstantKind::Pi => "pi",
+			ConstantKind::E => "e",
+			ConstantKind::Tau => "tau",
+			ConstantKind::Phi => "phi",
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+/// Grouping of operators with the same precedence.
+pub enum Associativity {
+	/// `a - b - c` is `(a - b) - c`
+	Left,
+	/// `a ^ b ^ c` is `a ^ (b ^ c)`
+	Right,
+}
+
+impl Operator {
+	/// Binding strength of unary minus and other prefix operators. It binds tighter
+	/// than multiplication, but looser than powers, so that `-2 ^ 2` is `-(2 ^ 2)`.
+	pub const NEGATION_PRECEDENCE: u8 = 5;
+
+	/// Binding strength, higher binds tighter.
+	pub fn precedence(self) -> u8 {
+		match self {
+			Operator::Eq
+			| Operator::Ne
+			| Operator::Lt
+			| Operator::Le
+			| Operator::Gt
+			| Operator::Ge => 1,
+			Operator::BitAnd => 2,
+			Operator::Add | Operator::Sub => 3,
+			Operator::Mul | Operator::Div => 4,
+			Operator::BitNot => Self::NEGATION_PRECEDENCE,
+			Operator::Pow => 6,
+			Operator::Percent | Operator::Fact => 7,
+		}
+	}
+
+	pub fn associativity(self) -> Associativity {
+		match self {
+			Operator::Pow => Associativity::Right,
+			_ => Associativity::Left,
+		}
+	}
+
+	/// Postfix operators follow their only operand.
+	pub fn is_postfix(self) -> bool {
+		matches!(self, Operator::Percent | Operator::Fact)
+	}
+
+	/// Prefix operators precede their only operand. Unary minus is not one of them,
+	/// since the same operator is also binary.
+	pub fn is_prefix(self) -> bool {
+		self == Operator::BitNot
+	}
+}
+
+impl fmt::Display for Operator {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Operator::Add => write!(f, "+"),
+			Operator::Sub => write!(f, "-"),
+			Operator::Mul => write!(f, "*"),
+			Operator::Div => write!(f, "/"),
+			Operator::Pow => write!(f, "^"),
+			Operator::Percent => write!(f, "%"),
+			Operator::Fact => write!(f, "!"),
+			Operator::Eq => write!(f, "=="),
+			Operator::Ne => write!(f, "!="),
+			Operator::Lt => write!(f, "<"),
+			Operator::Le => write!(f, "<="),
+			Operator::Gt => write!(f, ">"),
+			Operator::Ge => write!(f, ">="),
+			Operator::BitAnd => write!(f, "&"),
+			Operator::BitNot => write!(f, "~"),
+		}
+	}
+}
+
+impl fmt::Display for Token {
+	/// Tokens are displayed as they are written in the input.
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Token::Value(value) => write!(f, "{value}"),
+			Token::Constant(constant) => write!(f, "{}", constant.name()),
+			Token::Identifier(name) => write!(f, "{name}"),
+			Token::Operator(operator) => write!(f, "{operator}"),
+			Token::GroupStart => write!(f, "("),
+			Token::GroupEnd => write!(f, ")"),
+			Token::AbsStart | Token::AbsEnd => write!(f, "|"),
+			Token::Let => write!(f, "let"),
+			Token::Assign => write!(f, "="),
+			Token::Comma => write!(f, ","),
+		}
+	}
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+/// Byte range of a token in the input string.
+pub struct Span {
+	pub start: usize,
+	pub end: usize,
+	/// Zero-based line of the start of the token, only counted by `Tokenizer::with_line_tracking`
+	pub line: usize,
+}
+
+impl fmt::Display for Span {
+	/// Columns are one-based, as they are shown in text editors.
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "column {}", self.start + 1)
+	}
+}
+
+impl Span {
+	/// Create a span of a byte range on the first line.
+	pub const fn new(start: usize, end: usize) -> Self {
+		Self {
+			start,
+			end,
+			line: 0,
+		}
+	}
+
+	/// Create a span covering both spans and everything in between.
+	pub fn join(self, other: Self) -> Self {
+		Self {
+			start: self.start.min(other.start),
+			end: self.end.max(other.end),
+			line: self.line.min(other.line),
+		}
+	}
 }
 
-/// Split an input string into stream of tokens.
-pub fn tokenize(input: &str) -> impl Iterator<Item = Result<Token, rust_decimal::Error>> + '_ {
+/// A token with its span, or an invalid number with its span.
+pub type TokenResult = Result<(Token, Span), (rust_decimal::Error, Span)>;
+
+/// Iterator splitting an input string into a stream of tokens and their spans.
+/// Unlike an opaque iterator, it can be named and stored.
+pub struct Tokenizer<'a> {
+	tokens: Box<dyn Iterator<Item = TokenResult> + 'a>,
+}
+
+impl<'a> Tokenizer<'a> {
+	pub fn new(input: &'a str) -> Self {
+		Self {
+			tokens: Box::new(tokenize(input, 0, false, false)),
+		}
+	}
+
+	/// Tokenize an input like `new`, reading commas directly between digits as
+	/// thousands separators of a number, such as `1,000`. Commas separating
+	/// the arguments of a function call are never joined. Example: `max(1,2)`
+	pub fn with_thousands_separators(input: &'a str) -> Self {
+		Self {
+			tokens: Box::new(tokenize(input, 0, false, true)),
+		}
+	}
+
+	/// Tokenize an input of several lines, counting the line of each token in its span.
+	/// Newlines are whitespace like any other, so tokens are the same as with `new`.
+	pub fn with_line_tracking(input: &'a str) -> Self {
+		let mut line = 0;
+		let mut position = 0;
+		let tokens = tokenize(input, 0, false, false).map(move |mut token| {
+			// Tokens never contain whitespace, so only newlines between them are counted
+			let (Ok((_, span)) | Err((_, span))) = &mut token;
+			line += input[position..span.start].matches('\n').count();
+			position = span.start;
+			span.line = line;
+			token
+		});
+		Self {
+			tokens: Box::new(tokens),
+		}
+	}
+
+	/// Continue tokenizing an input split at whitespace, where the part starts
+	/// at a byte offset and may follow an operand from the previous part.
+	pub(super) fn resume(input: &'a str, offset: usize, follows_operand: bool) -> Self {
+		Self {
+			tokens: Box::new(tokenize(input, offset, follows_operand, false)),
+		}
+	}
+
+	/// Replay tokens which have already been tokenized.
+	pub(super) fn from_tokens(tokens: Vec<(Token, Span)>) -> Self {
+		Self {
+			tokens: Box::new(tokens.into_iter().map(Ok)),
+		}
+	}
+}
+
+impl Iterator for Tokenizer<'_> {
+	type Item = TokenResult;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.tokens.next()
+	}
+}
+
+impl Token {
+	/// Tokenize an input string, same as `Tokenizer::new`.
+	pub fn iter_from_str(input: &str) -> Tokenizer<'_> {
+		Tokenizer::new(input)
+	}
+
+	/// Determine whether an operand can end with the token.
+	pub(super) fn ends_operand(&self) -> bool {
+		match self {
+			Token::Operator(operator) => operator.is_postfix(),
+			Token::Value(_)
+			| Token::Constant(_)
+			| Token::Identifier(_)
+			| Token::GroupEnd
+			| Token::AbsEnd => true,
+			_ => false,
+		}
+	}
+}
+
+/// Split an input string into stream of tokens and their spans.
+fn tokenize(
+	input: &str,
+	offset: usize,
+	follows_operand: bool,
+	thousands_separators: bool,
+) -> impl Iterator<Item = TokenResult> + '_ {
 	// Since there are only two classes of tokens (static operators and dynamic values)
 	// static tokens can be used as separators, splitting the input string.
 	// 1. Split the string and separates separators
 	// 2. Format and filter remaining chunks
-	// 3. Identify chunks and map them to a specific token
-	input
-		.split_inclusive(is_separator)
+	// 3. Join two character comparison operators
+	// 4. Identify chunks and map them to a specific token
+	let mut chunks = input
+		.split_inclusive(separator_matcher())
 		.flat_map(|mut chunk| {
 			// `str::split_inclusve` includes separators with the previous chunk.
 			// They need to be split from the chunk for easier parsing.
@@ -33,46 +333,368 @@ pub fn tokenize(input: &str) -> impl Iterator<Item = Result<Token, rust_decimal:
 			let mut separator = "";
 			if let Some(c) = chunk.chars().last() {
 				if is_separator(c) {
-					// Separators are split by length (1) because they are single characters.
+					// Separators are split by the length of their single character.
 					// This part needs to be reworked for longer separators.
-					(chunk, separator) = chunk.split_at(chunk.len() - 1);
+					(chunk, separator) = chunk.split_at(chunk.len() - c.len_utf8());
 				}
 			}
 			[chunk, separator].into_iter()
 		})
 		.flat_map(str::split_whitespace)
 		.map(str::trim)
+		.flat_map(split_coefficient)
 		.filter(|value| !value.is_empty())
-		.map(|chunk| match chunk {
-			"+" => Ok(Token::Operator(Operator::Add)),
-			"-" => Ok(Token::Operator(Operator::Sub)),
-			"*" => Ok(Token::Operator(Operator::Mul)),
-			"/" => Ok(Token::Operator(Operator::Div)),
-			"(" => Ok(Token::GroupStart),
-			")" => Ok(Token::GroupEnd),
-			value => parse_number(value).map(Token::Value),
+		.map(move |chunk| {
+			// All chunks are subslices of the input string,
+			// so their offset is the distance between the two pointers.
+			let start = offset + chunk.as_ptr() as usize - input.as_ptr() as usize;
+			let span = Span::new(start, start + chunk.len());
+			(chunk, span)
 		})
+		.peekable();
+	// Whether each open parenthesis starts the arguments of a function call
+	let mut calls = Vec::new();
+	let mut follows_name = false;
+	iter::from_fn(move || {
+		// Comparison operators ending with `=` are joined with the preceding separator
+		// if there is no whitespace in between. Example: `<`, `=` -> `<=`
+		let (chunk, span) = chunks.next()?;
+		match chunk {
+			"(" => calls.push(follows_name),
+			")" => {
+				calls.pop();
+			}
+			_ => {}
+		}
+		follows_name = is_identifier(chunk);
+		if matches!(chunk, "=" | "!" | "<" | ">") {
+			if let Some(&("=", next)) = chunks.peek() {
+				if next.start == span.end {
+					chunks.next();
+					let span = span.join(next);
+					return Some((&input[span.start - offset..span.end - offset], span));
+				}
+			}
+		}
+		// Commas directly between digits separate thousands, so they are joined
+		// with their number unless they separate arguments of a function call.
+		// Example: `1`, `,`, `000` -> `1,000`
+		if thousands_separators
+			&& calls.last() != Some(&true)
+			&& chunk.starts_with(|c: char| c.is_ascii_digit())
+		{
+			let mut span = span;
+			while let Some(&(",", comma)) = chunks.peek() {
+				let rest = &input[comma.end - offset..];
+				if comma.start != span.end || !rest.starts_with(|c: char| c.is_ascii_digit()) {
+					break;
+				}
+				chunks.next();
+				span = chunks.next().map_or(comma, |(_, digits)| span.join(digits));
+			}
+			return Some((&input[span.start - offset..span.end - offset], span));
+		}
+		Some((chunk, span))
+	})
+	.map({
+		// The same `|` character both opens and closes absolute value groups.
+		// It closes a group only if it directly follows an operand.
+		let mut follows_operand = follows_operand;
+		move |(chunk, span)| {
+			let token = match chunk {
+				"+" => Ok(Token::Operator(Operator::Add)),
+				"-" | "−" => Ok(Token::Operator(Operator::Sub)),
+				"*" | "×" => Ok(Token::Operator(Operator::Mul)),
+				"/" | "÷" => Ok(Token::Operator(Operator::Div)),
+				"^" => Ok(Token::Operator(Operator::Pow)),
+				"%" => Ok(Token::Operator(Operator::Percent)),
+				"!" => Ok(Token::Operator(Operator::Fact)),
+				"==" => Ok(Token::Operator(Operator::Eq)),
+				"!=" => Ok(Token::Operator(Operator::Ne)),
+				"<" => Ok(Token::Operator(Operator::Lt)),
+				"<=" => Ok(Token::Operator(Operator::Le)),
+				">" => Ok(Token::Operator(Operator::Gt)),
+				">=" => Ok(Token::Operator(Operator::Ge)),
+				"&" => Ok(Token::Operator(Operator::BitAnd)),
+				"~" => Ok(Token::Operator(Operator::BitNot)),
+				"(" => Ok(Token::GroupStart),
+				")" => Ok(Token::GroupEnd),
+				"|" if follows_operand => Ok(Token::AbsEnd),
+				"|" => Ok(Token::AbsStart),
+				"=" => Ok(Token::Assign),
+				"," => Ok(Token::Comma),
+				"let" => Ok(Token::Let),
+				"pi" => Ok(Token::Constant(ConstantKind::Pi)),
+				"e" => Ok(Token::Constant(ConstantKind::E)),
+				"tau" => Ok(Token::Constant(ConstantKind::Tau)),
+				"phi" => Ok(Token::Constant(ConstantKind::Phi)),
+				name if is_identifier(name) => Ok(Token::Identifier(name.to_string())),
+				value => parse_number(value).map(Token::Value),
+			};
+			follows_operand = token.as_ref().is_ok_and(Token::ends_operand);
+			crate::debug!("Token {token:?} at {}..{}", span.start, span.end);
+			token
+				.map(|token| (token, span))
+				.map_err(|error| (error, span))
+		}
+	})
+}
+
+/// Determine whether a character can be part of any token or the whitespace between them.
+pub(super) fn is_expression_char(value: char) -> bool {
+	is_separator(value)
+		|| value.is_whitespace()
+		|| value.is_alphanumeric()
+		|| value == '_'
+		|| value == '.'
 }
 
 /// Determine whether a character is a token separator.
 fn is_separator(value: char) -> bool {
-	matches!(value, '+' | '-' | '*' | '/' | '(' | ')')
+	matches!(
+		value,
+		'+' | '-' | '*' | '/' | '(' | ')' | '^' | '%' | '!' | '|' | '=' | ',' | '<' | '>' | '&' | '~'
+			// Typographic minus, multiplication and division signs
+			| '−' | '×' | '÷'
+	)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+/// Progress of a number in scientific notation within the current chunk.
+enum Notation {
+	/// Nothing has been scanned since the last separator or whitespace
+	Start,
+	/// Only digits and decimal points have been scanned
+	Mantissa,
+	/// A single zero, which may start a hexadecimal prefix
+	Zero,
+	/// Hexadecimal digits and points after the `0x` prefix
+	Hexadecimal,
+	/// A mantissa followed by an exponent marker (`e` or `E`, or `p` or `P` after hexadecimal digits)
+	Exponent,
+	/// Anything else, including a complete exponent sign
+	Other,
+}
+
+/// Create a stateful separator predicate which, unlike `is_separator`,
+/// keeps exponent signs attached to their numbers.
+/// Example: `1e+5` is a single chunk instead of `1e`, `+`, `5`, and so is `0x1p-4`.
+fn separator_matcher() -> impl FnMut(char) -> bool {
+	let mut notation = Notation::Start;
+	move |value| {
+		if is_separator(value) {
+			if notation == Notation::Exponent && matches!(value, '+' | '-') {
+				notation = Notation::Other;
+				return false;
+			}
+			notation = Notation::Start;
+			return true;
+		}
+		notation = match (notation, value) {
+			(_, value) if value.is_whitespace() => Notation::Start,
+			(Notation::Start, '0') => Notation::Zero,
+			(Notation::Zero, 'x') => Notation::Hexadecimal,
+			(Notation::Hexadecimal, 'p' | 'P') => Notation::Exponent,
+			(Notation::Hexadecimal, value)
+				if value.is_ascii_hexdigit() || matches!(value, '.' | '_') =>
+			{
+				Notation::Hexadecimal
+			}
+			(Notation::Start | Notation::Zero | Notation::Mantissa, '0'..='9' | '.') => {
+				Notation::Mantissa
+			}
+			(Notation::Zero | Notation::Mantissa, 'e' | 'E') => Notation::Exponent,
+			_ => Notation::Other,
+		};
+		false
+	}
+}
+
+/// Determine whether a string token is a name, such as `ans` or `_`.
+/// Names start with a letter or an underscore, followed by letters, digits and underscores.
+fn is_identifier(value: &str) -> bool {
+	let mut chars = value.chars();
+	chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+		&& chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Split a number directly followed by a name from the name.
+/// It is an implicit multiplication. Example: `2sqrt` -> `2`, `sqrt`
+/// Exponents of scientific notation are part of the number, even if it is invalid.
+fn split_coefficient(chunk: &str) -> [&str; 2] {
+	let is_decimal = chunk.starts_with(|c: char| c.is_ascii_digit())
+		&& !["0x", "0o", "0b"]
+			.iter()
+			.any(|prefix| chunk.starts_with(prefix));
+	if is_decimal {
+		if let Some(index) = chunk.find(|c: char| !c.is_ascii_digit() && c != '.' && c != '_') {
+			let (number, name) = chunk.split_at(index);
+			if is_identifier(name) && !is_exponent(name) {
+				return [number, name];
+			}
+		}
+	}
+	[chunk, ""]
+}
+
+/// Determine whether a suffix of a number is the exponent of scientific notation.
+/// Example: `e5`, `E-3`, `e+1_0`
+fn is_exponent(suffix: &str) -> bool {
+	let Some(exponent) = suffix.strip_prefix(['e', 'E']) else {
+		return false;
+	};
+	let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+	exponent.starts_with(|c: char| c.is_ascii_digit())
+		&& exponent.chars().all(|c| c.is_ascii_digit() || c == '_')
 }
 
 /// Try converting a string token into a decimal.
+/// Commas are only part of numbers with thousands separators.
 fn parse_number(value: &str) -> Result<Decimal, rust_decimal::Error> {
+	if value.contains(',') {
+		return parse_number(&strip_thousands_separators(value)?);
+	}
 	if let Some(hex_value) = value.strip_prefix("0x") {
-		Decimal::from_str_radix(hex_value, 16)
+		if hex_value.contains(['p', 'P']) {
+			return parse_hex_float(&strip_digit_separators(hex_value, 16)?);
+		}
+		Decimal::from_str_radix(&strip_digit_separators(hex_value, 16)?, 16)
+	} else if let Some(octal_value) = value.strip_prefix("0o") {
+		Decimal::from_str_radix(&strip_digit_separators(octal_value, 8)?, 8)
+	} else if let Some(binary_value) = value.strip_prefix("0b") {
+		Decimal::from_str_radix(&strip_digit_separators(binary_value, 2)?, 2)
+	} else if value.contains(['e', 'E']) {
+		Decimal::from_scientific(&strip_digit_separators(value, 10)?)
+	} else {
+		Decimal::from_str(&strip_digit_separators(value, 10)?)
+	}
+}
+
+/// Convert a hexadecimal float without the `0x` prefix, such as `1.8p1`, into a decimal.
+/// The hexadecimal mantissa is scaled by two to the power of the decimal exponent after `p`.
+/// Only values which `Decimal` represents exactly are accepted.
+fn parse_hex_float(value: &str) -> Result<Decimal, rust_decimal::Error> {
+	let invalid = |message: &str| rust_decimal::Error::ErrorString(message.to_string());
+	let (mantissa, exponent) = value
+		.split_once(['p', 'P'])
+		.ok_or_else(|| invalid("Invalid decimal: missing binary exponent"))?;
+	let exponent: i64 = exponent
+		.parse()
+		.map_err(|_| invalid("Invalid decimal: invalid binary exponent"))?;
+	let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+	if integer.is_empty() && fraction.is_empty() {
+		return Err(invalid("Invalid decimal: missing hexadecimal mantissa"));
+	}
+	if !integer
+		.chars()
+		.chain(fraction.chars())
+		.all(|c| c.is_ascii_hexdigit())
+	{
+		return Err(invalid("Invalid decimal: invalid character"));
+	}
+	let digits = Decimal::from_str_radix(&[integer, fraction].concat(), 16)?;
+	if digits.is_zero() {
+		return Ok(Decimal::ZERO);
+	}
+	// Each hexadecimal digit of the fraction is four binary digits.
+	// Exponents too small to subtract them from are far too small for `Decimal` anyway.
+	let exponent = i64::try_from(fraction.len())
+		.ok()
+		.and_then(|len| len.checked_mul(4))
+		.and_then(|bits| exponent.checked_sub(bits))
+		.ok_or(rust_decimal::Error::Underflow)?;
+	let power = Decimal::TWO.checked_powu(exponent.unsigned_abs());
+	if exponent >= 0 {
+		power
+			.and_then(|power| digits.checked_mul(power))
+			.ok_or(rust_decimal::Error::ExceedsMaximumPossibleValue)
 	} else {
-		Decimal::from_str(value)
+		// Division rounds values with more fractional digits than `Decimal` can hold
+		power
+			.and_then(|power| {
+				digits
+					.checked_div(power)
+					.filter(|value| *value * power == digits)
+			})
+			.map(|value| value.normalize())
+			.ok_or(rust_decimal::Error::Underflow)
+	}
+}
+
+/// Remove underscores used as digit separators. Example: `1_000` -> `1000`
+/// Underscores are only allowed between two digits of the given radix.
+fn strip_digit_separators(value: &str, radix: u32) -> Result<Cow<'_, str>, rust_decimal::Error> {
+	if !value.contains('_') {
+		return Ok(Cow::Borrowed(value));
+	}
+	let chars: Vec<char> = value.chars().collect();
+	let is_digit = |index: Option<usize>| {
+		index
+			.and_then(|index| chars.get(index))
+			.is_some_and(|c| c.is_digit(radix))
+	};
+	for (index, _) in chars.iter().enumerate().filter(|(_, c)| **c == '_') {
+		if !is_digit(index.checked_sub(1)) || !is_digit(Some(index + 1)) {
+			return Err(rust_decimal::Error::ErrorString(
+				"Invalid decimal: misplaced digit separator".to_string(),
+			));
+		}
 	}
+	Ok(Cow::Owned(value.replace('_', "")))
+}
+
+/// Remove commas separating thousands of a decimal number. Example: `1,000.5` -> `1000.5`
+/// The first group has up to three digits and every other group exactly three.
+fn strip_thousands_separators(value: &str) -> Result<String, rust_decimal::Error> {
+	let (integer, fraction) = value.split_at(value.find('.').unwrap_or(value.len()));
+	let mut groups = integer.split(',');
+	let first = groups.next().unwrap_or_default();
+	let is_group = |group: &str, len| group.bytes().all(|c| c.is_ascii_digit()) && len;
+	let valid = is_group(first, (1..=3).contains(&first.len()))
+		&& groups.all(|group| is_group(group, group.len() == 3))
+		&& !fraction.contains(',');
+	if !valid {
+		return Err(rust_decimal::Error::ErrorString(
+			"Invalid decimal: misplaced thousands separator".to_string(),
+		));
+	}
+	Ok(value.replace(',', ""))
 }
 
 #[cfg(test)]
 mod tests {
-	use super::{parse_number, tokenize, Operator, Token};
+	use super::{parse_number, Associativity, ConstantKind, Operator, Span, Token, Tokenizer};
 	use rust_decimal::Decimal;
 
+	/// Tokenize the input and discard the spans.
+	fn tokenize(input: &str) -> impl Iterator<Item = Result<Token, rust_decimal::Error>> + '_ {
+		super::tokenize(input, 0, false, false)
+			.map(|token| token.map(|(token, _)| token).map_err(|(error, _)| error))
+	}
+
+	#[test]
+	fn parse_thousands_separators() {
+		assert_eq!(parse_number("1,000,000"), Ok(Decimal::new(1000000, 0)));
+		assert_eq!(parse_number("1,000.00"), Ok(Decimal::new(100000, 2)));
+		assert_eq!(parse_number("999"), Ok(Decimal::new(999, 0)));
+		for value in ["1,00", "1000,000", ",000", "1,", "1,000.000,0", "0x1,000"] {
+			assert!(parse_number(value).is_err(), "{value}");
+		}
+		let tokenize = |input| {
+			Tokenizer::with_thousands_separators(input)
+				.map(|token| token.map(|(token, _)| token).map_err(|(error, _)| error))
+				.collect::<Vec<_>>()
+		};
+		let tokens = tokenize("1,000 + max(1,000, (2,000))");
+		assert_eq!(tokens[0], Ok(Token::Value(Decimal::new(1000, 0))));
+		assert_eq!(tokens[4], Ok(Token::Value(Decimal::ONE)));
+		assert_eq!(tokens[6], Ok(Token::Value(Decimal::ZERO)));
+		assert_eq!(tokens[9], Ok(Token::Value(Decimal::new(2000, 0))));
+		assert_eq!(tokens.len(), 12);
+		assert!(tokenize("1,00").iter().any(Result::is_err));
+		assert!(tokenize("1,2").iter().any(Result::is_err));
+	}
+
 	#[test]
 	fn parse_integer() {
 		assert_eq!(parse_number("0"), Ok(Decimal::ZERO));
@@ -91,9 +713,106 @@ mod tests {
 		assert_eq!(parse_number("0x539"), Ok(Decimal::new(1337, 0)));
 	}
 
+	#[test]
+	fn parse_hexadecimal_float() {
+		for (input, value) in [
+			("0x1p1", Decimal::TWO),
+			("0x1.8p1", Decimal::new(3, 0)),
+			("0xFFp-4", Decimal::new(159375, 4)),
+			("0x1P+4", Decimal::new(16, 0)),
+			("0x.8p0", Decimal::new(5, 1)),
+			("0x1_0p0", Decimal::new(16, 0)),
+			("0x0p99999", Decimal::ZERO),
+			("0x1p-20", Decimal::new(95367431640625, 20)),
+		] {
+			assert_eq!(parse_number(input), Ok(value), "{input}");
+		}
+		assert_eq!(
+			parse_number("0x1p96"),
+			Err(rust_decimal::Error::ExceedsMaximumPossibleValue)
+		);
+		assert_eq!(
+			parse_number("0x1p-100"),
+			Err(rust_decimal::Error::Underflow)
+		);
+		// Extreme exponents fail instead of overflowing
+		for (input, error) in [
+			("0x1.8p-9223372036854775805", rust_decimal::Error::Underflow),
+			("0x1p-9223372036854775808", rust_decimal::Error::Underflow),
+			(
+				"0x1p9223372036854775807",
+				rust_decimal::Error::ExceedsMaximumPossibleValue,
+			),
+			(
+				"0x.1p9223372036854775807",
+				rust_decimal::Error::ExceedsMaximumPossibleValue,
+			),
+		] {
+			assert_eq!(parse_number(input), Err(error), "{input}");
+		}
+		for input in ["0x1p", "0xp1", "0x1p1.5", "0x1g1p1", "0x1.8.8p1"] {
+			assert!(parse_number(input).is_err(), "{input}");
+		}
+	}
+
+	#[test]
+	fn tokenize_hexadecimal_float() {
+		assert_eq!(
+			tokenize("0x1.8p1*0xFFp-4-0x1p+1").collect::<Vec<_>>(),
+			[
+				Ok(Token::Value(Decimal::new(3, 0))),
+				Ok(Token::Operator(Operator::Mul)),
+				Ok(Token::Value(Decimal::new(159375, 4))),
+				Ok(Token::Operator(Operator::Sub)),
+				Ok(Token::Value(Decimal::TWO)),
+			]
+		);
+	}
+
+	#[test]
+	fn parse_octal() {
+		assert_eq!(parse_number("0o0"), Ok(Decimal::ZERO));
+		assert_eq!(parse_number("0o777"), Ok(Decimal::new(511, 0)));
+	}
+
+	#[test]
+	fn parse_binary() {
+		assert_eq!(parse_number("0b0"), Ok(Decimal::ZERO));
+		assert_eq!(parse_number("0b1111"), Ok(Decimal::new(15, 0)));
+		assert!(parse_number("0b2").is_err());
+	}
+
+	#[test]
+	fn parse_scientific() {
+		assert_eq!(parse_number("1e5"), Ok(Decimal::new(100000, 0)));
+		assert_eq!(parse_number("1.5E+3"), Ok(Decimal::new(1500, 0)));
+		assert_eq!(parse_number("2.5e-4"), Ok(Decimal::new(25, 5)));
+	}
+
+	#[test]
+	fn parse_digit_separators() {
+		assert_eq!(parse_number("1_000"), Ok(Decimal::ONE_THOUSAND));
+		assert_eq!(
+			parse_number("1_000_000.000_1"),
+			Ok(Decimal::new(10000000001, 4))
+		);
+		assert_eq!(parse_number("0x1_00"), Ok(Decimal::new(256, 0)));
+		assert_eq!(parse_number("0b1_0"), Ok(Decimal::TWO));
+	}
+
+	#[test]
+	fn parse_misplaced_digit_separators() {
+		assert!(parse_number("_1").is_err());
+		assert!(parse_number("1_").is_err());
+		assert!(parse_number("1__0").is_err());
+		assert!(parse_number("1_.5").is_err());
+		assert!(parse_number("1._5").is_err());
+		assert!(parse_number("0x_1").is_err());
+	}
+
 	#[test]
 	fn tokenize_input() {
-		let mut tokens = tokenize("(0 + 0) - 0 * 0 / 0");
+		let mut tokens = tokenize("(0 + 0) - 0 * 0 / 0%");
 		assert_eq!(tokens.next().unwrap(), Ok(Token::GroupStart));
 		assert_eq!(tokens.next().unwrap(), Ok(Token::Value(Decimal::ZERO)));
 		assert_eq!(tokens.next().unwrap(), Ok(Token::Operator(Operator::Add)));
@@ -105,6 +824,322 @@ mod tests {
 		assert_eq!(tokens.next().unwrap(), Ok(Token::Value(Decimal::ZERO)));
 		assert_eq!(tokens.next().unwrap(), Ok(Token::Operator(Operator::Div)));
 		assert_eq!(tokens.next().unwrap(), Ok(Token::Value(Decimal::ZERO)));
+		assert_eq!(
+			tokens.next().unwrap(),
+			Ok(Token::Operator(Operator::Percent))
+		);
+		assert!(tokens.next().is_none());
+	}
+
+	#[test]
+	fn tokenize_radix_prefixes() {
+		let mut tokens = tokenize("0b1010 * 0o10");
+		assert_eq!(tokens.next().unwrap(), Ok(Token::Value(Decimal::TEN)));
+		assert_eq!(tokens.next().unwrap(), Ok(Token::Operator(Operator::Mul)));
+		assert_eq!(tokens.next().unwrap(), Ok(Token::Value(Decimal::new(8, 0))));
+		assert!(tokens.next().is_none());
+	}
+
+	#[test]
+	fn tokenize_scientific() {
+		let mut tokens = tokenize("1e5-1.5E+3+2.5e-4");
+		assert_eq!(
+			tokens.next().unwrap(),
+			Ok(Token::Value(Decimal::new(100000, 0)))
+		);
+		assert_eq!(tokens.next().unwrap(), Ok(Token::Operator(Operator::Sub)));
+		assert_eq!(
+			tokens.next().unwrap(),
+			Ok(Token::Value(Decimal::new(1500, 0)))
+		);
+		assert_eq!(tokens.next().unwrap(), Ok(Token::Operator(Operator::Add)));
+		assert_eq!(
+			tokens.next().unwrap(),
+			Ok(Token::Value(Decimal::new(25, 5)))
+		);
+		assert!(tokens.next().is_none());
+	}
+
+	#[test]
+	fn tokenize_zero_exponent() {
+		for input in ["1e0", "1E+0", "1E-0", "1.0e0"] {
+			assert_eq!(
+				tokenize(input).collect::<Vec<_>>(),
+				[Ok(Token::Value(Decimal::ONE))],
+				"{input}"
+			);
+		}
+		assert_eq!(
+			tokenize("0e0").collect::<Vec<_>>(),
+			[Ok(Token::Value(Decimal::ZERO))]
+		);
+	}
+
+	#[test]
+	fn tokenize_constants() {
+		assert_eq!(
+			tokenize("pi e tau phi pie E").collect::<Vec<_>>(),
+			[
+				Ok(Token::Constant(ConstantKind::Pi)),
+				Ok(Token::Constant(ConstantKind::E)),
+				Ok(Token::Constant(ConstantKind::Tau)),
+				Ok(Token::Constant(ConstantKind::Phi)),
+				Ok(Token::Identifier("pie".to_string())),
+				Ok(Token::Identifier("E".to_string())),
+			]
+		);
+		assert_eq!(Token::Constant(ConstantKind::Tau).to_string(), "tau");
+	}
+
+	#[test]
+	fn tokenize_newlines() {
+		assert_eq!(
+			tokenize("1\n+\n2").collect::<Vec<_>>(),
+			[
+				Ok(Token::Value(Decimal::ONE)),
+				Ok(Token::Operator(Operator::Add)),
+				Ok(Token::Value(Decimal::TWO))
+			]
+		);
+		assert_eq!(tokenize("\n\r\n").count(), 0);
+	}
+
+	#[test]
+	fn line_tracking() {
+		let lines: Vec<_> = Tokenizer::with_line_tracking("1 +\n2\n\n* (3\r\n)")
+			.map(|token| token.unwrap().1.line)
+			.collect();
+		assert_eq!(lines, [0, 0, 1, 3, 3, 3, 4]);
+		// Spans are the same as without line tracking on the first line
+		assert!(Tokenizer::with_line_tracking("1 + 2").eq(Tokenizer::new("1 + 2")));
+		assert!(matches!(
+			Tokenizer::with_line_tracking("\n0b2").next(),
+			Some(Err((_, Span { line: 1, .. })))
+		));
+	}
+
+	#[test]
+	fn tokenize_exponent_marker_only_after_mantissa() {
+		// A hexadecimal `e` digit is not an exponent marker
+		let mut tokens = tokenize("0x1e+5");
+		assert_eq!(
+			tokens.next().unwrap(),
+			Ok(Token::Value(Decimal::new(30, 0)))
+		);
+		assert_eq!(tokens.next().unwrap(), Ok(Token::Operator(Operator::Add)));
+		assert_eq!(tokens.next().unwrap(), Ok(Token::Value(Decimal::new(5, 0))));
+		assert!(tokens.next().is_none());
+	}
+
+	#[test]
+	fn tokenize_abs() {
+		let mut tokens = tokenize("||1| - |2||");
+		assert_eq!(tokens.next().unwrap(), Ok(Token::AbsStart));
+		assert_eq!(tokens.next().unwrap(), Ok(Token::AbsStart));
+		assert_eq!(tokens.next().unwrap(), Ok(Token::Value(Decimal::ONE)));
+		assert_eq!(tokens.next().unwrap(), Ok(Token::AbsEnd));
+		assert_eq!(tokens.next().unwrap(), Ok(Token::Operator(Operator::Sub)));
+		assert_eq!(tokens.next().unwrap(), Ok(Token::AbsStart));
+		assert_eq!(tokens.next().unwrap(), Ok(Token::Value(Decimal::TWO)));
+		assert_eq!(tokens.next().unwrap(), Ok(Token::AbsEnd));
+		assert_eq!(tokens.next().unwrap(), Ok(Token::AbsEnd));
+		assert!(tokens.next().is_none());
+	}
+
+	#[test]
+	fn display_operator() {
+		assert_eq!(Operator::Add.to_string(), "+");
+		assert_eq!(Operator::Sub.to_string(), "-");
+		assert_eq!(Operator::Mul.to_string(), "*");
+		assert_eq!(Operator::Div.to_string(), "/");
+		assert_eq!(Operator::Pow.to_string(), "^");
+		assert_eq!(Operator::Percent.to_string(), "%");
+		assert_eq!(Operator::Fact.to_string(), "!");
+		assert_eq!(Operator::Eq.to_string(), "==");
+		assert_eq!(Operator::Ne.to_string(), "!=");
+		assert_eq!(Operator::Lt.to_string(), "<");
+		assert_eq!(Operator::Le.to_string(), "<=");
+		assert_eq!(Operator::Gt.to_string(), ">");
+		assert_eq!(Operator::Ge.to_string(), ">=");
+		assert_eq!(Operator::BitAnd.to_string(), "&");
+		assert_eq!(Operator::BitNot.to_string(), "~");
+	}
+
+	#[test]
+	fn display_token() {
+		assert_eq!(Token::Value(Decimal::new(-15, 1)).to_string(), "-1.5");
+		assert_eq!(Token::Identifier("x".to_string()).to_string(), "x");
+		assert_eq!(Token::Operator(Operator::Le).to_string(), "<=");
+		assert_eq!(Token::GroupStart.to_string(), "(");
+		assert_eq!(Token::GroupEnd.to_string(), ")");
+		assert_eq!(Token::AbsStart.to_string(), "|");
+		assert_eq!(Token::AbsEnd.to_string(), "|");
+		assert_eq!(Token::Let.to_string(), "let");
+		assert_eq!(Token::Assign.to_string(), "=");
+		assert_eq!(Token::Comma.to_string(), ",");
+	}
+
+	#[test]
+	fn tokenize_spans() {
+		let mut tokens = super::tokenize(" 12+ (0x3)", 0, false, false);
+		assert_eq!(
+			tokens.next().unwrap(),
+			Ok((Token::Value(Decimal::new(12, 0)), Span::new(1, 3)))
+		);
+		assert_eq!(
+			tokens.next().unwrap(),
+			Ok((Token::Operator(Operator::Add), Span::new(3, 4)))
+		);
+		assert_eq!(
+			tokens.next().unwrap(),
+			Ok((Token::GroupStart, Span::new(5, 6)))
+		);
+		assert_eq!(
+			tokens.next().unwrap(),
+			Ok((Token::Value(Decimal::new(3, 0)), Span::new(6, 9)))
+		);
+		assert_eq!(
+			tokens.next().unwrap(),
+			Ok((Token::GroupEnd, Span::new(9, 10)))
+		);
+		assert!(tokens.next().is_none());
+	}
+
+	#[test]
+	fn tokenize_error_span() {
+		let mut tokens = super::tokenize("1 + 1.2.3", 0, false, false);
+		tokens.next();
+		tokens.next();
+		assert!(matches!(
+			tokens.next().unwrap(),
+			Err((
+				_,
+				Span {
+					start: 4,
+					end: 9,
+					..
+				}
+			))
+		));
+	}
+
+	#[test]
+	fn tokenize_identifiers() {
+		let mut tokens = tokenize("ans * _ + |x_1|");
+		assert_eq!(
+			tokens.next().unwrap(),
+			Ok(Token::Identifier("ans".to_string()))
+		);
+		assert_eq!(tokens.next().unwrap(), Ok(Token::Operator(Operator::Mul)));
+		assert_eq!(
+			tokens.next().unwrap(),
+			Ok(Token::Identifier("_".to_string()))
+		);
+		assert_eq!(tokens.next().unwrap(), Ok(Token::Operator(Operator::Add)));
+		assert_eq!(tokens.next().unwrap(), Ok(Token::AbsStart));
+		assert_eq!(
+			tokens.next().unwrap(),
+			Ok(Token::Identifier("x_1".to_string()))
+		);
+		assert_eq!(tokens.next().unwrap(), Ok(Token::AbsEnd));
+		assert!(tokens.next().is_none());
+	}
+
+	#[test]
+	fn tokenize_assignment() {
+		let mut tokens = tokenize("let x=1");
+		assert_eq!(tokens.next().unwrap(), Ok(Token::Let));
+		assert_eq!(
+			tokens.next().unwrap(),
+			Ok(Token::Identifier("x".to_string()))
+		);
+		assert_eq!(tokens.next().unwrap(), Ok(Token::Assign));
+		assert_eq!(tokens.next().unwrap(), Ok(Token::Value(Decimal::ONE)));
+		assert!(tokens.next().is_none());
+	}
+
+	#[test]
+	fn tokenize_comma() {
+		let mut tokens = tokenize("1,2");
+		assert_eq!(tokens.next().unwrap(), Ok(Token::Value(Decimal::ONE)));
+		assert_eq!(tokens.next().unwrap(), Ok(Token::Comma));
+		assert_eq!(tokens.next().unwrap(), Ok(Token::Value(Decimal::TWO)));
+		assert!(tokens.next().is_none());
+	}
+
+	#[test]
+	fn tokenize_comparisons() {
+		let tokens = tokenize("1==2!=3<4<=5>6>=7 = = 8!")
+			.filter_map(|token| match token {
+				Ok(Token::Operator(operator)) => Some(Ok(operator)),
+				Ok(Token::Assign) => Some(Err(())),
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(
+			tokens,
+			[
+				Ok(Operator::Eq),
+				Ok(Operator::Ne),
+				Ok(Operator::Lt),
+				Ok(Operator::Le),
+				Ok(Operator::Gt),
+				Ok(Operator::Ge),
+				Err(()),
+				Err(()),
+				Ok(Operator::Fact),
+			]
+		);
+	}
+
+	#[test]
+	fn tokenize_bitwise() {
+		let mut tokens = tokenize("~1&2");
+		assert_eq!(
+			tokens.next().unwrap(),
+			Ok(Token::Operator(Operator::BitNot))
+		);
+		assert_eq!(tokens.next().unwrap(), Ok(Token::Value(Decimal::ONE)));
+		assert_eq!(
+			tokens.next().unwrap(),
+			Ok(Token::Operator(Operator::BitAnd))
+		);
+		assert_eq!(tokens.next().unwrap(), Ok(Token::Value(Decimal::TWO)));
+		assert!(tokens.next().is_none());
+		assert!(Operator::BitNot.is_prefix() && !Operator::Sub.is_prefix());
+	}
+
+	#[test]
+	fn tokenize_unicode_operators() {
+		let mut tokens = Tokenizer::new("2×3−4÷5");
+		assert_eq!(
+			tokens.next().unwrap(),
+			Ok((Token::Value(Decimal::TWO), Span::new(0, 1)))
+		);
+		assert_eq!(
+			tokens.next().unwrap(),
+			Ok((Token::Operator(Operator::Mul), Span::new(1, 3)))
+		);
+		assert_eq!(
+			tokens.next().unwrap(),
+			Ok((Token::Value(Decimal::new(3, 0)), Span::new(3, 4)))
+		);
+		assert_eq!(
+			tokens.next().unwrap(),
+			Ok((Token::Operator(Operator::Sub), Span::new(4, 7)))
+		);
+		assert_eq!(
+			tokens.next().unwrap(),
+			Ok((Token::Value(Decimal::new(4, 0)), Span::new(7, 8)))
+		);
+		assert_eq!(
+			tokens.next().unwrap(),
+			Ok((Token::Operator(Operator::Div), Span::new(8, 10)))
+		);
+		assert_eq!(
+			tokens.next().unwrap(),
+			Ok((Token::Value(Decimal::new(5, 0)), Span::new(10, 11)))
+		);
 		assert!(tokens.next().is_none());
 	}
 
@@ -115,4 +1150,126 @@ mod tests {
 			tokenize("1 + 1").collect::<Vec<_>>()
 		);
 	}
+
+	#[cfg(feature = "serde")]
+	#[test]
+	fn serde_round_trip() {
+		let tokens = [
+			Token::Value(Decimal::ONE),
+			Token::Operator(Operator::Add),
+			Token::Identifier("x".to_string()),
+		];
+		let json = serde_json::to_string(&tokens).unwrap();
+		assert_eq!(
+			json,
+			r#"[{"Value":"1"},{"Operator":"Add"},{"Identifier":"x"}]"#
+		);
+		let round_trip: Vec<Token> = serde_json::from_str(&json).unwrap();
+		assert_eq!(round_trip, tokens);
+	}
+
+	#[test]
+	fn precedence() {
+		assert!(Operator::Add.precedence() == Operator::Sub.precedence());
+		assert!(Operator::Mul.precedence() == Operator::Div.precedence());
+		assert!(Operator::Add.precedence() < Operator::Mul.precedence());
+		assert!(Operator::Mul.precedence() < Operator::Pow.precedence());
+		assert!(Operator::Pow.precedence() < Operator::Percent.precedence());
+		assert!(Operator::Percent.precedence() == Operator::Fact.precedence());
+	}
+
+	#[test]
+	fn associativity() {
+		for operator in [Operator::Add, Operator::Sub, Operator::Mul, Operator::Div] {
+			assert_eq!(operator.associativity(), Associativity::Left);
+		}
+		assert_eq!(Operator::Pow.associativity(), Associativity::Right);
+	}
+
+	#[test]
+	fn abs_after_postfix() {
+		let mut tokens = tokenize("|50%|");
+		assert_eq!(tokens.next().unwrap(), Ok(Token::AbsStart));
+		assert_eq!(
+			tokens.next().unwrap(),
+			Ok(Token::Value(Decimal::new(50, 0)))
+		);
+		assert_eq!(
+			tokens.next().unwrap(),
+			Ok(Token::Operator(Operator::Percent))
+		);
+		assert_eq!(tokens.next().unwrap(), Ok(Token::AbsEnd));
+		assert!(tokens.next().is_none());
+	}
+
+	#[test]
+	fn coefficient() {
+		let mut tokens = super::tokenize("2sqrt 1.5x", 0, false, false);
+		assert_eq!(
+			tokens.next().unwrap(),
+			Ok((Token::Value(Decimal::TWO), Span::new(0, 1)))
+		);
+		assert_eq!(
+			tokens.next().unwrap(),
+			Ok((Token::Identifier("sqrt".to_string()), Span::new(1, 5)))
+		);
+		assert_eq!(
+			tokens.next().unwrap(),
+			Ok((Token::Value(Decimal::new(15, 1)), Span::new(6, 9)))
+		);
+		assert_eq!(
+			tokens.next().unwrap(),
+			Ok((Token::Identifier("x".to_string()), Span::new(9, 10)))
+		);
+		assert!(tokens.next().is_none());
+		// Numbers with letters are not split
+		assert_eq!(
+			tokenize("1e5").collect::<Vec<_>>(),
+			[Ok(Token::Value(Decimal::new(100000, 0)))]
+		);
+		assert_eq!(
+			tokenize("0xff").collect::<Vec<_>>(),
+			[Ok(Token::Value(Decimal::new(255, 0)))]
+		);
+		assert!(tokenize("0xfg").next().unwrap().is_err());
+		// Out of range exponents are invalid numbers, not names
+		for input in ["1e29", "9e28", "1.5e29", "1e300", "1e-300"] {
+			let tokens = super::tokenize(input, 0, false, false).collect::<Vec<_>>();
+			assert!(
+				matches!(tokens[..], [Err((_, span))] if span == Span::new(0, input.len())),
+				"{input}"
+			);
+		}
+		assert_eq!(
+			tokenize("2e5x").collect::<Vec<_>>(),
+			[
+				Ok(Token::Value(Decimal::TWO)),
+				Ok(Token::Identifier("e5x".to_string()))
+			]
+		);
+	}
+
+	#[test]
+	fn named_tokenizer() {
+		struct Parser<'a> {
+			tokens: Tokenizer<'a>,
+		}
+
+		let mut parser = Parser {
+			tokens: Tokenizer::new("1 +"),
+		};
+		assert_eq!(
+			parser.tokens.next(),
+			Some(Ok((Token::Value(Decimal::ONE), Span::new(0, 1))))
+		);
+		assert_eq!(
+			parser.tokens.next(),
+			Some(Ok((Token::Operator(Operator::Add), Span::new(2, 3))))
+		);
+		assert_eq!(parser.tokens.next(), None);
+		assert_eq!(
+			Token::iter_from_str("1 + 2").count(),
+			Tokenizer::new("1 + 2").count()
+		);
+	}
 }
@@ -0,0 +1,321 @@
+use super::{
+	ast::Builder,
+	error::Error,
+	function,
+	tokenizer::{Operator, Span, Token, Tokenizer},
+	DEFAULT_MAX_DEPTH,
+};
+use crate::engine::Node;
+use alloc::{string::String, vec, vec::Vec};
+use core::mem;
+use rust_decimal::Decimal;
+
+/// Group opened but not closed yet, with the builder of the enclosing group.
+enum Group {
+	Parentheses(Builder),
+	Abs(Builder),
+	/// Function call with the number of arguments followed by a comma so far
+	/// and the depth of the deepest of them
+	Call {
+		name: String,
+		span: Span,
+		arguments: usize,
+		depth: usize,
+		outer: Builder,
+	},
+}
+
+/// Check the syntax of an expression without building a tree.
+/// Tokens are checked in order, tracking only open groups, whether
+/// the last token ended an operand and the shape of the tree, which
+/// consists of placeholder values, so that its depth is limited like in `parse`.
+/// An input is valid if and only if `parse` succeeds.
+pub fn validate(input: &str) -> Result<(), Error> {
+	validate_tokens(Tokenizer::new(input), |_| {})
+}
+
+/// Check the syntax of tokens like `validate`, calling `complete` with the end
+/// of every token after which the tokens so far form a complete expression.
+pub(super) fn validate_tokens(
+	tokens: Tokenizer,
+	mut complete: impl FnMut(usize),
+) -> Result<(), Error> {
+	let mut groups = Vec::new();
+	// Shape of the innermost open group
+	let mut builder = Builder::new();
+	let mut follows_operand = false;
+	// Operator still missing its operand, reported if the operand never comes
+	let mut pending_operator = None;
+	let mut follows_plus = false;
+	let mut tokens = tokens.peekable();
+	while let Some(token) = tokens.next() {
+		let (token, span) = token.map_err(|(error, span)| Error::Value(error, span))?;
+		// Unary plus may only be repeated with another operator in between
+		let after_plus = mem::take(&mut follows_plus);
+		// Groups are nested as deeply as `parse` allows
+		if matches!(token, Token::GroupStart | Token::AbsStart) && groups.len() == DEFAULT_MAX_DEPTH
+		{
+			return Err(Error::MaxDepthExceeded(DEFAULT_MAX_DEPTH));
+		}
+		match token {
+			Token::Value(value) if follows_operand => {
+				return Err(Error::UnexpectedNode {
+					node: Node::Value(value),
+					span,
+				})
+			}
+			Token::Identifier(name)
+				if matches!(tokens.peek(), Some(Ok((Token::GroupStart, _)))) =>
+			{
+				implicit_multiplication(&mut builder, follows_operand, span)?;
+				// The name alone is an identifier
+				if groups.is_empty() {
+					complete(span.end);
+				}
+				tokens.next();
+				groups.push(Group::Call {
+					name,
+					span,
+					arguments: 0,
+					depth: 0,
+					outer: mem::take(&mut builder),
+				});
+				follows_operand = false;
+			}
+			// Identifiers directly following an operand are implicitly multiplied
+			Token::Value(_) | Token::Constant(_) | Token::Identifier(_) => {
+				implicit_multiplication(&mut builder, follows_operand, span)?;
+				builder.add_node_with_depth(Node::default(), span, 0)?;
+				follows_operand = true;
+				pending_operator = None;
+			}
+			Token::Operator(operator) if operator.is_postfix() && follows_operand => {
+				builder.add_postfix(operator, span)?;
+			}
+			Token::Operator(operator) if operator.is_prefix() && !follows_operand => {
+				builder.add_operator(operator, span)?;
+				pending_operator = Some((operator, span));
+			}
+			Token::Operator(operator)
+				if !operator.is_postfix() && !operator.is_prefix() && follows_operand =>
+			{
+				builder.add_operator(operator, span)?;
+				follows_operand = false;
+				pending_operator = Some((operator, span));
+			}
+			// Unary minus
+			Token::Operator(Operator::Sub) => {
+				builder.add_operator(Operator::Sub, span)?;
+				pending_operator = Some((Operator::Sub, span));
+			}
+			Token::Operator(Operator::Add) if !after_plus => {
+				builder.add_operator(Operator::Add, span)?;
+				pending_operator = Some((Operator::Add, span));
+				follows_plus = true;
+			}
+			Token::Operator(operator) => {
+				return Err(Error::UnexpectedOperator { op: operator, span })
+			}
+			// Groups directly following an operand are implicitly multiplied
+			Token::GroupStart => {
+				implicit_multiplication(&mut builder, follows_operand, span)?;
+				groups.push(Group::Parentheses(mem::take(&mut builder)));
+				follows_operand = false;
+				pending_operator = None;
+			}
+			Token::AbsStart => {
+				groups.push(Group::Abs(mem::take(&mut builder)));
+				pending_operator = None;
+			}
+			Token::GroupEnd => {
+				let depth = match groups.pop() {
+					Some(Group::Parentheses(outer)) => {
+						end_operand(follows_operand, pending_operator)?;
+						let (_, depth) = builder.finish_with_depth()?;
+						builder = outer;
+						depth
+					}
+					Some(Group::Call {
+						name,
+						span,
+						arguments,
+						depth,
+						outer,
+					}) => {
+						// Calls without arguments are the only empty groups
+						let mut depth = depth;
+						if follows_operand || arguments > 0 {
+							end_operand(follows_operand, pending_operator)?;
+							depth = depth.max(builder.finish_with_depth()?.1);
+						}
+						// Arities of functions are only defined by building their calls
+						let arguments = arguments + usize::from(follows_operand);
+						function::call(name, vec![Node::Value(Decimal::ZERO); arguments], span)?;
+						builder = outer;
+						depth + 1
+					}
+					Some(Group::Abs(_)) | None => return Err(Error::UninitializedGroup),
+				};
+				builder.add_node_with_depth(Node::default(), span, depth)?;
+				follows_operand = true;
+			}
+			Token::AbsEnd => {
+				let depth = match groups.pop() {
+					Some(Group::Abs(outer)) => {
+						end_operand(follows_operand, pending_operator)?;
+						let (_, depth) = builder.finish_with_depth()?;
+						builder = outer;
+						depth + 1
+					}
+					_ => return Err(Error::UninitializedGroup),
+				};
+				builder.add_node_with_depth(Node::default(), span, depth)?;
+				follows_operand = true;
+			}
+			Token::Comma => match groups.last_mut() {
+				Some(Group::Call {
+					arguments, depth, ..
+				}) => {
+					end_operand(follows_operand, pending_operator)?;
+					*depth = (*depth).max(builder.finish_with_depth()?.1);
+					*arguments += 1;
+					follows_operand = false;
+					pending_operator = None;
+				}
+				_ => return Err(Error::UnexpectedComma(span)),
+			},
+			Token::Let | Token::Assign => return Err(Error::InvalidAssignment(span)),
+		}
+		if groups.is_empty() && follows_operand {
+			complete(span.end);
+		}
+	}
+	if !groups.is_empty() {
+		return Err(Error::UnterminatedGroup);
+	}
+	end_operand(follows_operand, pending_operator)?;
+	builder.finish().map(|_| ())
+}
+
+/// Multiply an operand directly following another one, like `parse` does.
+fn implicit_multiplication(
+	builder: &mut Builder,
+	follows_operand: bool,
+	span: Span,
+) -> Result<(), Error> {
+	if follows_operand {
+		builder.add_operator(Operator::Mul, Span::new(span.start, span.start))?;
+	}
+	Ok(())
+}
+
+/// Check that an operand ends where a group, an argument or the input ends.
+fn end_operand(
+	follows_operand: bool,
+	pending_operator: Option<(Operator, Span)>,
+) -> Result<(), Error> {
+	match pending_operator {
+		_ if follows_operand => Ok(()),
+		Some((operator, span)) => Err(Error::UnexpectedOperator { op: operator, span }),
+		None => Err(Error::Empty),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::validate;
+	use crate::parser::{parse, Error, Operator};
+
+	#[test]
+	fn agrees_with_parse() {
+		for input in [
+			"1",
+			"1 + 2 * 3",
+			"-1",
+			"--1 - -2",
+			"(1 + 2) * 3",
+			"((1))",
+			"2(3)(4)",
+			"2x + 3 sqrt(4)",
+			"|1 - 2| * |-3|",
+			"||1||",
+			"5!% + 3!!",
+			"max(1, 2, -3) + min(1, (2))",
+			"round(1.5, 0)",
+			"1 < 2 == 1",
+			"0x1F + 1e-3 + 1_000",
+			"",
+			" ",
+			"1 +",
+			"-",
+			"* 1",
+			"!1",
+			"1 2",
+			"(1) 2",
+			"x 2",
+			"1 ++ 2",
+			"+1",
+			"++1",
+			"1 + ++2",
+			"+-+1",
+			"+(+1)",
+			"~1 & ~~2",
+			"1 ~ 2",
+			"1~",
+			"~",
+			"1 & & 2",
+			"-~1",
+			"+",
+			"(+)",
+			"()",
+			"(-)",
+			"(1",
+			"1)",
+			"(1))",
+			"|1",
+			"(|1)|",
+			"1, 2",
+			"(1, 2)",
+			"max(|1, 2|)",
+			"max(1,)",
+			"max(, 1)",
+			"max()",
+			"max(1)",
+			"sqrt()",
+			"sqrt(1, 2)",
+			"foo(1)",
+			"let x = 1",
+			"x = 1",
+			"1.2.3",
+			"1 + 1.2.3",
+		] {
+			assert_eq!(
+				validate(input).is_ok(),
+				parse(input).is_ok(),
+				"{input:?}: {:?}",
+				validate(input)
+			);
+		}
+	}
+
+	#[test]
+	fn errors() {
+		assert!(matches!(validate(""), Err(Error::Empty)));
+		assert!(matches!(
+			validate("1 *"),
+			Err(Error::UnexpectedOperator {
+				op: Operator::Mul,
+				..
+			})
+		));
+		assert!(matches!(validate("(1"), Err(Error::UnterminatedGroup)));
+		assert!(matches!(validate("1)"), Err(Error::UninitializedGroup)));
+		assert!(matches!(validate("1, 2"), Err(Error::UnexpectedComma(_))));
+		assert!(matches!(validate("1 2"), Err(Error::UnexpectedNode { .. })));
+		assert!(matches!(
+			validate("sqrt(1, 2)"),
+			Err(Error::InvalidArgumentCount(..))
+		));
+		assert!(matches!(validate("foo()"), Err(Error::UnknownFunction(..))));
+	}
+}
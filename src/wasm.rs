@@ -0,0 +1,12 @@
+use alloc::string::ToString;
+use wasm_bindgen::prelude::*;
+
+/// Evaluate an arithmetic expression like `crate::evaluate`, for JavaScript.
+/// Returns the result, or the error message if the calculation fails, as a string.
+#[wasm_bindgen]
+pub fn evaluate(expr: &str) -> JsValue {
+	match crate::evaluate(expr) {
+		Ok(value) => JsValue::from_str(&value.to_string()),
+		Err(error) => JsValue::from_str(&error.to_string()),
+	}
+}
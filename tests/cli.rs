@@ -0,0 +1,220 @@
+use calculator::cli::{Options, Repl};
+use std::{
+	env, fs,
+	io::{Cursor, Write},
+	process::{Command, Stdio},
+};
+
+#[test]
+fn file() {
+	let path = env::temp_dir().join(format!("calculator-{}.txt", std::process::id()));
+	fs::write(
+		&path,
+		"# five expressions\n1 + 1\nlet x = 2 * 3\n\nx - 1\n1 / 0\nsqrt(16)\n",
+	)
+	.unwrap();
+	let output = Command::new(env!("CARGO_BIN_EXE_calculator"))
+		.arg("-f")
+		.arg(&path)
+		.output()
+		.unwrap();
+	fs::remove_file(&path).unwrap();
+	assert!(output.status.success());
+	assert_eq!(
+		String::from_utf8(output.stdout).unwrap(),
+		"line 2: 2\nline 3: x = 6\nline 5: 5\nline 6: Error: Division by zero\nline 7: 4\n"
+	);
+}
+
+/// Run `--check` on a file with the given contents.
+fn check(name: &str, contents: &str) -> std::process::Output {
+	let path = env::temp_dir().join(format!("calculator-{name}-{}.txt", std::process::id()));
+	fs::write(&path, contents).unwrap();
+	let output = Command::new(env!("CARGO_BIN_EXE_calculator"))
+		.args(["--check", "-f"])
+		.arg(&path)
+		.output()
+		.unwrap();
+	fs::remove_file(&path).unwrap();
+	output
+}
+
+#[test]
+fn check_valid() {
+	let output = check("valid", "# comment\nlet x = 1 / 0\nx * sqrt(-1)\n");
+	assert_eq!(output.status.code(), Some(0));
+	assert_eq!(
+		String::from_utf8(output.stdout).unwrap(),
+		"line 2: OK\nline 3: OK\n"
+	);
+}
+
+#[test]
+fn check_last_result() {
+	let output = check("last-result", "1 + 1\nans * 2\n");
+	assert_eq!(output.status.code(), Some(0));
+	assert_eq!(
+		String::from_utf8(output.stdout).unwrap(),
+		"line 1: OK\nline 2: OK\n"
+	);
+}
+
+#[test]
+fn check_invalid() {
+	let output = check("invalid", "1 + 1\n(2\n");
+	assert_eq!(output.status.code(), Some(1));
+	assert_eq!(
+		String::from_utf8(output.stdout).unwrap(),
+		"line 1: OK\nline 2: Error: Unterminated group\n"
+	);
+}
+
+#[test]
+fn precision() {
+	let path = env::temp_dir().join(format!("calculator-precision-{}.txt", std::process::id()));
+	fs::write(&path, "1 / 3\n").unwrap();
+	let flag = Command::new(env!("CARGO_BIN_EXE_calculator"))
+		.args(["--precision", "4", "-f"])
+		.arg(&path)
+		.env_remove("CALCULATOR_PRECISION")
+		.output()
+		.unwrap();
+	let var = Command::new(env!("CARGO_BIN_EXE_calculator"))
+		.arg("-f")
+		.arg(&path)
+		.env("CALCULATOR_PRECISION", "2")
+		.output()
+		.unwrap();
+	fs::remove_file(&path).unwrap();
+	assert_eq!(String::from_utf8(flag.stdout).unwrap(), "line 1: 0.3333\n");
+	assert_eq!(String::from_utf8(var.stdout).unwrap(), "line 1: 0.33\n");
+}
+
+#[test]
+fn repl() {
+	let input = Cursor::new(b"let x = 2\nx * 3\n".as_slice());
+	let mut output = Vec::new();
+	let mut repl = Repl::new(Options::default());
+	repl.run(input, &mut output).unwrap();
+	assert_eq!(String::from_utf8(output).unwrap(), "> x = 2\n\n> 6\n\n> ");
+}
+
+#[test]
+fn repl_error() {
+	let input = Cursor::new(b"1 +\n1 / 0\n2".as_slice());
+	let mut output = Vec::new();
+	Repl::new(Options::default())
+		.run(input, &mut output)
+		.unwrap();
+	assert_eq!(
+		String::from_utf8(output).unwrap(),
+		"> Error: '+' is not valid at column 3\n\n> Error: Division by zero\n\n> 2\n\n> "
+	);
+}
+
+#[test]
+fn repl_line_continuation() {
+	let input = Cursor::new(b"1 + \\\n2 * \\ \n3\n4 - \\\n".as_slice());
+	let mut output = Vec::new();
+	Repl::new(Options::default())
+		.run(input, &mut output)
+		.unwrap();
+	assert_eq!(
+		String::from_utf8(output).unwrap(),
+		"> ... ... 7\n\n> ... Error: '-' is not valid at column 3\n\n"
+	);
+}
+
+#[test]
+fn repl_invalid_utf8() {
+	let input = Cursor::new(b"1\n\xFF\n2\n".as_slice());
+	let mut output = Vec::new();
+	Repl::new(Options::default())
+		.run(input, &mut output)
+		.unwrap();
+	assert_eq!(
+		String::from_utf8(output).unwrap(),
+		"> 1\n\n> Error: stream did not contain valid UTF-8\n\n> 2\n\n> "
+	);
+}
+
+#[test]
+fn repl_prompt() {
+	let input = Cursor::new(b"1\n:set prompt \">>> \"\n2\n:set prompt $\n:foo\n".as_slice());
+	let mut output = Vec::new();
+	let mut repl = Repl {
+		prompt: "calc> ".to_string(),
+		..Repl::default()
+	};
+	repl.run(input, &mut output).unwrap();
+	assert_eq!(
+		String::from_utf8(output).unwrap(),
+		"calc> 1\n\ncalc> >>> 2\n\n>>> $Error: Unknown command :foo\n\n$"
+	);
+	assert_eq!(repl.prompt, "$");
+}
+
+#[test]
+fn repl_commands() {
+	let input = Cursor::new(b":help\nlet x = 1 / 3\n:precision 2\n:vars\n:quit\n1\n".as_slice());
+	let mut output = Vec::new();
+	Repl::new(Options::default())
+		.run(input, &mut output)
+		.unwrap();
+	let output = String::from_utf8(output).unwrap();
+	let (help, output) = output.split_once("\n> x = ").unwrap();
+	for keyword in ["Operators", "sqrt(x)", "max(x, y, ...)", ":precision N"] {
+		assert!(help.contains(keyword), "{keyword}");
+	}
+	assert_eq!(
+		output,
+		"0.3333333333333333333333333333\n\n> > x = 0.3333333333333333333333333333\n\
+		 ans = 0.3333333333333333333333333333\n\n> "
+	);
+}
+
+#[test]
+fn repl_json() {
+	let input = Cursor::new(b"1+1\n1+\n".as_slice());
+	let mut output = Vec::new();
+	let options = Options {
+		json: true,
+		..Options::default()
+	};
+	Repl::new(options).run(input, &mut output).unwrap();
+	assert_eq!(
+		String::from_utf8(output).unwrap(),
+		"{\"expression\":\"1+1\",\"result\":\"2\"}\n\
+		 {\"expression\":\"1+\",\"error\":\"Error: '+' is not valid at column 2\"}\n"
+	);
+}
+
+fn pipe(args: &[&str], input: &str) -> String {
+	let mut child = Command::new(env!("CARGO_BIN_EXE_calculator"))
+		.args(args)
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.spawn()
+		.unwrap();
+	child
+		.stdin
+		.take()
+		.unwrap()
+		.write_all(input.as_bytes())
+		.unwrap();
+	let output = child.wait_with_output().unwrap();
+	assert!(output.status.success());
+	String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn piped_input() {
+	assert_eq!(pipe(&[], "1+1\n2*3\n"), "2\n6\n");
+	assert_eq!(
+		pipe(&["--non-interactive"], "1+\n"),
+		"Error: '+' is not valid at column 2\n"
+	);
+	let interactive = pipe(&["--interactive"], "1+1\n");
+	assert!(interactive.starts_with("Type an arithmetic expression"));
+	assert!(interactive.contains("> 2\n\n"));
+}
@@ -0,0 +1,22 @@
+//! The parser and the engine only depend on `core` and `alloc`.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::string::ToString;
+use calculator::{engine::Context, parser, Decimal};
+
+#[test]
+fn parse_and_evaluate() {
+	let node = parser::parse("(1 + 2) * 3").unwrap();
+	assert_eq!(Decimal::try_from(node), Ok(Decimal::new(9, 0)));
+}
+
+#[test]
+fn parse_with_context() {
+	let mut context = Context::new();
+	context.set("x".to_string(), Decimal::TWO);
+	let node = parser::parse_with_context("x ^ 3", &context).unwrap();
+	assert_eq!(Decimal::try_from(node), Ok(Decimal::new(8, 0)));
+}
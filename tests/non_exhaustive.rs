@@ -0,0 +1,25 @@
+//! Downstream crates have to handle variants added in the future.
+//! Matching without a wildcard arm does not compile, see `NonExhaustive` in the crate root.
+
+use calculator::{engine, parse, Decimal, Expr, Node};
+
+#[test]
+fn wildcard_arms() {
+	let node = parse("1 + 2").unwrap();
+	let name = match &node {
+		Node::Value(_) => "value",
+		Node::Expr(expr) => match **expr {
+			Expr::Add(..) => "addition",
+			_ => "other",
+		},
+		_ => "unknown",
+	};
+	assert_eq!(name, "addition");
+
+	let error = Decimal::try_from(parse("1 / 0").unwrap()).unwrap_err();
+	let message = match error {
+		engine::Error::DivisionByZero => "division by zero",
+		_ => "other",
+	};
+	assert_eq!(message, "division by zero");
+}
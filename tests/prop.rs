@@ -0,0 +1,172 @@
+use calculator::{
+	parse,
+	parser::{validate, Error, DEFAULT_MAX_DEPTH},
+	Decimal,
+};
+use proptest::prelude::*;
+
+/// Expression tree evaluated independently of the calculator.
+#[derive(Debug, Clone)]
+enum Tree {
+	Value(i64),
+	Binary(Box<Tree>, char, Box<Tree>),
+}
+
+impl Tree {
+	/// Fully parenthesized expression string.
+	fn input(&self) -> String {
+		match self {
+			Tree::Value(value) => value.to_string(),
+			Tree::Binary(lhs, operator, rhs) => {
+				format!("({} {operator} {})", lhs.input(), rhs.input())
+			}
+		}
+	}
+
+	/// Exact value, if every division has no remainder.
+	fn evaluate(&self) -> Option<i64> {
+		match self {
+			Tree::Value(value) => Some(*value),
+			Tree::Binary(lhs, operator, rhs) => {
+				let (lhs, rhs) = (lhs.evaluate()?, rhs.evaluate()?);
+				match operator {
+					'+' => lhs.checked_add(rhs),
+					'-' => lhs.checked_sub(rhs),
+					'*' => lhs.checked_mul(rhs),
+					_ if rhs != 0 && lhs % rhs == 0 => lhs.checked_div(rhs),
+					_ => None,
+				}
+			}
+		}
+	}
+}
+
+fn tree() -> impl Strategy<Value = Tree> {
+	tree_of(-100..=100)
+}
+
+/// Trees of binary operators with leaves in the range.
+/// Negative leaves are parsed as negations of their absolute values.
+fn tree_of(leaves: std::ops::RangeInclusive<i64>) -> impl Strategy<Value = Tree> {
+	let leaf = leaves.prop_map(Tree::Value);
+	leaf.prop_recursive(4, 16, 2, |inner| {
+		(
+			inner.clone(),
+			prop::sample::select(&['+', '-', '*', '/'][..]),
+			inner,
+		)
+			.prop_map(|(lhs, operator, rhs)| Tree::Binary(lhs.into(), operator, rhs.into()))
+	})
+}
+
+fn calculate(input: &str) -> Decimal {
+	Decimal::try_from(parse(input).unwrap()).unwrap()
+}
+
+/// Evaluate a flat sum of products, with multiplication binding tighter.
+fn sum_of_products(first: i64, rest: &[(char, i64)]) -> i64 {
+	let mut sum = 0;
+	let mut product = first;
+	for &(operator, value) in rest {
+		match operator {
+			'*' => product *= value,
+			'+' => {
+				sum += product;
+				product = value;
+			}
+			_ => {
+				sum += product;
+				product = -value;
+			}
+		}
+	}
+	sum + product
+}
+
+proptest! {
+	#![proptest_config(ProptestConfig {
+		cases: 256,
+		max_shrink_iters: 1024,
+		..ProptestConfig::default()
+	})]
+
+	#[test]
+	fn matches_integer_arithmetic(tree in tree()) {
+		let expected = tree.evaluate();
+		prop_assume!(expected.is_some());
+		prop_assert_eq!(calculate(&tree.input()), Decimal::from(expected.unwrap()));
+	}
+
+	#[test]
+	fn counts(tree in tree_of(0..=100)) {
+		let node = parse(&tree.input()).unwrap();
+		prop_assert_eq!(node.count_values(), node.count_ops() + 1);
+	}
+
+	#[test]
+	fn precedence(
+		first in -100i64..=100,
+		rest in prop::collection::vec((prop::sample::select(&['+', '-', '*'][..]), 0i64..=100), 0..8),
+	) {
+		let mut input = first.to_string();
+		for (operator, value) in &rest {
+			input.push_str(&format!(" {operator} {value}"));
+		}
+		prop_assert_eq!(calculate(&input), Decimal::from(sum_of_products(first, &rest)));
+	}
+
+	#[test]
+	fn commutativity(a in tree(), b in tree()) {
+		for operator in ['+', '*'] {
+			let (a, b) = (a.input(), b.input());
+			let lhs = parse(&format!("{a} {operator} {b}")).unwrap();
+			let rhs = parse(&format!("{b} {operator} {a}")).unwrap();
+			prop_assert_eq!(Decimal::try_from(lhs).ok(), Decimal::try_from(rhs).ok());
+		}
+	}
+
+	#[test]
+	fn associativity_and_distributivity(a in -1000i64..=1000, b in -1000i64..=1000, c in -1000i64..=1000) {
+		prop_assert_eq!(calculate(&format!("({a} + {b}) + {c}")), calculate(&format!("{a} + ({b} + {c})")));
+		prop_assert_eq!(calculate(&format!("{a} * ({b} + {c})")), calculate(&format!("{a} * {b} + {a} * {c}")));
+	}
+
+	#[test]
+	fn nested_groups(value in -100i64..=100, depth in 1usize..64, abs in any::<bool>()) {
+		let (start, end) = if abs { ("|", "|") } else { ("(", ")") };
+		let input = format!("{}{value}{}", start.repeat(depth), end.repeat(depth));
+		let expected = if abs { value.abs() } else { value };
+		prop_assert_eq!(calculate(&input), Decimal::from(expected));
+		let unbalanced = format!("{}{value}{}", start.repeat(depth), end.repeat(depth - 1));
+		prop_assert!(parse(&unbalanced).is_err());
+	}
+
+	#[test]
+	fn deep_chains(
+		depth in 1usize..2000,
+		(start, end) in prop::sample::select(&[
+			("-", ""),
+			("~", ""),
+			("", "!"),
+			("", "^1"),
+			("", " - 1"),
+			("(", ")"),
+			("|", "|"),
+			("sqrt(", ")"),
+		][..]),
+	) {
+		// Deep trees are rejected instead of overflowing the stack
+		let input = format!("{}1{}", start.repeat(depth), end.repeat(depth));
+		match parse(&input) {
+			Ok(node) => {
+				prop_assert!(depth <= DEFAULT_MAX_DEPTH);
+				let _ = Decimal::try_from(node);
+			}
+			Err(error) => {
+				prop_assert!(depth > DEFAULT_MAX_DEPTH);
+				prop_assert_eq!(error, Error::MaxDepthExceeded(DEFAULT_MAX_DEPTH));
+			}
+		}
+		prop_assert_eq!(validate(&input).is_ok(), depth <= DEFAULT_MAX_DEPTH);
+	}
+}
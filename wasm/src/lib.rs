@@ -0,0 +1,5 @@
+//! WebAssembly module of the calculator, built with `wasm-pack build --target nodejs`.
+//! The library only exports its functions with the `wasm` feature,
+//! since it cannot be a `cdylib` without the standard library.
+
+pub use calculator::wasm::evaluate;
@@ -0,0 +1,6 @@
+// Calls the package built by `wasm-pack build --target nodejs` from Node.js
+const assert = require("node:assert");
+const { evaluate } = require("../pkg");
+
+assert.strictEqual(evaluate("1 + 2"), "3");
+assert.strictEqual(evaluate("1 / 0"), "Error: Division by zero");